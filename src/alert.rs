@@ -0,0 +1,117 @@
+use std::{
+    collections::HashMap,
+    process::{Child, Command, Stdio},
+};
+
+use crate::{
+    Drive,
+    severity::{Severity, Thresholds},
+};
+
+/// A mounted filesystem whose usage got worse since the previous check.
+struct Crossing {
+    device: String,
+    mountpoint: String,
+    percent: f64,
+    severity: Severity,
+}
+
+/// What to do when a filesystem crosses a usage threshold: run the
+/// `--on-threshold` command, send a desktop notification, or both.
+pub struct Alerts {
+    command: Option<String>,
+    notify: bool,
+    /// Severity of each mountpoint as of the previous check
+    last: HashMap<String, Severity>,
+    /// Hooks that were started and not yet reaped
+    running: Vec<Child>,
+}
+
+impl Alerts {
+    pub fn new(command: Option<String>, notify: bool) -> Alerts {
+        Alerts {
+            command,
+            notify,
+            last: HashMap::new(),
+            running: Vec::new(),
+        }
+    }
+
+    /// Fires the hooks for every filesystem that is now warning or critical
+    /// and was healthier at the previous check. The first check compares
+    /// against all-healthy, so a one-shot run alerts on everything over a limit.
+    pub fn check(&mut self, drives: &[Drive], thresholds: &Thresholds) {
+        if self.command.is_none() && !self.notify {
+            return;
+        }
+        self.running
+            .retain_mut(|child| !matches!(child.try_wait(), Ok(Some(_))));
+
+        for crossing in self.crossings(drives, thresholds) {
+            if let Some(command) = &self.command {
+                match Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .env("PBLK_DEVICE", &crossing.device)
+                    .env("PBLK_MOUNTPOINT", &crossing.mountpoint)
+                    .env("PBLK_PERCENT", format!("{:.1}", crossing.percent))
+                    .env("PBLK_SEVERITY", crossing.severity.name())
+                    .stdin(Stdio::null())
+                    .spawn()
+                {
+                    Ok(child) => self.running.push(child),
+                    Err(e) => warn!("pblk: cannot run --on-threshold command: {}", e),
+                }
+            }
+            if self.notify {
+                let urgency = match crossing.severity {
+                    Severity::Critical => "critical",
+                    _ => "normal",
+                };
+                match Command::new("notify-send")
+                    .arg(format!("--urgency={}", urgency))
+                    .arg(format!("{} is {:.0}% full", crossing.mountpoint, crossing.percent))
+                    .arg(format!(
+                        "{} crossed the {} threshold",
+                        crossing.device,
+                        crossing.severity.name()
+                    ))
+                    .stdin(Stdio::null())
+                    .spawn()
+                {
+                    Ok(child) => self.running.push(child),
+                    Err(e) => warn!("pblk: cannot send a notification with notify-send: {}", e),
+                }
+            }
+        }
+    }
+
+    fn crossings(&mut self, drives: &[Drive], thresholds: &Thresholds) -> Vec<Crossing> {
+        let mut crossings = Vec::new();
+        for partition in drives.iter().flat_map(|d| &d.partitions) {
+            let (Some(mountpoint), Some(used)) = (&partition.mountpoint, partition.used_bytes()) else {
+                continue;
+            };
+            let total = partition.size * 512;
+            let severity = thresholds.classify(Some(mountpoint), partition.fstype.as_deref(), used, total);
+            let previous = self.last.insert(mountpoint.clone(), severity).unwrap_or(Severity::Ok);
+            if severity > previous {
+                crossings.push(Crossing {
+                    device: partition.dev_name().to_string(),
+                    mountpoint: mountpoint.clone(),
+                    percent: used as f64 / total.max(1) as f64 * 100.0,
+                    severity,
+                });
+            }
+        }
+        crossings
+    }
+
+    /// Waits for hooks that are still running, so a one-shot run does not
+    /// exit before they finish.
+    pub fn wait(&mut self) {
+        for mut child in self.running.drain(..) {
+            let _ = child.wait();
+        }
+    }
+}
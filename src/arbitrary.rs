@@ -0,0 +1,96 @@
+//! Random drives for property tests: sizes from empty to the largest the
+//! kernel can report, partitions overlapping or past the end of the disk,
+//! usage larger than the filesystem and labels of every width.
+
+use crate::{Drive, Inodes, Mount, Partition};
+
+/// Cases each property is checked against. The seed is fixed so a failure
+/// reproduces; the case number is in the assertion message.
+pub const CASES: u64 = 2000;
+
+/// xorshift64*, plenty for spreading test inputs.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(case: u64) -> Rng {
+        Rng(case.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform in `0..bound`, 0 when `bound` is 0.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next().checked_rem(bound).unwrap_or(0)
+    }
+
+    pub fn chance(&mut self, one_in: u64) -> bool {
+        self.below(one_in) == 0
+    }
+
+    pub fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len() as u64) as usize]
+    }
+
+    /// Mostly ordinary quantities with the edges mixed in: 0, 1, the
+    /// largest values and everything in between on a log scale.
+    pub fn quantity(&mut self) -> u64 {
+        match self.below(8) {
+            0 => 0,
+            1 => 1,
+            2 => u64::MAX - self.below(2),
+            3 => u64::MAX / 512 + self.below(2),
+            _ => self.next() >> self.below(64),
+        }
+    }
+}
+
+const NAMES: [&str; 6] = [
+    "sda1",
+    "nvme0n1p12",
+    "データ",
+    "📦backup",
+    "cafe\u{301}",
+    "a-very-long-partition-name",
+];
+
+/// A drive with up to eight partitions and devices stacked on them.
+pub fn drive(rng: &mut Rng) -> Drive {
+    let count = rng.below(9);
+    let mut depth = 0;
+    let partitions = (0..count)
+        .map(|i| {
+            // Pre-order: each row nests at most one level below the one before
+            depth = match i {
+                0 => 0,
+                _ => rng.below(depth as u64 + 2) as usize,
+            };
+            let mount = Mount::new(
+                rng.pick(&["/", "/home", "/データ"]),
+                rng.pick(&["ext4", "btrfs", "iso9660"]),
+                &[],
+            );
+            let mounted = rng.chance(2).then_some(&mount);
+            let mut partition = Partition::new(format!("sda/{}", rng.pick(&NAMES)), rng.quantity(), mounted);
+            partition.depth = depth;
+            partition.start = rng.chance(3).then(|| rng.quantity());
+            if mounted.is_some() {
+                partition.used = rng
+                    .chance(5)
+                    .then(|| rng.quantity())
+                    .or(Some(rng.below(partition.size + 1) * 512));
+                partition.free = rng.chance(2).then(|| rng.quantity());
+                partition.inodes = rng.chance(2).then(|| Inodes {
+                    used: rng.quantity(),
+                    total: rng.quantity(),
+                });
+            }
+            partition
+        })
+        .collect();
+    Drive::new("sda", rng.quantity(), partitions)
+}
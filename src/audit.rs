@@ -0,0 +1,198 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+};
+
+use colored::*;
+
+use crate::{
+    Drive,
+    clock::Clock,
+    json, layout,
+    severity::{self, Severity, Thresholds},
+};
+
+/// One check that fired during a run.
+pub struct Problem {
+    /// Stable identifier such as `usage.critical`
+    pub check: &'static str,
+    pub device: String,
+    pub mountpoint: Option<String>,
+    pub value: f64,
+}
+
+impl Problem {
+    /// How this instance is named in `acknowledged`, e.g. `usage.warning:/backup`.
+    pub fn id(&self) -> String {
+        format!("{}:{}", self.check, self.mountpoint.as_deref().unwrap_or(&self.device))
+    }
+
+    /// Listed in `acknowledged`, by mountpoint or by device name.
+    pub fn acknowledged(&self, acknowledged: &[String]) -> bool {
+        let by_device = format!("{}:{}", self.check, self.device);
+        acknowledged.iter().any(|id| *id == self.id() || *id == by_device)
+    }
+}
+
+/// Exit status for `--silent`, after the convention of `pblk pct`: 0
+/// without open problems, 2 when one is critical and 1 otherwise.
+pub fn exit_status(problems: &[Problem], acknowledged: &[String]) -> i32 {
+    problems
+        .iter()
+        .filter(|p| !p.acknowledged(acknowledged))
+        .map(|p| if p.check.ends_with(".critical") { 2 } else { 1 })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Collects the problems the display flags: usage and inode thresholds,
+/// low space on system mounts, unresponsive mounts, failed multipath
+/// paths, partitions not aligned to 1 MiB and, after --fs-health, dirty
+/// filesystems and those with errors recorded, and after --smart drives
+/// that expect to fail.
+pub fn detect(drives: &[Drive], thresholds: &Thresholds) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    for drive in drives {
+        if let Some(paths) = &drive.multipath {
+            let down = paths.iter().filter(|p| !p.up()).count();
+            if down > 0 {
+                problems.push(Problem {
+                    check: "multipath.path_down",
+                    device: drive.name.clone(),
+                    mountpoint: None,
+                    value: down as f64,
+                });
+            }
+        }
+        #[cfg(feature = "smart")]
+        if drive.smart.as_ref().is_some_and(|health| !health.passed) {
+            problems.push(Problem {
+                check: "smart.critical",
+                device: drive.name.clone(),
+                mountpoint: None,
+                value: 1.0,
+            });
+        }
+        for partition in layout::misaligned(drive) {
+            problems.push(Problem {
+                check: "partition.misaligned",
+                device: partition.dev_name().to_string(),
+                mountpoint: None,
+                value: partition.start.unwrap_or(0) as f64,
+            });
+        }
+
+        for partition in &drive.partitions {
+            let mountpoint = partition.mountpoint.as_deref();
+            let fstype = partition.fstype.as_deref();
+            let mut push = |check, value| {
+                problems.push(Problem {
+                    check,
+                    device: partition.dev_name().to_string(),
+                    mountpoint: partition.mountpoint.clone(),
+                    value,
+                })
+            };
+
+            if partition.stalled {
+                push("mount.unresponsive", 1.0);
+            }
+            if let Some(health) = &partition.fs_health {
+                if health.errors > 0 {
+                    push("filesystem.errors", health.errors as f64);
+                } else if health.dirty {
+                    push("filesystem.dirty", 1.0);
+                }
+            }
+            if let Some(used) = partition.used_bytes() {
+                let total = partition.size * 512;
+                let percent = used as f64 / total.max(1) as f64 * 100.0;
+                match thresholds.classify(mountpoint, fstype, used, total) {
+                    Severity::Critical => push("usage.critical", percent),
+                    Severity::Warning => push("usage.warning", percent),
+                    Severity::Ok => {}
+                }
+            }
+            if let Some(inodes) = &partition.inodes {
+                let ratio = inodes.used as f64 / inodes.total as f64;
+                match thresholds.classify_ratio(mountpoint, fstype, ratio) {
+                    Severity::Critical => push("inodes.critical", ratio * 100.0),
+                    Severity::Warning => push("inodes.warning", ratio * 100.0),
+                    Severity::Ok => {}
+                }
+            }
+        }
+    }
+
+    for (mountpoint, free) in severity::system_space_warnings(drives) {
+        let device = drives
+            .iter()
+            .flat_map(|d| &d.partitions)
+            .find(|p| p.mountpoint.as_deref() == Some(mountpoint))
+            .map(|p| p.dev_name().to_string())
+            .unwrap_or_default();
+        problems.push(Problem {
+            check: "space.system_reserve",
+            device,
+            mountpoint: Some(mountpoint.to_string()),
+            value: free as f64,
+        });
+    }
+    problems
+}
+
+/// Prints the problems nobody acknowledged, one per line with the id that
+/// acknowledges it.
+pub fn print(problems: &[Problem], acknowledged: &[String]) {
+    let open: Vec<&Problem> = problems.iter().filter(|p| !p.acknowledged(acknowledged)).collect();
+    let hidden = problems.len() - open.len();
+    if open.is_empty() {
+        println!("{}", "No problems found".green());
+    }
+    for problem in open {
+        let id = match problem.check.ends_with(".critical") {
+            true => problem.id().red().bold(),
+            false => problem.id().yellow(),
+        };
+        let device = match &problem.mountpoint {
+            Some(_) => format!(" {}", format!("on {}", problem.device).dimmed()),
+            None => String::new(),
+        };
+        println!("{}{} {}", id, device, (problem.value * 100.0).round() / 100.0);
+    }
+    if hidden > 0 {
+        println!(
+            "{}",
+            format!("{} acknowledged in the config not shown", hidden).dimmed()
+        );
+    }
+}
+
+/// Appends one JSON object per problem to `path`, creating it if needed.
+/// Acknowledged problems are logged too, marked as such.
+pub fn append(path: &Path, problems: &[Problem], acknowledged: &[String], clock: &dyn Clock) -> io::Result<()> {
+    if problems.is_empty() {
+        return Ok(());
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let ts = clock.now();
+
+    let mut out = String::new();
+    for problem in problems {
+        out.push_str(&format!(
+            "{{\"ts\":{},\"check\":{},\"device\":{},\"mountpoint\":{},\"value\":{},\"acknowledged\":{}}}\n",
+            ts,
+            json::string(problem.check),
+            json::string(&problem.device),
+            json::optional(problem.mountpoint.as_deref()),
+            (problem.value * 100.0).round() / 100.0,
+            problem.acknowledged(acknowledged)
+        ));
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(out.as_bytes())
+}
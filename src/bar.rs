@@ -0,0 +1,300 @@
+//! Layout of the partition bar drawn under each drive header.
+
+use std::io;
+
+use crate::{Drive, PALETTE, Partition, PrettyblkError, glyph, text, theme};
+
+/// Splits `width` cells between segments proportionally to `sizes` using
+/// the largest-remainder method, so the widths always add up to exactly
+/// `width`. Every segment of at least `min` gets at least one cell as long
+/// as there are enough cells to go around; the cells for that come out of
+/// the widest segments.
+pub fn allocate(sizes: &[u64], width: usize, min: u64) -> Vec<usize> {
+    let total: u128 = sizes.iter().map(|&s| s as u128).sum();
+    if total == 0 {
+        let mut cells = vec![0; sizes.len()];
+        if let Some(last) = cells.last_mut() {
+            *last = width;
+        }
+        return cells;
+    }
+
+    // Exact shares as integer quotients and remainders, avoiding float drift
+    let shares: Vec<(usize, u128)> = sizes
+        .iter()
+        .map(|&s| {
+            let scaled = s as u128 * width as u128;
+            ((scaled / total) as usize, scaled % total)
+        })
+        .collect();
+    let mut cells: Vec<usize> = shares.iter().map(|&(quotient, _)| quotient).collect();
+
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by(|&a, &b| shares[b].1.cmp(&shares[a].1).then(a.cmp(&b)));
+    let assigned: usize = cells.iter().sum();
+    for &i in order.iter().take(width - assigned) {
+        cells[i] += 1;
+    }
+
+    let visible = |i: usize| sizes[i] > 0 && sizes[i] >= min;
+    if (0..sizes.len()).filter(|&i| visible(i)).count() <= width {
+        for i in 0..sizes.len() {
+            if !visible(i) || cells[i] > 0 {
+                continue;
+            }
+            // Take from whichever segment can best spare a cell
+            let spare = |j: usize| cells[j].saturating_sub(visible(j) as usize);
+            let donor = (0..cells.len()).max_by_key(|&j| (spare(j), usize::MAX - j)).unwrap();
+            cells[donor] -= 1;
+            cells[i] = 1;
+        }
+    }
+    cells
+}
+
+/// One segment of a drive's partition bar, measured in terminal cells, so a
+/// GUI or web front-end can draw the same layout at any scale.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Segment {
+    /// Cells before this segment
+    pub offset: usize,
+    pub width: usize,
+    /// Device the segment stands for, `None` for space outside any partition,
+    /// which is drawn blank
+    pub device: Option<String>,
+    /// Index into [`PALETTE`], `None` for space outside any partition
+    pub palette: Option<usize>,
+    /// Color of the segment as RGB
+    pub rgb: Option<(u8, u8, u8)>,
+    /// Fill character of the terminal rendering
+    pub symbol: &'static str,
+    /// Text centered in the segment; only set when it fits with a cell to
+    /// spare on each side
+    pub label: Option<String>,
+}
+
+impl Segment {
+    /// The segment as terminal text, without color, exactly `width` cells
+    /// wide. A label that does not fit is left out.
+    pub fn render(&self) -> String {
+        let room = self
+            .label
+            .as_ref()
+            .and_then(|label| self.width.checked_sub(text::width(label) + 2));
+        let (Some(label), Some(room)) = (&self.label, room) else {
+            return self.symbol.repeat(self.width);
+        };
+        let left = room / 2;
+        let right = room - left;
+        format!("{} {} {}", self.symbol.repeat(left), label, self.symbol.repeat(right))
+    }
+}
+
+/// Unpartitioned space up to this many sectors between partitions is left
+/// out of the bar; 1 MiB alignment leaves such gaps everywhere.
+const ALIGNMENT_GAP: u64 = 2048;
+
+/// A partition with its index in table order and its palette slot.
+type Placed<'a> = (usize, &'a Partition, usize);
+
+/// Segments of the partition bar of `drive` drawn `width` cells wide: one
+/// per partition in on-disk order, with unpartitioned gaps between them and
+/// the space after the last one as segments without a device. Stacked
+/// devices live inside their partition and get no segment of their own.
+/// Drives whose partitions do not all know their start keep table order.
+/// A drive whose size could not be read is drawn as if it were empty; see
+/// [`try_geometry`] to tell that apart.
+pub fn geometry(drive: &Drive, width: usize) -> Vec<Segment> {
+    let mut top: Vec<(usize, &Partition, usize)> = drive
+        .partitions
+        .iter()
+        .zip(drive.color_slots())
+        .filter(|(p, _)| p.depth == 0)
+        .enumerate()
+        .map(|(i, (p, slot))| (i, p, slot))
+        .collect();
+    let placed = top.iter().all(|(_, p, _)| p.start.is_some());
+    if placed {
+        top.sort_by_key(|(_, p, _)| p.start);
+    }
+
+    // A partition, or free space when `None`
+    let mut pieces: Vec<(Option<Placed>, u64)> = Vec::new();
+    let mut cursor = 0;
+    for &(i, partition, slot) in &top {
+        if let Some(start) = partition.start.filter(|_| placed) {
+            if start.saturating_sub(cursor) > ALIGNMENT_GAP {
+                pieces.push((None, start - cursor));
+            }
+            cursor = cursor.max(start);
+        }
+        pieces.push((Some((i, partition, slot)), partition.size));
+        // A corrupt table can place partitions anywhere, even past 2^64
+        cursor = cursor.saturating_add(partition.size);
+    }
+    let partitioned = pieces.iter().map(|(_, sectors)| *sectors).fold(0, u64::saturating_add);
+    pieces.push((None, drive.size.saturating_sub(partitioned)));
+
+    let sizes: Vec<u64> = pieces.iter().map(|(_, sectors)| *sectors).collect();
+    // 1 MiB keeps BIOS boot partitions visible but not alignment gaps
+    let cells = allocate(&sizes, width, 2048);
+
+    let mut offset = 0;
+    let symbols = glyph::glyphs().segments;
+    let mut segments = Vec::with_capacity(cells.len());
+    for ((piece, _), &cells) in pieces.iter().zip(&cells) {
+        let segment = match piece {
+            Some((i, partition, slot)) => {
+                let label = text::isolate(partition.dev_name()).into_owned();
+                let fits = cells >= text::width(&label) + 4;
+                Segment {
+                    offset,
+                    width: cells,
+                    device: Some(partition.dev_name().to_string()),
+                    palette: Some(*slot),
+                    rgb: Some(theme::rgb(PALETTE[*slot])),
+                    symbol: symbols[i % symbols.len()],
+                    label: fits.then_some(label),
+                }
+            }
+            None => Segment {
+                offset,
+                width: cells,
+                device: None,
+                palette: None,
+                rgb: None,
+                symbol: " ",
+                label: None,
+            },
+        };
+        offset += cells;
+        segments.push(segment);
+    }
+    segments
+}
+
+/// Like [`geometry`], but fails for a drive whose size could not be read,
+/// whose bar would be made up, and for a bar with no room at all.
+pub fn try_geometry(drive: &Drive, width: usize) -> Result<Vec<Segment>, PrettyblkError> {
+    let error = |source| PrettyblkError::Render {
+        drive: drive.name.clone(),
+        source,
+    };
+    if let Some(size_error) = drive.size_error() {
+        return Err(error(io::Error::other(size_error.chain())));
+    }
+    if width == 0 {
+        return Err(error(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "no room for the bar",
+        )));
+    }
+    Ok(geometry(drive, width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrary::{self, Rng};
+
+    #[test]
+    fn allocation_fills_the_width_exactly() {
+        for case in 0..arbitrary::CASES {
+            let mut rng = Rng::new(case);
+            let sizes: Vec<u64> = (0..rng.below(12) + 1).map(|_| rng.quantity()).collect();
+            let width = rng.below(300) as usize;
+            let min = rng.quantity();
+
+            let cells = allocate(&sizes, width, min);
+            assert_eq!(cells.len(), sizes.len(), "case {}", case);
+            assert_eq!(
+                cells.iter().sum::<usize>(),
+                width,
+                "case {}: {:?} in {}",
+                case,
+                sizes,
+                width
+            );
+            let visible = |size: u64| size > 0 && size >= min;
+            if sizes.iter().filter(|&&size| visible(size)).count() <= width {
+                for (&size, &cells) in sizes.iter().zip(&cells) {
+                    assert!(
+                        !visible(size) || cells > 0,
+                        "case {}: {:?} got {:?}",
+                        case,
+                        sizes,
+                        cells
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn segments_tile_the_bar_for_any_drive() {
+        for case in 0..arbitrary::CASES {
+            let mut rng = Rng::new(case);
+            let drive = arbitrary::drive(&mut rng);
+            let width = rng.below(300) as usize;
+
+            let mut offset = 0;
+            for segment in geometry(&drive, width) {
+                assert_eq!(segment.offset, offset, "case {}", case);
+                assert_eq!(
+                    text::width(&segment.render()),
+                    segment.width,
+                    "case {}: {:?}",
+                    case,
+                    segment
+                );
+                offset += segment.width;
+            }
+            assert_eq!(offset, width, "case {}", case);
+        }
+    }
+
+    #[test]
+    fn labels_that_do_not_fit_are_left_out() {
+        let segment = Segment {
+            offset: 0,
+            width: 3,
+            device: None,
+            palette: None,
+            rgb: None,
+            symbol: "#",
+            label: Some("sda1".to_string()),
+        };
+        assert_eq!(segment.render(), "###");
+    }
+
+    #[test]
+    fn segments_follow_the_disk_with_gaps() {
+        // sda2 comes first on disk, then a 100 MiB hole before sda1
+        let mut second = Partition::new("sda/sda2".to_string(), 204_800, None);
+        second.start = Some(2048);
+        let mut first = Partition::new("sda/sda1".to_string(), 409_600, None);
+        first.start = Some(411_648);
+        let drive = Drive::new("sda", 1_024_000, vec![first, second]);
+
+        let segments = geometry(&drive, 20);
+        let layout: Vec<(Option<&str>, usize)> = segments
+            .iter()
+            .map(|segment| (segment.device.as_deref(), segment.width))
+            .collect();
+        assert_eq!(layout, [(Some("sda2"), 4), (None, 4), (Some("sda1"), 8), (None, 4)]);
+    }
+
+    #[test]
+    fn drives_of_unknown_size_are_not_laid_out() {
+        let mut drive = Drive::new("sda", 0, Vec::new());
+        drive.size_error = Some(PrettyblkError::Sysfs {
+            path: "/sys/block/sda/size".into(),
+            source: io::Error::from(io::ErrorKind::PermissionDenied),
+        });
+        let error = try_geometry(&drive, 20).unwrap_err();
+        assert_eq!(
+            error.chain(),
+            "cannot lay out the bar of sda: cannot read /sys/block/sda/size: permission denied"
+        );
+    }
+}
@@ -0,0 +1,95 @@
+//! Where a drive physically sits: a slot of a SCSI enclosure (SES) or a
+//! PCIe hotplug slot for NVMe, and the LED that helps find it.
+
+use std::{
+    fmt,
+    fs::{canonicalize, read_dir, read_to_string, write},
+    io,
+    path::{Path, PathBuf},
+};
+
+/// The bay of a drive, as named by the enclosure or the PCIe slot.
+pub struct Bay {
+    /// SCSI address of the enclosure; `None` for a PCIe slot
+    pub enclosure: Option<String>,
+    pub slot: String,
+    /// The attribute that turns the locate LED on and off, where there is one
+    locate: Option<PathBuf>,
+}
+
+impl fmt::Display for Bay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.enclosure {
+            Some(enclosure) => write!(f, "{} of enclosure {}", self.slot, enclosure),
+            None => write!(f, "PCIe slot {}", self.slot),
+        }
+    }
+}
+
+fn entries(path: &Path) -> impl Iterator<Item = PathBuf> {
+    read_dir(path)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// The enclosure slot whose `device` link points at the drive's SCSI device.
+fn enclosure_slot(name: &str) -> Option<Bay> {
+    let device = canonicalize(format!("/sys/block/{}/device", name)).ok()?;
+    entries(Path::new("/sys/class/enclosure")).find_map(|enclosure| {
+        let slot = entries(&enclosure).find(|slot| canonicalize(slot.join("device")).is_ok_and(|d| d == device))?;
+        let locate = slot.join("locate");
+        Some(Bay {
+            enclosure: Some(file_name(&enclosure)),
+            slot: file_name(&slot),
+            locate: locate.exists().then_some(locate),
+        })
+    })
+}
+
+/// The hotplug slot of an NVMe drive's PCIe function. Slots list their
+/// address without the function, e.g. `0000:3b:00` for `0000:3b:00.0`.
+fn pcie_slot(name: &str) -> Option<Bay> {
+    let function = canonicalize(format!("/sys/block/{}/device/device", name)).ok()?;
+    let function_name = file_name(&function);
+    let (address, _) = function_name.rsplit_once('.')?;
+    let slot = entries(Path::new("/sys/bus/pci/slots"))
+        .find(|slot| read_to_string(slot.join("address")).is_ok_and(|a| a.trim() == address))?;
+    // Native PCIe Enclosure Management drives a locate LED of its own;
+    // otherwise the slot's attention indicator serves the same purpose
+    let npem = entries(&function.join("leds"))
+        .find(|led| file_name(led).ends_with(":enclosure:locate"))
+        .map(|led| led.join("brightness"));
+    let attention = slot.join("attention");
+    Some(Bay {
+        enclosure: None,
+        slot: file_name(&slot),
+        locate: npem.or_else(|| attention.exists().then_some(attention)),
+    })
+}
+
+/// The bay of the whole disk `/sys/block/<name>`, if sysfs knows it.
+pub fn find(name: &str) -> Option<Bay> {
+    match name.starts_with("nvme") {
+        true => pcie_slot(name),
+        false => enclosure_slot(name),
+    }
+}
+
+/// Turns the locate LED of `bay` on or off.
+pub fn locate(bay: &Bay, on: bool) -> io::Result<()> {
+    let Some(path) = &bay.locate else {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} has no locate LED", bay),
+        ));
+    };
+    write(path, if on { "1" } else { "0" })
+}
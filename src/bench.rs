@@ -0,0 +1,153 @@
+//! A short read-only benchmark of a block device: sequential reads of
+//! 1 MiB and random reads of 4 KiB, each for a few seconds, bypassing the
+//! page cache so the numbers are the device's and not memory's.
+
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// How long each of the two passes reads for.
+const DURATION: Duration = Duration::from_secs(3);
+
+const SEQUENTIAL_BLOCK: usize = 1 << 20;
+const RANDOM_BLOCK: usize = 4096;
+
+/// O_DIRECT needs buffers and offsets aligned to the logical block size,
+/// which is at most 4 KiB on the devices in use.
+const ALIGNMENT: usize = 4096;
+
+pub struct Speed {
+    /// Sequential throughput in bytes per second
+    pub sequential: f64,
+    /// Random reads per second
+    pub iops: f64,
+}
+
+impl Speed {
+    /// Random throughput in bytes per second.
+    pub fn random(&self) -> f64 {
+        self.iops * RANDOM_BLOCK as f64
+    }
+}
+
+/// Opens `path` for reading past the page cache: O_DIRECT where there is
+/// one, F_NOCACHE on macOS and FILE_FLAG_NO_BUFFERING on Windows.
+fn open_direct(path: &str) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.read(true);
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(nix::fcntl::OFlag::O_DIRECT.bits());
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        options.custom_flags(windows_sys::Win32::Storage::FileSystem::FILE_FLAG_NO_BUFFERING);
+    }
+    let file = options.open(path)?;
+    #[cfg(target_os = "macos")]
+    {
+        use std::os::fd::AsRawFd;
+        // SAFETY: F_NOCACHE only sets a flag on the open descriptor
+        if unsafe { nix::libc::fcntl(file.as_raw_fd(), nix::libc::F_NOCACHE, 1) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(file)
+}
+
+/// Size of the device in bytes.
+#[cfg(unix)]
+fn size(mut file: &File) -> io::Result<u64> {
+    use std::io::{Seek, SeekFrom};
+    file.seek(SeekFrom::End(0))
+}
+
+#[cfg(windows)]
+fn size(file: &File) -> io::Result<u64> {
+    crate::platform::windows::length(file)
+}
+
+/// Fills `buffer` from `offset` of `file`.
+#[cfg(unix)]
+fn read_at(file: &File, buffer: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.read_exact_at(buffer, offset)
+}
+
+#[cfg(windows)]
+fn read_at(file: &File, buffer: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match file.seek_read(&mut buffer[filled..], offset + filled as u64)? {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            read => filled += read,
+        }
+    }
+    Ok(())
+}
+
+/// A zeroed buffer of `len` bytes starting at an `ALIGNMENT` boundary, as
+/// the slice `storage[offset..offset + len]`.
+fn aligned(storage: &mut Vec<u8>, len: usize) -> &mut [u8] {
+    *storage = vec![0; len + ALIGNMENT];
+    let offset = storage.as_ptr().align_offset(ALIGNMENT);
+    &mut storage[offset..offset + len]
+}
+
+/// Reads blocks of `buffer.len()` at the offsets `next` yields until
+/// `DURATION` has passed, and returns how many were read per second.
+fn pass(file: &File, buffer: &mut [u8], mut next: impl FnMut() -> u64) -> io::Result<f64> {
+    let start = Instant::now();
+    let mut reads = 0u64;
+    while start.elapsed() < DURATION {
+        read_at(file, buffer, next())?;
+        reads += 1;
+    }
+    Ok(reads as f64 / start.elapsed().as_secs_f64())
+}
+
+/// Benchmarks the block device at `path`, which takes twice `DURATION`.
+/// Nothing is written.
+pub fn run(path: &str) -> io::Result<Speed> {
+    let file = open_direct(path)?;
+    let size = size(&file)?;
+    if size < SEQUENTIAL_BLOCK as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the device is smaller than 1 MiB",
+        ));
+    }
+    let mut storage = Vec::new();
+
+    // Starts over at the beginning after reaching the end of small devices
+    let blocks = size / SEQUENTIAL_BLOCK as u64;
+    let mut block = 0;
+    let sequential = pass(&file, aligned(&mut storage, SEQUENTIAL_BLOCK), || {
+        let offset = block * SEQUENTIAL_BLOCK as u64;
+        block = (block + 1) % blocks;
+        offset
+    })?;
+
+    // xorshift64, seeded from the clock so repeated runs hit other blocks
+    let mut state = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(1, |t| t.as_nanos() as u64)
+        | 1;
+    let blocks = size / RANDOM_BLOCK as u64;
+    let iops = pass(&file, aligned(&mut storage, RANDOM_BLOCK), || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state % blocks * RANDOM_BLOCK as u64
+    })?;
+
+    Ok(Speed {
+        sequential: sequential * SEQUENTIAL_BLOCK as f64,
+        iops,
+    })
+}
@@ -0,0 +1,61 @@
+//! `pblk-smart-helper <DRIVE>`: the SMART and NVMe health queries behind
+//! `pblk --smart`, for desktops where pblk itself runs unprivileged.
+//! Installed setuid root, e.g.
+//!
+//!     install -o root -g root -m 4755 pblk-smart-helper /usr/local/bin/
+//!
+//! it runs nothing but smartctl from a fixed path, with an empty
+//! environment and fixed read-only arguments, on a whole disk that sysfs
+//! lists, so running it gives a user no more than what those queries print.
+
+use std::process;
+#[cfg(unix)]
+use std::{env, os::unix::process::CommandExt, path::Path, process::Command};
+
+/// Where distributions install smartctl. `PATH` is not trusted.
+#[cfg(unix)]
+const SMARTCTL: [&str; 3] = ["/usr/sbin/smartctl", "/usr/bin/smartctl", "/usr/local/sbin/smartctl"];
+
+/// The same queries pblk runs as root.
+#[cfg(unix)]
+const ARGS: [&str; 4] = ["--json=c", "--health", "--attributes", "--nocheck=standby"];
+
+fn fail(message: &str) -> ! {
+    eprintln!("pblk-smart-helper: {}", message);
+    process::exit(2);
+}
+
+/// A kernel disk name such as `sda` or `nvme0n1`; anything that could
+/// walk out of /dev or name a partition is refused.
+#[cfg(unix)]
+fn is_drive(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+        && Path::new("/sys/block").join(name).is_dir()
+}
+
+#[cfg(unix)]
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let [drive] = args.as_slice() else {
+        fail("usage: pblk-smart-helper <DRIVE>, e.g. sda or nvme0n1");
+    };
+    if !is_drive(drive) {
+        fail(&format!("{} is not a drive in /sys/block", drive));
+    }
+    let Some(smartctl) = SMARTCTL.iter().find(|path| Path::new(path).is_file()) else {
+        fail("smartctl is not installed");
+    };
+    let error = Command::new(smartctl)
+        .env_clear()
+        .args(ARGS)
+        .arg(format!("/dev/{}", drive))
+        .exec();
+    fail(&format!("cannot run {}: {}", smartctl, error));
+}
+
+#[cfg(not(unix))]
+fn main() {
+    fail("only needed on Unix; run pblk --smart as administrator instead");
+}
@@ -0,0 +1,277 @@
+/// Membership of a partition in a (possibly multi-device) btrfs filesystem.
+/// Only the `primary` member carries the filesystem-wide usage so pools are
+/// not counted once per device.
+pub struct PoolMember {
+    pub mountpoint: String,
+    pub devid: u64,
+    pub num_devices: usize,
+    pub primary: bool,
+    pub device_size: u64,
+    pub allocated: u64,
+    pub data_profile: &'static str,
+    pub data_used: u64,
+    pub data_total: u64,
+    pub unallocated: u64,
+}
+
+#[cfg(all(feature = "btrfs", unix))]
+pub use probe::annotate;
+
+#[cfg(all(feature = "btrfs", unix))]
+mod probe {
+    use std::{
+        collections::HashSet,
+        fs::{self, File},
+        os::fd::AsRawFd,
+        path::Path,
+    };
+
+    use nix::{ioctl_read, ioctl_readwrite};
+
+    use crate::Drive;
+
+    use super::PoolMember;
+
+    const BTRFS_IOCTL_MAGIC: u8 = 0x94;
+
+    const BLOCK_GROUP_DATA: u64 = 1 << 0;
+    const BLOCK_GROUP_SYSTEM: u64 = 1 << 1;
+    const BLOCK_GROUP_METADATA: u64 = 1 << 2;
+    const SPACE_INFO_GLOBAL_RSV: u64 = 1 << 49;
+
+    const PROFILES: [(u64, &str); 8] = [
+        (1 << 3, "RAID0"),
+        (1 << 4, "RAID1"),
+        (1 << 5, "DUP"),
+        (1 << 6, "RAID10"),
+        (1 << 7, "RAID5"),
+        (1 << 8, "RAID6"),
+        (1 << 9, "RAID1C3"),
+        (1 << 10, "RAID1C4"),
+    ];
+
+    #[repr(C)]
+    struct FsInfoArgs {
+        max_id: u64,
+        num_devices: u64,
+        fsid: [u8; 16],
+        nodesize: u32,
+        sectorsize: u32,
+        clone_alignment: u32,
+        csum_type: u16,
+        csum_size: u16,
+        flags: u64,
+        generation: u64,
+        metadata_uuid: [u8; 16],
+        reserved: [u8; 944],
+    }
+
+    #[repr(C)]
+    struct DevInfoArgs {
+        devid: u64,
+        uuid: [u8; 16],
+        bytes_used: u64,
+        total_bytes: u64,
+        unused: [u64; 379],
+        path: [u8; 1024],
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct SpaceInfo {
+        flags: u64,
+        total_bytes: u64,
+        used_bytes: u64,
+    }
+
+    #[repr(C)]
+    struct SpaceArgs {
+        space_slots: u64,
+        total_spaces: u64,
+    }
+
+    const _: () = assert!(size_of::<FsInfoArgs>() == 1024);
+    const _: () = assert!(size_of::<DevInfoArgs>() == 4096);
+
+    ioctl_readwrite!(btrfs_space_info, BTRFS_IOCTL_MAGIC, 20, SpaceArgs);
+    ioctl_readwrite!(btrfs_dev_info, BTRFS_IOCTL_MAGIC, 30, DevInfoArgs);
+    ioctl_read!(btrfs_fs_info, BTRFS_IOCTL_MAGIC, 31, FsInfoArgs);
+
+    pub struct Device {
+        pub devid: u64,
+        pub path: String,
+        pub size: u64,
+        pub allocated: u64,
+    }
+
+    /// One block group type (data, metadata, system) with its RAID profile.
+    /// `total` and `used` are logical sizes as reported by the kernel.
+    pub struct Space {
+        pub kind: &'static str,
+        pub profile: &'static str,
+        pub total: u64,
+        pub used: u64,
+    }
+
+    pub struct Filesystem {
+        pub fsid: String,
+        pub mountpoint: String,
+        pub devices: Vec<Device>,
+        pub spaces: Vec<Space>,
+    }
+
+    impl Filesystem {
+        pub fn unallocated(&self) -> u64 {
+            self.devices.iter().map(|d| d.size.saturating_sub(d.allocated)).sum()
+        }
+
+        fn data(&self) -> Option<&Space> {
+            self.spaces.iter().find(|s| s.kind == "Data")
+        }
+
+        pub fn member(&self, devid: u64, primary: bool) -> Option<PoolMember> {
+            let device = self.devices.iter().find(|d| d.devid == devid)?;
+            let data = self.data();
+            Some(PoolMember {
+                mountpoint: self.mountpoint.clone(),
+                devid,
+                num_devices: self.devices.len(),
+                primary,
+                device_size: device.size,
+                allocated: device.allocated,
+                data_profile: data.map(|d| d.profile).unwrap_or("single"),
+                data_used: data.map(|d| d.used).unwrap_or(0),
+                data_total: data.map(|d| d.total).unwrap_or(0),
+                unallocated: self.unallocated(),
+            })
+        }
+    }
+
+    /// Queries a mounted btrfs filesystem the way `btrfs filesystem usage` does.
+    pub fn probe(mountpoint: &str) -> Option<Filesystem> {
+        let file = File::open(mountpoint).ok()?;
+        let fd = file.as_raw_fd();
+
+        // SAFETY: the argument structs match the kernel ABI (checked by the size
+        // assertions above) and live for the duration of each call.
+        let mut info: FsInfoArgs = unsafe { std::mem::zeroed() };
+        unsafe { btrfs_fs_info(fd, &mut info) }.ok()?;
+
+        let mut devices = Vec::new();
+        for devid in 1..=info.max_id {
+            let mut dev: DevInfoArgs = unsafe { std::mem::zeroed() };
+            dev.devid = devid;
+            // Missing devids are holes left by removed devices
+            if unsafe { btrfs_dev_info(fd, &mut dev) }.is_err() {
+                continue;
+            }
+            let len = dev.path.iter().position(|&b| b == 0).unwrap_or(dev.path.len());
+            devices.push(Device {
+                devid,
+                path: String::from_utf8_lossy(&dev.path[..len]).into_owned(),
+                size: dev.total_bytes,
+                allocated: dev.bytes_used,
+            });
+        }
+
+        Some(Filesystem {
+            fsid: info.fsid.iter().map(|b| format!("{:02x}", b)).collect(),
+            mountpoint: mountpoint.to_string(),
+            devices,
+            spaces: space_info(fd).unwrap_or_default(),
+        })
+    }
+
+    fn space_info(fd: i32) -> Option<Vec<Space>> {
+        let mut header = SpaceArgs {
+            space_slots: 0,
+            total_spaces: 0,
+        };
+        unsafe { btrfs_space_info(fd, &mut header) }.ok()?;
+
+        // The kernel fills `space_slots` entries directly after the header
+        let slots = header.total_spaces as usize;
+        let words = 2 + slots * 3;
+        let mut buffer = vec![0u64; words];
+        buffer[0] = slots as u64;
+        unsafe { btrfs_space_info(fd, buffer.as_mut_ptr() as *mut SpaceArgs) }.ok()?;
+
+        let filled = (buffer[1] as usize).min(slots);
+        let spaces = buffer[2..2 + filled * 3]
+            .chunks_exact(3)
+            .map(|c| SpaceInfo {
+                flags: c[0],
+                total_bytes: c[1],
+                used_bytes: c[2],
+            })
+            .filter(|s| s.flags & SPACE_INFO_GLOBAL_RSV == 0)
+            .filter_map(|s| {
+                let kind = if s.flags & BLOCK_GROUP_DATA != 0 {
+                    "Data"
+                } else if s.flags & BLOCK_GROUP_METADATA != 0 {
+                    "Metadata"
+                } else if s.flags & BLOCK_GROUP_SYSTEM != 0 {
+                    "System"
+                } else {
+                    return None;
+                };
+                let profile = PROFILES
+                    .iter()
+                    .find(|(bit, _)| s.flags & bit != 0)
+                    .map(|(_, name)| *name)
+                    .unwrap_or("single");
+                Some(Space {
+                    kind,
+                    profile,
+                    total: s.total_bytes,
+                    used: s.used_bytes,
+                })
+            })
+            .collect();
+        Some(spaces)
+    }
+
+    /// Resolves a device path such as `/dev/mapper/x` or `/dev/disk/by-id/...`
+    /// to its kernel name.
+    fn kernel_name(path: &str) -> Option<String> {
+        let resolved = fs::canonicalize(path).ok()?;
+        Path::new(&resolved)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(String::from)
+    }
+
+    /// Attaches btrfs pool membership to every partition that belongs to a
+    /// mounted btrfs filesystem, including unmounted members of multi-device pools.
+    pub fn annotate(drives: &mut [Drive]) {
+        let mountpoints: Vec<(String, String)> = drives
+            .iter()
+            .flat_map(|d| &d.partitions)
+            .filter(|p| p.fstype.as_deref() == Some("btrfs"))
+            .filter_map(|p| Some((p.dev_name().to_string(), p.mountpoint.clone()?)))
+            .collect();
+
+        let mut seen = HashSet::new();
+        for (mounted_dev, mountpoint) in mountpoints {
+            let Some(fs) = probe(&mountpoint) else {
+                continue;
+            };
+            if !seen.insert(fs.fsid.clone()) {
+                continue;
+            }
+
+            for device in &fs.devices {
+                let Some(name) = kernel_name(&device.path) else {
+                    continue;
+                };
+                let partition = drives
+                    .iter_mut()
+                    .flat_map(|d| d.partitions.iter_mut())
+                    .find(|p| p.dev_name() == name);
+                if let Some(partition) = partition {
+                    partition.pool = fs.member(device.devid, name == mounted_dev);
+                }
+            }
+        }
+    }
+}
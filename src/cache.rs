@@ -0,0 +1,212 @@
+//! Hybrid drives built from a fast cache device in front of a slow backing
+//! device: bcache, and dm-cache as set up by `lvconvert --type cache`.
+
+use std::{
+    fs::{canonicalize, read_to_string},
+    path::Path,
+};
+
+use crate::{
+    Drive,
+    platform::linux::{block_name, dm_device, dm_maps, dmsetup, sysfs_links},
+    privilege, units,
+};
+
+/// A cached device and the two devices it is built from, named by their
+/// kernel names. The hit and miss counts are since the cache was attached.
+#[derive(Clone)]
+pub struct Tier {
+    pub kind: &'static str,
+    /// The combined device, e.g. `bcache0` or the `dm-N` of a cached LV
+    pub device: String,
+    /// The SSD side; bcache keeps working without one when it is detached
+    pub cache: Option<String>,
+    pub backing: String,
+    /// `writeback`, `writethrough`, ...
+    pub mode: Option<String>,
+    /// `None` when the counters could not be read
+    pub hits: Option<u64>,
+    pub misses: Option<u64>,
+    /// Bytes written to the cache and not yet to the backing device
+    pub dirty: Option<u64>,
+}
+
+impl Tier {
+    /// Share of requests answered by the cache, `None` before the first one
+    /// or without counters.
+    pub fn hit_ratio(&self) -> Option<f64> {
+        let (hits, requests) = (self.hits?, self.requests()?);
+        (requests > 0).then(|| hits as f64 / requests as f64)
+    }
+
+    pub fn requests(&self) -> Option<u64> {
+        Some(self.hits?.saturating_add(self.misses?))
+    }
+}
+
+fn sysfs(path: &Path, attribute: &str) -> Option<String> {
+    read_to_string(path.join(attribute)).ok().map(|s| s.trim().to_string())
+}
+
+/// The block device a sysfs `bcache` directory belongs to, e.g. `sdb1` for
+/// `/sys/block/sdb/sdb1/bcache`.
+fn owner(bcache_dir: &Path) -> Option<String> {
+    let path = canonicalize(bcache_dir).ok()?;
+    Some(path.parent()?.file_name()?.to_str()?.to_string())
+}
+
+/// The option in brackets of a sysfs choice like `writethrough [writeback] none`.
+fn selected(choices: &str) -> Option<String> {
+    let start = choices.find('[')? + 1;
+    let end = start + choices[start..].find(']')?;
+    Some(choices[start..end].to_string())
+}
+
+/// The bcache device `name`, read from the backing device's attributes
+/// that `/sys/block/bcacheN/bcache` links to.
+fn bcache(name: &str) -> Option<Tier> {
+    let dir = Path::new("/sys/block").join(name).join("bcache");
+    let backing = owner(&dir)?;
+    // An attached backing device links to its cache set, which links to
+    // the bcache directory of each of its cache devices
+    let cache = sysfs_links(&dir.join("cache").display().to_string())
+        .into_iter()
+        .find(|entry| entry.starts_with("cache") && entry[5..].parse::<u32>().is_ok())
+        .and_then(|entry| owner(&dir.join("cache").join(entry)));
+    let stat = |attribute| sysfs(&dir.join("stats_total"), attribute).and_then(|v| v.parse().ok());
+    Some(Tier {
+        kind: "bcache",
+        device: name.to_string(),
+        cache,
+        backing,
+        mode: sysfs(&dir, "cache_mode").and_then(|choices| selected(&choices)),
+        hits: stat("cache_hits"),
+        misses: stat("cache_misses"),
+        dirty: sysfs(&dir, "dirty_data").and_then(|dirty| units::parse_size(&dirty)),
+    })
+}
+
+/// The kernel name of the device `major:minor`, followed down through
+/// stacked dm devices to the disk or partition at the bottom.
+fn bottom(device: &str) -> Option<String> {
+    let mut name = block_name(device)?;
+    for _ in 0..8 {
+        match sysfs_links(&format!("/sys/class/block/{}/slaves", name))
+            .into_iter()
+            .next()
+        {
+            Some(slave) => name = slave,
+            None => break,
+        }
+    }
+    Some(name)
+}
+
+/// One cached map from the `dmsetup table` and `dmsetup status` lines for it.
+/// The table names the metadata, cache and origin devices; the status
+/// counts read and write hits and misses and dirty cache blocks.
+fn dm_cache(table: &str, status: &str) -> Option<Tier> {
+    let table: Vec<&str> = table.split_whitespace().collect();
+    let status: Vec<&str> = status.split_whitespace().collect();
+    // name: start length cache metadata cache origin block_size ...
+    let device = dm_device(table.first()?.strip_suffix(':')?)?;
+    let block_size: u64 = table.get(7)?.parse().ok()?;
+    let count = |i: usize| status.get(i).and_then(|v| v.parse::<u64>().ok());
+    // name: start length cache metadata_block used/total block_size
+    // used/total read_hits read_misses write_hits write_misses demotions
+    // promotions dirty feature_count features...
+    let mode = count(15).and_then(|features| {
+        status
+            .iter()
+            .skip(16)
+            .take(features as usize)
+            .find(|feature| matches!(**feature, "writeback" | "writethrough" | "passthrough"))
+            .map(|mode| mode.to_string())
+    });
+    Some(Tier {
+        kind: "dm-cache",
+        device,
+        cache: bottom(table.get(5)?),
+        backing: bottom(table.get(6)?)?,
+        mode,
+        hits: Some(count(8)?.saturating_add(count(10)?)),
+        misses: Some(count(9)?.saturating_add(count(11)?)),
+        dirty: count(14).map(|blocks| blocks.saturating_mul(block_size).saturating_mul(512)),
+    })
+}
+
+/// Whether LVM has set up a cached volume, whose hidden origin and cache
+/// data volumes get `_corig` and `_cdata` map names.
+fn lvm_cache_present() -> bool {
+    dm_maps()
+        .iter()
+        .any(|(_, map)| map.ends_with("_corig") || map.ends_with("_cdata"))
+}
+
+/// All dm-cache maps, if there are any and dmsetup can tell.
+fn dm_caches() -> Vec<Tier> {
+    let (Some(tables), Some(statuses)) = (dmsetup("table", "cache"), dmsetup("status", "cache")) else {
+        if !privilege::is_root() && lvm_cache_present() {
+            privilege::deny("dm-cache statistics".to_string());
+        }
+        return Vec::new();
+    };
+    tables
+        .lines()
+        .filter_map(|table| {
+            let map = table.split_whitespace().next()?;
+            let status = statuses
+                .lines()
+                .find(|line| line.split_whitespace().next() == Some(map))?;
+            dm_cache(table, status)
+        })
+        .collect()
+}
+
+/// Attaches each cache tier to the combined device's row, and to the drive
+/// holding the cache device so the SSD does not look unrelated.
+pub fn annotate(drives: &mut [Drive]) {
+    let names: Vec<String> = drives
+        .iter()
+        .flat_map(|d| &d.partitions)
+        .map(|p| p.dev_name().to_string())
+        .collect();
+    let mut tiers: Vec<Tier> = names
+        .iter()
+        .filter(|name| name.starts_with("bcache"))
+        .filter_map(|name| bcache(name))
+        .collect();
+    // Only ask dmsetup, which needs root, if there are dm devices at all
+    if names.iter().any(|name| name.starts_with("dm-")) {
+        tiers.extend(dm_caches());
+    }
+
+    for tier in tiers {
+        for drive in drives.iter_mut() {
+            let holds_cache = tier
+                .cache
+                .as_ref()
+                .is_some_and(|cache| *cache == drive.name || drive.partitions.iter().any(|p| p.dev_name() == cache));
+            if holds_cache {
+                drive.caching.push(tier.clone());
+            }
+            for partition in drive.partitions.iter_mut().filter(|p| p.dev_name() == tier.device) {
+                partition.cache = Some(tier.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selected_choice() {
+        assert_eq!(
+            selected("writethrough [writeback] writearound none").as_deref(),
+            Some("writeback")
+        );
+        assert_eq!(selected("no brackets"), None);
+    }
+}
@@ -0,0 +1,81 @@
+//! External tools that can hang, on a dead drive or a wedged RAID
+//! controller, run with a time limit and without being left behind.
+
+use std::{
+    io::{self, Read},
+    process::{Command, Output, Stdio},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often a command that closed its output is checked for having exited.
+const POLL: Duration = Duration::from_millis(10);
+
+/// What `command` printed and how it exited, or a `TimedOut` error once
+/// `timeout` has passed. A command that overruns is killed; one asleep in
+/// a drive ioctl only dies when that returns, so it is reaped on a thread
+/// of its own instead of holding up the caller.
+pub fn output(command: &mut Command, timeout: Duration) -> io::Result<Output> {
+    let deadline = Instant::now() + timeout;
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let (mut stdout, mut stderr) = (child.stdout.take(), child.stderr.take());
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        // Read apart so a command filling up stderr cannot block on it
+        let errors = thread::spawn(move || {
+            let mut bytes = Vec::new();
+            if let Some(pipe) = &mut stderr {
+                let _ = pipe.read_to_end(&mut bytes);
+            }
+            bytes
+        });
+        let mut bytes = Vec::new();
+        if let Some(pipe) = &mut stdout {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        let _ = sender.send((bytes, errors.join().unwrap_or_default()));
+    });
+
+    let printed = receiver
+        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        .ok();
+    if let Some((stdout, stderr)) = printed {
+        while Instant::now() < deadline {
+            if let Some(status) = child.try_wait()? {
+                return Ok(Output { status, stdout, stderr });
+            }
+            thread::sleep(POLL);
+        }
+    }
+    let _ = child.kill();
+    thread::spawn(move || child.wait());
+    Err(io::Error::new(
+        io::ErrorKind::TimedOut,
+        format!("timed out after {:.1}s", timeout.as_secs_f64()),
+    ))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_that_overrun_are_given_up_on() {
+        let printed = output(
+            Command::new("sh").args(["-c", "echo out; echo err >&2"]),
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        assert_eq!((printed.stdout, printed.stderr), (b"out\n".to_vec(), b"err\n".to_vec()));
+
+        let started = Instant::now();
+        let error = output(Command::new("sleep").arg("5"), Duration::from_millis(100)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
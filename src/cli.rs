@@ -0,0 +1,640 @@
+use std::{env, path::PathBuf, process, time::Duration};
+
+use crate::{
+    columns::{self, Column, Sort},
+    config::MAX_HISTORY_DAYS,
+    growth,
+    lang::Lang,
+    theme::Theme,
+    units,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Pretty,
+    Csv,
+    Tsv,
+    /// Shell assignments, one per line
+    Env,
+    /// NUL-terminated `key=value` records
+    Kv,
+}
+
+/// When the charts go through a pager.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Paging {
+    /// Only when they do not fit on the terminal
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Export {
+    Html,
+    Svg,
+}
+
+pub enum ConfigAction {
+    Show,
+    Path,
+    Init,
+}
+
+pub enum Command {
+    Show,
+    #[cfg_attr(not(feature = "history"), allow(dead_code))]
+    History {
+        target: String,
+        days: Option<u64>,
+    },
+    Config(ConfigAction),
+    Doctor,
+    Compare {
+        first: String,
+        second: String,
+    },
+    Plan {
+        add: u64,
+        to: String,
+    },
+    Snapshot,
+    Inventory {
+        hosts: Vec<String>,
+    },
+    Diff {
+        path: PathBuf,
+    },
+    Image {
+        path: PathBuf,
+    },
+    /// Print one filesystem's usage percentage and exit with its severity
+    Pct {
+        target: String,
+    },
+    /// Switch the locate LED of a drive's bay
+    Locate {
+        device: String,
+        on: bool,
+    },
+    /// Measure the read throughput of a device
+    Bench {
+        device: String,
+    },
+    /// Summarize every drive, those with problems first, optionally as a
+    /// MIME message for sendmail
+    Report {
+        email: bool,
+    },
+}
+
+pub struct Args {
+    pub command: Command,
+    pub format: Option<Format>,
+    pub export: Option<Export>,
+    pub record: bool,
+    pub force: bool,
+    pub swap_files: bool,
+    pub loops: bool,
+    pub inodes: bool,
+    pub verbose: bool,
+    pub summary: bool,
+    pub tuning: bool,
+    /// List partition start and end sectors and the gaps between them
+    pub layout: bool,
+    pub removable_only: bool,
+    /// Print the device node chosen from a list instead of the charts
+    pub pick: bool,
+    /// Include card readers and optical drives with no media inserted
+    pub all: bool,
+    /// Bounds on drive size in bytes, from --min-size and --max-size
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Bounds on the percentage of mounted space used on a drive
+    pub min_usage: Option<f64>,
+    pub max_usage: Option<f64>,
+    pub quota: bool,
+    pub fs_health: bool,
+    /// Ask each drive for its SMART or NVMe health verdict
+    pub smart: bool,
+    pub glyphs: bool,
+    pub depth: Option<usize>,
+    pub flat: bool,
+    pub quiet: bool,
+    /// Print nothing and exit with the worst severity of the problems found
+    pub silent: bool,
+    /// Default usage limits in percent, over those in the config
+    pub warn: Option<f64>,
+    pub critical: Option<f64>,
+    /// Leave the hostname, kernel and collection time out of reports and
+    /// machine-readable output
+    pub no_identity: bool,
+    pub paging: Option<Paging>,
+    pub memory_backed: bool,
+    /// Partition row columns chosen with --output
+    pub output: Option<Vec<Column>>,
+    /// Drive order chosen with --sort
+    pub sort: Option<Sort>,
+    pub no_bars: bool,
+    pub theme: Option<Theme>,
+    /// Language of the labels and decimal separator, instead of the locale's
+    pub lang: Option<Lang>,
+    /// Draw with ASCII characters only
+    pub ascii: bool,
+    /// Show a sparkline of recorded usage in each partition row
+    pub history: bool,
+    /// Project when each growing filesystem fills up from the history
+    pub forecast: bool,
+    /// Write rates to project fill dates from without history
+    pub assume_growth: Vec<growth::Assumption>,
+    pub days: Option<u64>,
+    /// Redraw every this often instead of printing once
+    pub watch: Option<Duration>,
+    /// Shell command run when a filesystem crosses a usage threshold
+    pub on_threshold: Option<String>,
+    pub notify: bool,
+    pub audit: Option<PathBuf>,
+    /// A mounted system image whose fstab and crypttab are checked instead
+    pub chroot: Option<PathBuf>,
+    /// List detected problems instead of the charts
+    pub problems: bool,
+    pub exporter: bool,
+    pub listen: Option<String>,
+    pub pidfile: Option<PathBuf>,
+}
+
+const USAGE: &str = "\
+Usage: pblk [OPTIONS]
+       pblk history <MOUNTPOINT|DEVICE> [--days N]
+       pblk config <show|path|init> [--force]
+       pblk doctor
+       pblk compare <DRIVE> <DRIVE>
+       pblk snapshot > state.json
+       pblk inventory [--host HOST]...
+       pblk diff <SNAPSHOT>
+       pblk image <FILE>
+       pblk pct <PATH|DEVICE>      exit status 1 when over warning, 2 when critical
+       pblk locate <DRIVE> [on|off]  blink the LED of the drive's enclosure bay
+       pblk bench <DEVICE>         read for a few seconds and print throughput and IOPS
+       pblk report [--email-format]  drives with problems first, e.g. for a weekly cron mail
+       pblk plan --add <SIZE> --to <POOL|MOUNTPOINT|VG>
+
+Options:
+  --format <FORMAT>  Output format: pretty, csv, tsv, env (shell assignments) or
+                     kv (NUL-terminated key=value records) [default: pretty]
+  --no-identity      Leave the hostname, kernel version and collection time out of
+                     csv, tsv, env and kv output, snapshots and reports
+  --paging <WHEN>    Show the charts through $PAGER (less by default), colors kept:
+                     auto when they do not fit on the terminal, always or never
+                     [default: auto]
+  -o, --output <COLS>
+                     Columns of each partition row, comma-separated, from name, bar,
+                     trend, usage, fstype, size, used, avail, use% and mountpoint
+                     [default: name,bar,usage,mountpoint]
+  --sort <KEY>       Order drives by name, size or use (of the fullest filesystem);
+                     a leading - sorts descending, e.g. --sort -use
+  --no-bars          Hide the partition and usage bars, leaving the text columns
+  --theme <THEME>    default, or high-contrast for bright colors and spelled-out
+                     WARNING and CRITICAL markers [default: display.theme in the config]
+  --lang <LANG>      Labels and decimal separator in en, de, fr, es or pt
+                     [default: from LC_ALL, LC_MESSAGES or LANG]
+  --ascii            Draw bars with #, = and - instead of block characters; the
+                     default when the locale is not UTF-8 or TERM is a vt100 or dumb
+  --inodes           Show inode usage below each usage bar
+  --glyphs           Draw a small ASCII picture and the model beside each drive
+  --export <KIND>    Render the charts as a standalone html page or svg image
+  --swap-files       List active swap files under the partition holding them
+  --loops            List loop devices under the partition holding their backing file
+  --memory-backed    List zram devices with their compression ratio and large tmpfs mounts
+  --quota            List the largest users, groups and projects on filesystems with quotas
+  --fs-health        Read the superblocks of unmounted ext2/3/4 and btrfs filesystems
+                     and flag those left dirty or with errors recorded
+  --smart            Show the SMART or NVMe health verdict, temperature and power-on
+                     hours of each drive from smartctl; as an ordinary user through
+                     the setuid pblk-smart-helper when it is installed
+  --chroot <DIR>     Check unmounted partitions against the fstab and crypttab of
+                     the system image mounted at DIR, e.g. /mnt/sysimage in a
+                     rescue environment, instead of those of the running system
+  --record           Append a usage sample for each mounted partition to the history
+  --history          Show a sparkline of the recorded usage of the last --days days
+                     beside each usage bar, like the `trend` column of --output
+  --forecast         Fit the growth recorded over the last --days days and show when
+                     each growing filesystem will be full, in red within a week
+  --assume-growth <MOUNTPOINT=SIZE/day>
+                     Project when a filesystem is full if SIZE is written to it
+                     every day, for new partitions without history, in the
+                     charts and `pblk report`; repeatable
+  --exporter         Serve usage as Prometheus metrics instead of printing charts
+  --listen <ADDR>    Address for --exporter to listen on [default: 127.0.0.1:9633]
+  --pidfile <FILE>   Lock file that keeps a second --exporter from starting
+                     [default: $XDG_RUNTIME_DIR/prettyblk-exporter.pid]
+  --watch <SECS>     Redraw the charts about every SECS seconds until interrupted,
+                     down to a quarter of that while usage or disk traffic changes
+                     and up to four times that while nothing does; slower still
+                     on battery, see [power] in the config. Keys: s cycles the
+                     sort key, r reverses it, 1-9 and 0 toggle the columns of
+                     --output in the order listed there, w saves sort and
+                     columns to the config, b selects the next drive in an
+                     enclosure bay, l blinks its locate LED or stops it and
+                     q quits
+  --on-threshold <CMD>
+                     Run CMD with sh when a filesystem crosses its warning or critical
+                     threshold, with PBLK_DEVICE, PBLK_MOUNTPOINT, PBLK_PERCENT and
+                     PBLK_SEVERITY set; with --watch only on the scan it gets worse
+  --notify           Send a desktop notification (notify-send) on the same crossings
+  --audit <FILE>     Append a JSON line for every problem detected to FILE
+  --problems         List the problems detected instead of the charts, leaving out
+                     those acknowledged in the config
+  --days <N>         Number of days of history to plot or sparkline [default: 7]
+  --force            Let `config init` overwrite an existing config file, or
+                     start --exporter while another instance holds the pidfile
+  --add <SIZE>       Raw capacity of the disk to plan for, e.g. 2T
+  --to <TARGET>      ZFS pool, btrfs mountpoint or LVM volume group to add it to
+  --host <HOST>      Collect the inventory from HOST over ssh instead; repeatable
+  --email-format     Print the report as a text and HTML email with a Subject, ready
+                     for `| sendmail admin@example.com`
+  --depth <N>        Show devices at most N levels below each drive, 1 for partitions only
+  --flat             List stacked devices (LUKS, LVM, md) like partitions, without a tree
+  -v, --verbose      Show partition table type, partition types and flags, what
+                     btrfs and ZFS compression saves (btrfs needs compsize) and
+                     further mountpoints of filesystems mounted more than once
+  --summary          Show mounted usage per drive and capacity totals across drives
+  --removable-only   Show only removable and USB drives, e.g. to find a thumb drive
+  --pick             Choose a drive or partition with the arrow keys and print only
+                     its /dev path, e.g. dd of=$(pblk --pick --removable-only);
+                     exits 1 when cancelled
+  -a, --all          Also show card readers and optical drives with no media inserted
+  --min-size <SIZE>  Show only drives of at least SIZE, e.g. 100G; --max-size for at most
+  --min-usage <PCT>  Show only drives whose mounted filesystems use at least PCT of their
+                     space, e.g. 90%; --max-usage for at most
+  --tuning           Show discard support, I/O scheduler and queue depth per drive,
+                     and how and when each filesystem was last trimmed
+  --layout           List the start and end sector of every partition in on-disk order,
+                     the free gaps between them and partitions not aligned to 1 MiB
+  --query <TARGET>   Like `pblk pct`: print the use% of the filesystem holding a
+                     device or path, and exit 0 when ok, 1 over the warning
+                     threshold, 2 over the critical one and 3 when it is missing;
+                     with --quiet only the exit status is left, for check plugins
+  --bench <DEVICE>   Like `pblk bench`: read the device sequentially and at random
+                     for three seconds each, past the page cache, and print the
+                     throughput in the [units] rate unit and IOPS. Nothing is written
+  --warn <PCT>       Warning limit for filesystems without their own in the config,
+                     e.g. 90%; --critical for the critical one
+  --silent           Print nothing at all and exit 0 when no problems are found, 1
+                     with a warning and 2 with a critical one, for shell prompts
+  -q, --quiet        Print nothing to stderr unless the run fails
+  -h, --help         Print this help";
+
+impl Paging {
+    pub fn parse(value: &str) -> Option<Paging> {
+        match value {
+            "auto" => Some(Paging::Auto),
+            "always" => Some(Paging::Always),
+            "never" => Some(Paging::Never),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Paging::Auto => "auto",
+            Paging::Always => "always",
+            Paging::Never => "never",
+        }
+    }
+}
+
+impl Format {
+    pub fn parse(value: &str) -> Option<Format> {
+        match value {
+            "pretty" => Some(Format::Pretty),
+            "csv" => Some(Format::Csv),
+            "tsv" => Some(Format::Tsv),
+            "env" => Some(Format::Env),
+            "kv" => Some(Format::Kv),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Pretty => "pretty",
+            Format::Csv => "csv",
+            Format::Tsv => "tsv",
+            Format::Env => "env",
+            Format::Kv => "kv",
+        }
+    }
+}
+
+impl Args {
+    pub fn parse() -> Args {
+        let mut args = Args {
+            command: Command::Show,
+            format: None,
+            export: None,
+            record: false,
+            force: false,
+            swap_files: false,
+            loops: false,
+            inodes: false,
+            verbose: false,
+            summary: false,
+            tuning: false,
+            layout: false,
+            removable_only: false,
+            pick: false,
+            all: false,
+            min_size: None,
+            max_size: None,
+            min_usage: None,
+            max_usage: None,
+            quota: false,
+            fs_health: false,
+            smart: false,
+            glyphs: false,
+            depth: None,
+            flat: false,
+            quiet: false,
+            silent: false,
+            warn: None,
+            critical: None,
+            no_identity: false,
+            paging: None,
+            memory_backed: false,
+            output: None,
+            sort: None,
+            no_bars: false,
+            theme: None,
+            lang: None,
+            ascii: false,
+            history: false,
+            forecast: false,
+            assume_growth: Vec::new(),
+            days: None,
+            watch: None,
+            on_threshold: None,
+            notify: false,
+            audit: None,
+            chroot: None,
+            problems: false,
+            exporter: false,
+            listen: None,
+            pidfile: None,
+        };
+        let mut subcommand: Option<String> = None;
+        let mut positional: Vec<String> = Vec::new();
+        let mut days = None;
+        let mut add = None;
+        let mut to = None;
+        let mut hosts = Vec::new();
+        let mut query = None;
+        let mut bench = None;
+        let mut email = false;
+        let mut argv = env::args().skip(1);
+
+        while let Some(arg) = argv.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+                _ => (arg.clone(), None),
+            };
+            let mut value = |name: &str| {
+                inline
+                    .clone()
+                    .or_else(|| argv.next())
+                    .unwrap_or_else(|| fail(&format!("missing value for {}", name)))
+            };
+
+            match flag.as_str() {
+                "--format" => {
+                    let v = value("--format");
+                    args.format = Some(Format::parse(&v).unwrap_or_else(|| fail(&format!("unknown format '{}'", v))));
+                }
+                "--export" => {
+                    let v = value("--export");
+                    args.export = Some(match v.as_str() {
+                        "html" => Export::Html,
+                        "svg" => Export::Svg,
+                        _ => fail(&format!("unknown export '{}'", v)),
+                    });
+                }
+                "--record" => args.record = true,
+                "--exporter" => args.exporter = true,
+                "--listen" => args.listen = Some(value("--listen")),
+                "--pidfile" => args.pidfile = Some(PathBuf::from(value("--pidfile"))),
+                "--audit" => args.audit = Some(PathBuf::from(value("--audit"))),
+                "--chroot" => args.chroot = Some(PathBuf::from(value("--chroot"))),
+                "--problems" => args.problems = true,
+                "--swap-files" => args.swap_files = true,
+                "--loops" => args.loops = true,
+                "--inodes" => args.inodes = true,
+                "-v" | "--verbose" => args.verbose = true,
+                "--summary" => args.summary = true,
+                "--tuning" => args.tuning = true,
+                "--layout" => args.layout = true,
+                "--removable-only" => args.removable_only = true,
+                "--pick" => args.pick = true,
+                "-a" | "--all" => args.all = true,
+                "--min-size" | "--max-size" => {
+                    let v = value(&flag);
+                    let size =
+                        units::parse_size(&v).unwrap_or_else(|| fail(&format!("invalid size '{}' for {}", v, flag)));
+                    match flag.as_str() {
+                        "--min-size" => args.min_size = Some(size),
+                        _ => args.max_size = Some(size),
+                    }
+                }
+                "--min-usage" | "--max-usage" => {
+                    let v = value(&flag);
+                    let percent = units::parse_percent(&v)
+                        .unwrap_or_else(|| fail(&format!("invalid percentage '{}' for {}", v, flag)));
+                    match flag.as_str() {
+                        "--min-usage" => args.min_usage = Some(percent),
+                        _ => args.max_usage = Some(percent),
+                    }
+                }
+                "--quota" => args.quota = true,
+                "--fs-health" => args.fs_health = true,
+                "--smart" => args.smart = true,
+                "--glyphs" => args.glyphs = true,
+                "--flat" => args.flat = true,
+                "--memory-backed" => args.memory_backed = true,
+                "-q" | "--quiet" => args.quiet = true,
+                "--silent" => args.silent = true,
+                "--warn" | "--critical" => {
+                    let v = value(&flag);
+                    let percent = units::parse_percent(&v)
+                        .unwrap_or_else(|| fail(&format!("invalid percentage '{}' for {}", v, flag)));
+                    match flag.as_str() {
+                        "--warn" => args.warn = Some(percent),
+                        _ => args.critical = Some(percent),
+                    }
+                }
+                "--no-identity" => args.no_identity = true,
+                "--paging" => {
+                    let v = value("--paging");
+                    args.paging = Some(Paging::parse(&v).unwrap_or_else(|| fail(&format!("unknown paging '{}'", v))));
+                }
+                "--depth" => {
+                    let depth = parse_number(&value("--depth"), "--depth");
+                    if depth == 0 {
+                        fail("--depth must be at least 1");
+                    }
+                    args.depth = Some(depth as usize);
+                }
+                "--output" | "-o" => {
+                    args.output = Some(columns::parse_list(&value("--output")).unwrap_or_else(|e| fail(&e)));
+                }
+                "--sort" => args.sort = Some(Sort::parse(&value("--sort")).unwrap_or_else(|e| fail(&e))),
+                "--no-bars" => args.no_bars = true,
+                "--ascii" => args.ascii = true,
+                "--lang" => {
+                    let v = value("--lang");
+                    args.lang = Some(Lang::parse(&v).unwrap_or_else(|| {
+                        fail(&format!(
+                            "unknown language '{}', expected one of: {}",
+                            v,
+                            Lang::codes().join(", ")
+                        ))
+                    }));
+                }
+                "--theme" => {
+                    let v = value("--theme");
+                    args.theme = Some(Theme::parse(&v).unwrap_or_else(|| fail(&format!("unknown theme '{}'", v))));
+                }
+                "--history" => args.history = true,
+                "--forecast" => args.forecast = true,
+                "--assume-growth" => {
+                    args.assume_growth
+                        .push(growth::Assumption::parse(&value("--assume-growth")).unwrap_or_else(|e| fail(&e)));
+                }
+                "--watch" => {
+                    let seconds = parse_number(&value("--watch"), "--watch");
+                    if !(1..=86_400).contains(&seconds) {
+                        fail("--watch must be from 1 second to a day (86400)");
+                    }
+                    args.watch = Some(Duration::from_secs(seconds));
+                }
+                "--on-threshold" => args.on_threshold = Some(value("--on-threshold")),
+                "--notify" => args.notify = true,
+                "--days" => {
+                    let n = parse_number(&value("--days"), "--days");
+                    if n > MAX_HISTORY_DAYS {
+                        fail(&format!("--days can be at most {}", MAX_HISTORY_DAYS));
+                    }
+                    days = Some(n);
+                }
+                "--force" => args.force = true,
+                "--add" => {
+                    let v = value("--add");
+                    add =
+                        Some(units::parse_size(&v).unwrap_or_else(|| fail(&format!("invalid size '{}' for --add", v))));
+                }
+                "--to" => to = Some(value("--to")),
+                "--host" => {
+                    let host = value("--host");
+                    // ssh would take it for an option such as -oProxyCommand
+                    if host.starts_with('-') {
+                        fail(&format!("invalid host '{}' for --host", host));
+                    }
+                    hosts.push(host);
+                }
+                "--email-format" => email = true,
+                "--query" => query = Some(value("--query")),
+                "--bench" => bench = Some(value("--bench")),
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    process::exit(0);
+                }
+                _ if arg.starts_with('-') => fail(&format!("unexpected argument '{}'", arg)),
+                _ if subcommand.is_none() => subcommand = Some(arg),
+                _ => positional.push(arg),
+            }
+        }
+
+        args.days = days;
+        args.command = match subcommand.as_deref() {
+            None => match (query, bench) {
+                (Some(target), _) => Command::Pct { target },
+                (None, Some(device)) => Command::Bench { device },
+                (None, None) => Command::Show,
+            },
+            Some("history") => {
+                if positional.len() != 1 {
+                    fail("history requires exactly one mountpoint or device");
+                }
+                Command::History {
+                    target: positional.remove(0),
+                    days,
+                }
+            }
+            Some("config") => {
+                let action = match positional.as_slice() {
+                    [] => ConfigAction::Show,
+                    [action] if action == "show" => ConfigAction::Show,
+                    [action] if action == "path" => ConfigAction::Path,
+                    [action] if action == "init" => ConfigAction::Init,
+                    _ => fail("config expects one of: show, path, init"),
+                };
+                Command::Config(action)
+            }
+            Some("doctor") if positional.is_empty() => Command::Doctor,
+            Some("compare") => match <[String; 2]>::try_from(positional) {
+                Ok([first, second]) => Command::Compare { first, second },
+                Err(_) => fail("compare requires exactly two drives"),
+            },
+            Some("snapshot") if positional.is_empty() => Command::Snapshot,
+            Some("inventory") if positional.is_empty() => Command::Inventory { hosts },
+            Some("report") if positional.is_empty() => Command::Report { email },
+            Some("diff") => match <[String; 1]>::try_from(positional) {
+                Ok([path]) => Command::Diff {
+                    path: PathBuf::from(path),
+                },
+                Err(_) => fail("diff requires exactly one snapshot file"),
+            },
+            Some("pct") => match <[String; 1]>::try_from(positional) {
+                Ok([target]) => Command::Pct { target },
+                Err(_) => fail("pct requires exactly one path or device"),
+            },
+            Some("locate") => match positional.as_slice() {
+                [device] => Command::Locate {
+                    device: device.clone(),
+                    on: true,
+                },
+                [device, state] if state == "on" || state == "off" => Command::Locate {
+                    device: device.clone(),
+                    on: state == "on",
+                },
+                _ => fail("locate requires a drive and optionally on or off"),
+            },
+            Some("bench") => match <[String; 1]>::try_from(positional) {
+                Ok([device]) => Command::Bench { device },
+                Err(_) => fail("bench requires exactly one device"),
+            },
+            Some("image") => match <[String; 1]>::try_from(positional) {
+                Ok([path]) => Command::Image {
+                    path: PathBuf::from(path),
+                },
+                Err(_) => fail("image requires exactly one raw disk image"),
+            },
+            Some("plan") if positional.is_empty() => match (add, to) {
+                (Some(add), Some(to)) => Command::Plan { add, to },
+                _ => fail("plan requires --add and --to"),
+            },
+            Some(other) => fail(&format!("unknown command '{}'", other)),
+        };
+
+        args
+    }
+}
+
+fn parse_number(value: &str, name: &str) -> u64 {
+    value
+        .parse()
+        .unwrap_or_else(|_| fail(&format!("invalid number '{}' for {}", value, name)))
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("pblk: {}\n\n{}", message, USAGE);
+    process::exit(2);
+}
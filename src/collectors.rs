@@ -0,0 +1,104 @@
+use std::{io, process::Command, time::Duration};
+
+use crate::{Drive, Partition, child, json};
+
+/// An external command declared under `[collectors.<name>]` that reports
+/// drives sysfs cannot see, e.g. the disks behind a hardware RAID volume.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collector {
+    pub name: String,
+    pub command: Vec<String>,
+    pub timeout: Duration,
+}
+
+impl Collector {
+    pub fn new(name: &str) -> Collector {
+        Collector {
+            name: name.to_string(),
+            command: Vec::new(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Where a virtual drive came from and the state its collector reported.
+pub struct Origin {
+    pub collector: String,
+    pub state: Option<String>,
+}
+
+/// Runs every collector and returns the drives they report. A collector
+/// that fails, times out or prints something unexpected is reported and
+/// skipped so that the rest of the chart still renders.
+pub fn collect(collectors: &[Collector]) -> Vec<Drive> {
+    let mut drives = Vec::new();
+    for collector in collectors {
+        match run(collector).and_then(|output| parse(&collector.name, &output)) {
+            Ok(found) => drives.extend(found),
+            Err(e) => warn!("pblk: collector '{}': {}", collector.name, e),
+        }
+    }
+    drives
+}
+
+fn run(collector: &Collector) -> Result<String, String> {
+    let Some((program, args)) = collector.command.split_first() else {
+        return Err("no command configured".to_string());
+    };
+    let output = child::output(Command::new(program).args(args), collector.timeout).map_err(|e| match e.kind() {
+        io::ErrorKind::TimedOut => e.to_string(),
+        _ => format!("cannot run {}: {}", program, e),
+    })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} failed: {}", program, stderr.trim()));
+    }
+    String::from_utf8(output.stdout).map_err(|_| "output is not UTF-8".to_string())
+}
+
+/// Parses `{"drives": [{"name", "size", "state"?, "partitions"?: [{"name",
+/// "size", "used"?, "free"?, "mountpoint"?, "fstype"?}]}]}` with sizes in bytes.
+fn parse(collector: &str, output: &str) -> Result<Vec<Drive>, String> {
+    let root = json::parse(output)?;
+    let invalid = |what: &str| format!("output has no {}", what);
+
+    let mut drives = Vec::new();
+    let entries = root.get("drives").and_then(json::Value::as_array);
+    for entry in entries.ok_or_else(|| invalid("drives list"))? {
+        let name = entry
+            .get("name")
+            .and_then(json::Value::as_str)
+            .ok_or_else(|| invalid("drive name"))?;
+        let size = entry
+            .get("size")
+            .and_then(json::Value::as_u64)
+            .ok_or_else(|| invalid("drive size"))?;
+
+        let mut partitions = Vec::new();
+        for part in entry
+            .get("partitions")
+            .and_then(json::Value::as_array)
+            .unwrap_or_default()
+        {
+            let field = |key| part.get(key).and_then(json::Value::as_str).map(String::from);
+            let number = |key| part.get(key).and_then(json::Value::as_u64);
+            let part_name = field("name").ok_or_else(|| invalid("partition name"))?;
+            let part_size = number("size").ok_or_else(|| invalid("partition size"))?;
+
+            let mut partition = Partition::new(part_name, part_size / 512, None);
+            partition.used = number("used");
+            partition.free = number("free").or_else(|| partition.used.map(|used| part_size.saturating_sub(used)));
+            partition.mountpoint = field("mountpoint");
+            partition.fstype = field("fstype");
+            partitions.push(partition);
+        }
+
+        let mut drive = Drive::new(name, size / 512, partitions);
+        drive.origin = Some(Origin {
+            collector: collector.to_string(),
+            state: entry.get("state").and_then(json::Value::as_str).map(String::from),
+        });
+        drives.push(drive);
+    }
+    Ok(drives)
+}
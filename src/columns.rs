@@ -0,0 +1,153 @@
+/// A column of the partition rows in the pretty output, chosen with `--output`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Column {
+    Name,
+    /// The usage bar
+    Bar,
+    /// Sparkline of recorded usage, see `--history`
+    Trend,
+    /// Used and total size together, e.g. `1.2 / 4.0 GB`
+    Usage,
+    Fstype,
+    Size,
+    Used,
+    Avail,
+    UsePercent,
+    Mountpoint,
+}
+
+/// Cells of the `trend` sparkline.
+pub const TREND_WIDTH: usize = 14;
+
+/// The layout without `--output`.
+pub const DEFAULT: [Column; 4] = [Column::Name, Column::Bar, Column::Usage, Column::Mountpoint];
+
+pub const ALL: [Column; 10] = [
+    Column::Name,
+    Column::Bar,
+    Column::Trend,
+    Column::Usage,
+    Column::Fstype,
+    Column::Size,
+    Column::Used,
+    Column::Avail,
+    Column::UsePercent,
+    Column::Mountpoint,
+];
+
+impl Column {
+    pub fn parse(value: &str) -> Option<Column> {
+        ALL.into_iter().find(|column| column.name() == value)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Name => "name",
+            Column::Bar => "bar",
+            Column::Trend => "trend",
+            Column::Usage => "usage",
+            Column::Fstype => "fstype",
+            Column::Size => "size",
+            Column::Used => "used",
+            Column::Avail => "avail",
+            Column::UsePercent => "use%",
+            Column::Mountpoint => "mountpoint",
+        }
+    }
+
+    /// Numbers line up on the right like they do in df.
+    pub fn right_aligned(self) -> bool {
+        matches!(
+            self,
+            Column::Usage | Column::Size | Column::Used | Column::Avail | Column::UsePercent
+        )
+    }
+
+    /// Narrowest the column gets, so the default layout keeps its shape
+    /// across drives with short and long sizes.
+    pub fn min_width(self) -> usize {
+        match self {
+            Column::Usage => 18,
+            Column::UsePercent => 4,
+            Column::Trend => TREND_WIDTH,
+            _ => 0,
+        }
+    }
+}
+
+/// Parses a comma-separated list such as `name,size,use%,mountpoint`.
+pub fn parse_list(value: &str) -> Result<Vec<Column>, String> {
+    value
+        .split(',')
+        .map(|name| {
+            let name = name.trim().to_ascii_lowercase();
+            Column::parse(&name).ok_or_else(|| {
+                let known: Vec<&str> = ALL.iter().map(|c| c.name()).collect();
+                format!("unknown column '{}', expected one of: {}", name, known.join(", "))
+            })
+        })
+        .collect()
+}
+
+/// What the drives are ordered by, chosen with `--sort`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortKey {
+    Name,
+    Size,
+    /// Usage of the drive's fullest filesystem
+    Use,
+}
+
+const SORT_KEYS: [SortKey; 3] = [SortKey::Name, SortKey::Size, SortKey::Use];
+
+impl SortKey {
+    pub fn name(self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Size => "size",
+            SortKey::Use => "use",
+        }
+    }
+
+    /// The key after this one, back to the first after the last.
+    pub fn next(self) -> SortKey {
+        let i = SORT_KEYS.iter().position(|&key| key == self).unwrap_or(0);
+        SORT_KEYS[(i + 1) % SORT_KEYS.len()]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl Sort {
+    /// A key such as `size`, or `-size` for largest first.
+    pub fn parse(value: &str) -> Result<Sort, String> {
+        let (name, descending) = match value.trim().strip_prefix('-') {
+            Some(name) => (name, true),
+            None => (value.trim(), false),
+        };
+        let key = SORT_KEYS
+            .into_iter()
+            .find(|key| key.name() == name.to_ascii_lowercase())
+            .ok_or_else(|| {
+                let known: Vec<&str> = SORT_KEYS.iter().map(|k| k.name()).collect();
+                format!("unknown sort key '{}', expected one of: {}", name, known.join(", "))
+            })?;
+        Ok(Sort { key, descending })
+    }
+
+    pub fn name(self) -> String {
+        match self.descending {
+            true => format!("-{}", self.key.name()),
+            false => self.key.name().to_string(),
+        }
+    }
+}
+
+/// The comma-separated list `parse_list` reads back.
+pub fn join(columns: &[Column]) -> String {
+    columns.iter().map(|c| c.name()).collect::<Vec<_>>().join(",")
+}
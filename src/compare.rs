@@ -0,0 +1,156 @@
+use std::{collections::HashMap, fs::read_dir, path::Path};
+
+use colored::Colorize;
+
+use crate::{Drive, platform::linux, ptable, text, udev, units::Units};
+
+/// Filesystem identity of one partition, as needed to tell clones apart.
+struct Identity {
+    number: u32,
+    name: String,
+    size: u64,
+    type_name: Option<String>,
+    uuid: Option<String>,
+    label: Option<String>,
+}
+
+/// Maps each device the `/dev/disk/by-*` links in `dir` point at to the
+/// link name, for when udev's database is not readable.
+fn links(dir: &str) -> HashMap<String, String> {
+    read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let target = entry.path().canonicalize().ok()?;
+            let device = target.file_name()?.to_str()?.to_string();
+            let name = entry.file_name().to_str()?.replace("\\x20", " ");
+            Some((device, name))
+        })
+        .collect()
+}
+
+fn identities(drive: &mut Drive) -> Vec<Identity> {
+    let uuids = links("/dev/disk/by-uuid");
+    let labels = links("/dev/disk/by-label");
+
+    let mut identities: Vec<Identity> = drive
+        .partitions
+        .iter_mut()
+        .filter(|partition| partition.depth == 0)
+        .map(|partition| {
+            let dev = partition.dev_name().to_string();
+            let props = udev::properties(&partition.sysfs_dir());
+            Identity {
+                number: ptable::partition_number(&drive.name, &dev).unwrap_or(0),
+                size: partition.size * 512,
+                type_name: partition.table_entry.take().map(|e| e.type_name),
+                uuid: props.get("ID_FS_UUID").cloned().or_else(|| uuids.get(&dev).cloned()),
+                label: props.get("ID_FS_LABEL").cloned().or_else(|| labels.get(&dev).cloned()),
+                name: dev,
+            }
+        })
+        .collect();
+    identities.sort_by_key(|id| id.number);
+    identities
+}
+
+fn describe(id: Option<&Identity>, units: &Units) -> String {
+    let Some(id) = id else {
+        return "-".dimmed().to_string();
+    };
+    let mut fields = vec![
+        id.name.clone(),
+        format!("{:.2} {}", units.size.value(id.size), units.size),
+        id.type_name.clone().unwrap_or_else(|| "?".to_string()),
+    ];
+    if let Some(label) = &id.label {
+        fields.push(format!("\"{}\"", text::isolate(label)));
+    }
+    fields.push(id.uuid.clone().unwrap_or_else(|| "no uuid".to_string()));
+    fields.join("  ")
+}
+
+/// Prints the partition tables of two drives side by side, flagging size and
+/// type mismatches and filesystems that share a UUID. Returns false if either
+/// drive does not exist.
+pub fn run(first: &str, second: &str, units: &Units) -> bool {
+    let names = [first, second].map(|name| name.trim_start_matches("/dev/").to_string());
+    for name in &names {
+        if !Path::new(&format!("/sys/block/{}", name)).exists() {
+            eprintln!("pblk: no such drive '{}'", name);
+            return false;
+        }
+    }
+
+    let mut drives = names.map(|name| linux::drive(&name, &HashMap::new()));
+    ptable::annotate(&mut drives);
+
+    for drive in &drives {
+        println!(
+            "{} {} ({:.2} {}, {})",
+            "Drive:".bold().blue(),
+            drive.name.bold(),
+            units.size.value(drive.size * 512),
+            units.size,
+            drive
+                .table
+                .map(str::to_uppercase)
+                .unwrap_or_else(|| "no table".to_string()),
+        );
+    }
+    let mut problems = Vec::new();
+    if drives[0].table != drives[1].table {
+        problems.push("partition table types differ".to_string());
+    }
+    if drives[0].size != drives[1].size {
+        problems.push("drive sizes differ".to_string());
+    }
+    println!();
+
+    let [mut a, mut b] = drives;
+    let (a, b) = (identities(&mut a), identities(&mut b));
+    let mut numbers: Vec<u32> = a.iter().chain(&b).map(|id| id.number).collect();
+    numbers.sort();
+    numbers.dedup();
+
+    for number in numbers {
+        let left = a.iter().find(|id| id.number == number);
+        let right = b.iter().find(|id| id.number == number);
+
+        let mut notes = Vec::new();
+        match (left, right) {
+            (Some(l), Some(r)) => {
+                if l.size != r.size {
+                    notes.push("size mismatch".yellow().to_string());
+                }
+                if l.type_name != r.type_name {
+                    notes.push("type mismatch".yellow().to_string());
+                }
+                if l.uuid.is_some() && l.uuid == r.uuid {
+                    notes.push("identical UUID".red().to_string());
+                }
+            }
+            _ => notes.push("missing".yellow().to_string()),
+        }
+
+        println!("  {:>3}  {}", number, describe(left, units));
+        println!("       {}", describe(right, units));
+        if !notes.is_empty() {
+            println!("       {}", notes.join(", "));
+        }
+    }
+
+    // Clones often end up with a filesystem duplicated at a different slot
+    for l in &a {
+        for r in &b {
+            if l.number != r.number && l.uuid.is_some() && l.uuid == r.uuid {
+                problems.push(format!("{} and {} share a UUID", l.name, r.name));
+            }
+        }
+    }
+    for problem in problems {
+        println!("{} {}", "note:".yellow(), problem);
+    }
+    true
+}
@@ -0,0 +1,144 @@
+//! How much transparent compression saves: btrfs as measured by `compsize`
+//! and ZFS datasets as accounted by `zfs get`. NTFS keeps no totals, only
+//! per-file attributes, so it is not covered.
+
+use std::{process::Command, time::Duration};
+
+use crate::{Drive, child};
+
+/// compsize reads the extents of every file, which takes a while on large
+/// filesystems; slower runs of it or of zfs are given up on.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Savings {
+    /// The algorithms in use, e.g. `zstd` or `zstd, lzo`
+    pub algorithms: String,
+    /// Bytes of data as the files hold them
+    pub logical: u64,
+    /// Bytes they take up on disk
+    pub physical: u64,
+}
+
+impl Savings {
+    /// Share of the logical size that compression saves, negative when
+    /// compressed data took more room.
+    pub fn saved(&self) -> f64 {
+        1.0 - self.physical as f64 / self.logical.max(1) as f64
+    }
+}
+
+/// The stdout of `program` with `args`, `None` if it fails or takes longer
+/// than `timeout`.
+fn output(program: &'static str, args: Vec<String>, timeout: Duration) -> Option<String> {
+    let output = child::output(Command::new(program).args(args), timeout).ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parses `compsize -b` output: a `TOTAL` row of percentage, disk usage,
+/// uncompressed and referenced bytes, then one row per algorithm.
+fn parse_compsize(output: &str) -> Option<Savings> {
+    let rows: Vec<Vec<&str>> = output.lines().map(|line| line.split_whitespace().collect()).collect();
+    let total = rows.iter().find(|row| row.first() == Some(&"TOTAL"))?;
+    let algorithms: Vec<&str> = rows
+        .iter()
+        .skip_while(|row| row.first() != Some(&"TOTAL"))
+        .skip(1)
+        .filter_map(|row| row.first().copied())
+        .filter(|&algorithm| algorithm != "none" && algorithm != "prealloc")
+        .collect();
+    if algorithms.is_empty() {
+        return None;
+    }
+    Some(Savings {
+        algorithms: algorithms.join(", "),
+        logical: total.get(3)?.parse().ok()?,
+        physical: total.get(2)?.parse().ok()?,
+    })
+}
+
+/// Savings on the btrfs filesystem mounted at `mountpoint`, if any of its
+/// data is compressed. compsize needs root.
+fn btrfs(mountpoint: &str) -> Option<Savings> {
+    let args = vec!["-b".to_string(), "-x".to_string(), mountpoint.to_string()];
+    parse_compsize(&output("compsize", args, TIMEOUT)?)
+}
+
+/// Parses `zfs get -Hp -o property,value compression,logicalused,used`.
+#[cfg_attr(not(feature = "zfs"), allow(dead_code))]
+fn parse_zfs(output: &str) -> Option<Savings> {
+    let value = |property: &str| {
+        output
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .find(|(name, _)| *name == property)
+            .map(|(_, value)| value.trim())
+    };
+    let algorithm = value("compression").filter(|&algorithm| algorithm != "off")?;
+    Some(Savings {
+        algorithms: algorithm.to_string(),
+        logical: value("logicalused")?.parse().ok()?,
+        physical: value("used")?.parse().ok()?,
+    })
+}
+
+/// Savings across the datasets of the ZFS pool `pool`, from its root dataset.
+#[cfg_attr(not(feature = "zfs"), allow(dead_code))]
+pub fn zfs(pool: &str) -> Option<Savings> {
+    let args = [
+        "get",
+        "-Hp",
+        "-o",
+        "property,value",
+        "compression,logicalused,used",
+        pool,
+    ];
+    parse_zfs(&output("zfs", args.map(String::from).to_vec(), TIMEOUT)?)
+}
+
+/// Measures the savings of each mounted btrfs filesystem, once per
+/// filesystem for pools spanning several devices.
+pub fn annotate(drives: &mut [Drive]) {
+    for partition in drives.iter_mut().flat_map(|d| d.partitions.iter_mut()) {
+        if partition.fstype.as_deref() != Some("btrfs") || partition.pool.as_ref().is_some_and(|p| !p.primary) {
+            continue;
+        }
+        if let Some(mountpoint) = partition.mountpoint.clone() {
+            partition.compression = btrfs(&mountpoint);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compsize_and_zfs_totals() {
+        let compsize = "\
+Processed 3356 files, 1434 regular extents (1434 refs), 2027 inline.
+Type       Perc     Disk Usage   Uncompressed Referenced
+TOTAL       60%      213741768    354347827    354347827
+none       100%      100000000    100000000    100000000
+zstd        44%      113741768    254347827    254347827
+";
+        let savings = parse_compsize(compsize).unwrap();
+        assert_eq!(savings.algorithms, "zstd");
+        assert!((savings.saved() - 0.397).abs() < 0.001);
+        assert_eq!(parse_compsize(&compsize.replace("zstd", "none")), None);
+
+        let zfs = "compression\tlz4\nlogicalused\t3000\nused\t2000\n";
+        assert_eq!(
+            parse_zfs(zfs),
+            Some(Savings {
+                algorithms: "lz4".to_string(),
+                logical: 3000,
+                physical: 2000
+            })
+        );
+        assert_eq!(parse_zfs(&zfs.replace("lz4", "off")), None);
+    }
+}
@@ -0,0 +1,961 @@
+use std::{
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    PrettyblkError,
+    cli::{ConfigAction, Format, Paging},
+    collectors::Collector,
+    columns::{self, Column, Sort},
+    severity::{Combine, Rule, Thresholds},
+    theme::{Depth, Theme},
+    units::{self, DEFAULT_SIZE_UNIT, SizeUnit, TemperatureUnit, Units},
+};
+
+const SYSTEM_CONFIG: &str = "/etc/prettyblk/config.toml";
+
+/// The longest span of history that can be kept or looked back over.
+pub const MAX_HISTORY_DAYS: u64 = 100 * 365;
+
+pub const STARTER: &str = r#"# prettyblk configuration
+#
+# Settings are read from /etc/prettyblk/config.toml and then from this file;
+# later files override earlier ones and command line flags override both.
+
+[display]
+# Output format when --format is not given: pretty, csv, tsv, env, kv
+# format = "pretty"
+
+# When the charts go through $PAGER (--paging): auto when they do not fit
+# on the terminal, always or never
+# paging = "auto"
+
+# Upper bound for the drive chart width in columns
+# max_width = 100
+
+# Draw a small ASCII picture and the model beside each drive (--glyphs)
+# glyphs = false
+
+# Colors the terminal supports: 16, 256 or truecolor; detected from
+# COLORTERM and TERM when not set
+# colors = "256"
+
+# "high-contrast" uses bright colors, no dimmed text and spells out
+# warning and critical filesystems, for low vision and projectors
+# theme = "default"
+
+# Columns of each partition row (--output) and what the drives are ordered
+# by (--sort): name, size or use, with a leading "-" for descending. Both
+# can be changed in --watch, where w saves them here
+# columns = "name,bar,usage,mountpoint"
+# sort = "-use"
+
+[units]
+# Units per column: B, KB, MB, GB, TB (powers of 1000) or KiB, MiB, GiB, TiB
+# (powers of 1024). Unset columns keep the classic binary sizes labelled "GB".
+# Capacity of drives and partitions
+# size = "GiB"
+# Used and free space
+# used = "MiB"
+# Throughput, e.g. of pblk bench, per second [default: MB]
+# rate = "MiB"
+# Drive temperatures from --smart: C or F [default: C]
+# temperature = "F"
+
+[thresholds]
+# Usage bars turn yellow at `warn` and red at `critical` percent used.
+# `warn_free` / `critical_free` fire when less than that much space is free.
+# With combine = "any" either kind of limit raises the level; with "all" a
+# level needs both, which keeps huge disks at 95% but with 800G free quiet.
+# warn = 80
+# critical = 90
+# warn_free = "20G"
+# critical_free = "5G"
+# combine = "any"
+
+# Mounts that are full by design are shown but never flagged. Entries are
+# mountpoint globs; filesystem types match exactly.
+# expected_full = ["/snap/*", "/media/backup-archive"]
+# expected_full_fstypes = ["squashfs", "iso9660", "udf", "erofs"]
+
+# Per-mountpoint overrides inherit unset limits from [thresholds]
+# [thresholds."/var/lib/docker"]
+# warn = 70
+#
+# [thresholds."/boot"]
+# critical_free = "50M"
+
+[scan]
+# How long to wait for statvfs on a mount (e.g. unresponsive NFS) before
+# showing it without usage
+# timeout_ms = 2000
+
+[history]
+# Days of history plotted by `pblk history` when --days is not given
+# days = 7
+
+# Where `--record` stores usage samples
+# file = "~/.local/share/prettyblk/history.tsv"
+
+[history.retention]
+# Days to keep every sample, then hourly and daily averages; older samples
+# are compacted each time one is recorded
+# raw_days = 7
+# hourly_days = 90
+# daily_days = 730
+
+# [collectors.megaraid]
+# Command printing {"drives": [...]} JSON for drives sysfs cannot see, such
+# as the disks behind a hardware RAID controller; see the README
+# command = ["/usr/local/libexec/pblk-storcli"]
+# timeout_ms = 10000
+
+[power]
+# While on battery, --watch refreshes this many times less often
+# battery_slowdown = 3
+# and external collectors are not run
+# battery_skip_collectors = true
+
+[audit]
+# Append a JSON line per detected problem (threshold, low system space,
+# unresponsive mount, failed multipath path) to this file on every run
+# file = "/var/log/prettyblk/audit.jsonl"
+
+# Known and accepted problems, as "check:device" or "check:mountpoint"
+# like `pblk --problems` prints them. They no longer show up there, and
+# the audit log keeps them marked as acknowledged.
+# acknowledged = ["usage.warning:/backup"]
+"#;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    List(Vec<Value>),
+}
+
+/// A single `key = value` assignment together with the table it appeared in.
+pub struct Entry {
+    pub table: Vec<String>,
+    pub key: String,
+    pub value: Value,
+    pub line: usize,
+}
+
+/// How many days history samples are kept at each resolution.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Retention {
+    pub raw_days: u64,
+    pub hourly_days: u64,
+    pub daily_days: u64,
+}
+
+impl Default for Retention {
+    fn default() -> Retention {
+        Retention {
+            raw_days: 7,
+            hourly_days: 90,
+            daily_days: 730,
+        }
+    }
+}
+
+/// What changes while a laptop runs on battery.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Battery {
+    /// Factor the --watch refresh interval is stretched by
+    pub slowdown: f64,
+    pub skip_collectors: bool,
+}
+
+impl Default for Battery {
+    fn default() -> Battery {
+        Battery {
+            slowdown: 3.0,
+            skip_collectors: true,
+        }
+    }
+}
+
+pub struct Config {
+    pub format: Format,
+    pub paging: Paging,
+    pub max_width: usize,
+    pub color_depth: Option<Depth>,
+    pub theme: Theme,
+    pub glyphs: bool,
+    /// Partition row columns when --output is not given
+    pub columns: Option<Vec<Column>>,
+    pub sort: Option<Sort>,
+    pub history_days: u64,
+    pub history_file: Option<PathBuf>,
+    pub history_retention: Retention,
+    pub audit_file: Option<PathBuf>,
+    /// Problem ids that `--problems` leaves out
+    pub acknowledged: Vec<String>,
+    pub collectors: Vec<Collector>,
+    pub battery: Battery,
+    pub units: Units,
+    pub scan_timeout: Duration,
+    pub thresholds: Thresholds,
+    pub sources: Vec<PathBuf>,
+    /// Where fstab and crypttab are read from, `/` unless --chroot
+    pub root: PathBuf,
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Str(s) => write!(f, "\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::List(items) => {
+                let items: Vec<String> = items.iter().map(|v| v.to_string()).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+        }
+    }
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Int(i) => Some(*i as f64),
+            Value::Float(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// A byte count given either as an integer or a size string like "50M".
+    pub fn as_size(&self) -> Option<u64> {
+        match self {
+            Value::Int(i) if *i >= 0 => Some(*i as u64),
+            Value::Str(s) => units::parse_size(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            format: Format::Pretty,
+            paging: Paging::Auto,
+            max_width: 100,
+            color_depth: None,
+            theme: Theme::Default,
+            glyphs: false,
+            columns: None,
+            sort: None,
+            history_days: 7,
+            history_file: None,
+            history_retention: Retention::default(),
+            audit_file: None,
+            acknowledged: Vec::new(),
+            collectors: Vec::new(),
+            battery: Battery::default(),
+            units: Units::default(),
+            scan_timeout: Duration::from_secs(2),
+            thresholds: Thresholds::default(),
+            sources: Vec::new(),
+            root: PathBuf::from("/"),
+        }
+    }
+}
+
+impl Config {
+    fn apply(&mut self, path: &Path, entries: &[Entry]) -> Result<(), PrettyblkError> {
+        for entry in entries {
+            let error = |message: String| PrettyblkError::Config {
+                path: path.to_path_buf(),
+                line: entry.line,
+                message,
+            };
+            let expected = |kind: &str| error(format!("{} must be {}", entry.key, kind));
+            let table: Vec<&str> = entry.table.iter().map(String::as_str).collect();
+
+            match (table.as_slice(), entry.key.as_str()) {
+                (["display"], "format") => {
+                    let value = entry.value.as_str().ok_or_else(|| expected("a string"))?;
+                    self.format = Format::parse(value).ok_or_else(|| error(format!("unknown format '{}'", value)))?;
+                }
+                (["display"], "paging") => {
+                    let value = entry.value.as_str().ok_or_else(|| expected("a string"))?;
+                    self.paging = Paging::parse(value).ok_or_else(|| {
+                        error(format!(
+                            "paging must be \"auto\", \"always\" or \"never\", not '{}'",
+                            value
+                        ))
+                    })?;
+                }
+                (["display"], "glyphs") => {
+                    self.glyphs = match entry.value {
+                        Value::Bool(glyphs) => glyphs,
+                        _ => return Err(expected("true or false")),
+                    };
+                }
+                (["display"], "colors") => {
+                    let value = entry.value.as_str().ok_or_else(|| expected("a string"))?;
+                    self.color_depth = Some(Depth::parse(value).ok_or_else(|| {
+                        error(format!(
+                            "colors must be \"16\", \"256\" or \"truecolor\", not '{}'",
+                            value
+                        ))
+                    })?);
+                }
+                (["display"], "theme") => {
+                    let value = entry.value.as_str().ok_or_else(|| expected("a string"))?;
+                    self.theme = Theme::parse(value).ok_or_else(|| {
+                        error(format!(
+                            "theme must be \"default\" or \"high-contrast\", not '{}'",
+                            value
+                        ))
+                    })?;
+                }
+                (["display"], "columns") => {
+                    let value = entry.value.as_str().ok_or_else(|| expected("a string"))?;
+                    self.columns = Some(columns::parse_list(value).map_err(error)?);
+                }
+                (["display"], "sort") => {
+                    let value = entry.value.as_str().ok_or_else(|| expected("a string"))?;
+                    self.sort = Some(Sort::parse(value).map_err(error)?);
+                }
+                (["display"], "max_width") => {
+                    self.max_width = entry
+                        .value
+                        .as_int()
+                        .filter(|w| *w > 0)
+                        .ok_or_else(|| expected("a positive integer"))? as usize;
+                }
+                (["history"], "days") => {
+                    self.history_days = entry
+                        .value
+                        .as_int()
+                        .filter(|d| (1..=MAX_HISTORY_DAYS as i64).contains(d))
+                        .ok_or_else(|| expected(&format!("a number of days from 1 to {}", MAX_HISTORY_DAYS)))?
+                        as u64;
+                }
+                (["history"], "file") => {
+                    let value = entry.value.as_str().ok_or_else(|| expected("a string"))?;
+                    self.history_file = Some(expand_home(value));
+                }
+                (["history", "retention"], key @ ("raw_days" | "hourly_days" | "daily_days")) => {
+                    let days = entry
+                        .value
+                        .as_int()
+                        .filter(|d| (1..=MAX_HISTORY_DAYS as i64).contains(d))
+                        .ok_or_else(|| expected(&format!("a number of days from 1 to {}", MAX_HISTORY_DAYS)))?
+                        as u64;
+                    let retention = &mut self.history_retention;
+                    match key {
+                        "raw_days" => retention.raw_days = days,
+                        "hourly_days" => retention.hourly_days = days,
+                        _ => retention.daily_days = days,
+                    }
+                }
+                (["collectors", name], key @ ("command" | "timeout_ms")) => {
+                    let index = match self.collectors.iter().position(|c| c.name == *name) {
+                        Some(index) => index,
+                        None => {
+                            self.collectors.push(Collector::new(name));
+                            self.collectors.len() - 1
+                        }
+                    };
+                    let collector = &mut self.collectors[index];
+                    match key {
+                        "command" => {
+                            collector.command = match &entry.value {
+                                Value::List(items) if !items.is_empty() => items
+                                    .iter()
+                                    .map(|item| item.as_str().map(String::from))
+                                    .collect::<Option<Vec<String>>>(),
+                                _ => None,
+                            }
+                            .ok_or_else(|| expected("a non-empty list of strings"))?;
+                        }
+                        _ => {
+                            let ms = entry
+                                .value
+                                .as_int()
+                                .filter(|ms| *ms > 0)
+                                .ok_or_else(|| expected("a positive integer"))?;
+                            collector.timeout = Duration::from_millis(ms as u64);
+                        }
+                    }
+                }
+                (["audit"], "file") => {
+                    let value = entry.value.as_str().ok_or_else(|| expected("a string"))?;
+                    self.audit_file = Some(expand_home(value));
+                }
+                (["audit"], "acknowledged") => {
+                    self.acknowledged = match &entry.value {
+                        Value::List(items) => items
+                            .iter()
+                            .map(|item| item.as_str().map(String::from))
+                            .collect::<Option<Vec<String>>>(),
+                        _ => None,
+                    }
+                    .ok_or_else(|| expected("a list of strings"))?;
+                }
+                (["power"], "battery_slowdown") => {
+                    self.battery.slowdown = entry
+                        .value
+                        .as_float()
+                        .filter(|factor| (1.0..=100.0).contains(factor))
+                        .ok_or_else(|| expected("a number from 1 to 100"))?;
+                }
+                (["power"], "battery_skip_collectors") => {
+                    self.battery.skip_collectors = match entry.value {
+                        Value::Bool(skip) => skip,
+                        _ => return Err(expected("true or false")),
+                    };
+                }
+                (["scan"], "timeout_ms") => {
+                    let ms = entry
+                        .value
+                        .as_int()
+                        .filter(|ms| *ms > 0)
+                        .ok_or_else(|| expected("a positive integer"))?;
+                    self.scan_timeout = Duration::from_millis(ms as u64);
+                }
+                (["thresholds"], key @ ("expected_full" | "expected_full_fstypes")) => {
+                    let list = match &entry.value {
+                        Value::List(items) => items
+                            .iter()
+                            .map(|item| item.as_str().map(String::from))
+                            .collect::<Option<Vec<String>>>(),
+                        _ => None,
+                    }
+                    .ok_or_else(|| expected("a list of strings"))?;
+                    match key {
+                        "expected_full" => self.thresholds.expected_full = list,
+                        _ => self.thresholds.expected_full_fstypes = list,
+                    }
+                }
+                (["thresholds", mount @ ..], key) if mount.len() <= 1 => {
+                    let rule = match mount.first() {
+                        None => &mut self.thresholds.default,
+                        Some(path) => {
+                            let mounts = &mut self.thresholds.mounts;
+                            let index = match mounts.iter().position(|(p, _)| p == path) {
+                                Some(index) => index,
+                                None => {
+                                    mounts.push((path.to_string(), Rule::default()));
+                                    mounts.len() - 1
+                                }
+                            };
+                            &mut mounts[index].1
+                        }
+                    };
+                    let percent = || {
+                        entry
+                            .value
+                            .as_float()
+                            .filter(|p| (0.0..=100.0).contains(p))
+                            .ok_or_else(|| expected("a percentage between 0 and 100"))
+                    };
+                    let size = || {
+                        entry
+                            .value
+                            .as_size()
+                            .ok_or_else(|| expected("a size such as \"50M\" or a byte count"))
+                    };
+                    match key {
+                        "warn" => rule.warn = Some(percent()?),
+                        "critical" => rule.critical = Some(percent()?),
+                        "warn_free" => rule.warn_free = Some(size()?),
+                        "critical_free" => rule.critical_free = Some(size()?),
+                        "combine" => {
+                            let value = entry.value.as_str().ok_or_else(|| expected("a string"))?;
+                            rule.combine = Some(Combine::parse(value).ok_or_else(|| {
+                                error(format!("combine must be \"any\" or \"all\", not '{}'", value))
+                            })?);
+                        }
+                        _ => return Err(error(format!("unknown threshold '{}'", key))),
+                    }
+                }
+                (["units"], key @ ("size" | "used" | "rate")) => {
+                    let value = entry.value.as_str().ok_or_else(|| expected("a string"))?;
+                    let unit = SizeUnit::parse(value).ok_or_else(|| error(format!("unknown size unit '{}'", value)))?;
+                    match key {
+                        "size" => self.units.size = unit,
+                        "used" => self.units.used = unit,
+                        _ => self.units.rate = unit,
+                    }
+                }
+                (["units"], "temperature") => {
+                    let value = entry.value.as_str().ok_or_else(|| expected("a string"))?;
+                    self.units.temperature = TemperatureUnit::parse(value)
+                        .ok_or_else(|| error(format!("unknown temperature unit '{}', expected C or F", value)))?;
+                }
+                _ => warn!(
+                    "pblk: {}:{}: ignoring unknown setting {}",
+                    path.display(),
+                    entry.line,
+                    qualified_key(entry)
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// The configured history file, or the default under the XDG data dir
+    /// (the local app data dir on Windows).
+    pub fn history_path(&self) -> PathBuf {
+        self.history_file.clone().unwrap_or_else(|| {
+            env::var_os("XDG_DATA_HOME")
+                .map(PathBuf::from)
+                .filter(|path| path.is_absolute())
+                .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+                .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join("prettyblk")
+                .join("history.tsv")
+        })
+    }
+
+    /// Renders the effective settings in config file syntax.
+    pub fn to_toml(&self) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        let str_value = |s: &str| Value::Str(s.to_string());
+        // The classic unit has no config name, so it is shown commented out
+        let unit = |key: &str, unit: SizeUnit| match unit {
+            DEFAULT_SIZE_UNIT => format!("# {} = (classic GB)", key),
+            _ => format!("{} = {}", key, str_value(unit.label)),
+        };
+        let rule = |out: &mut String, rule: &Rule| {
+            let mut line = |key: &str, value: Option<String>| {
+                if let Some(value) = value {
+                    let _ = writeln!(out, "{} = {}", key, value);
+                }
+            };
+            line("warn", rule.warn.map(|p| p.to_string()));
+            line("critical", rule.critical.map(|p| p.to_string()));
+            line("warn_free", rule.warn_free.map(|b| b.to_string()));
+            line("critical_free", rule.critical_free.map(|b| b.to_string()));
+            line("combine", rule.combine.map(|c| str_value(c.name()).to_string()));
+        };
+
+        let _ = writeln!(out, "[display]");
+        let _ = writeln!(out, "format = {}", str_value(self.format.name()));
+        let _ = writeln!(out, "paging = {}", str_value(self.paging.name()));
+        let _ = writeln!(out, "max_width = {}", self.max_width);
+        let _ = writeln!(out, "glyphs = {}", self.glyphs);
+        match self.color_depth {
+            Some(depth) => {
+                let _ = writeln!(out, "colors = {}", str_value(depth.name()));
+            }
+            None => {
+                let _ = writeln!(out, "# colors = (detected: {})", Depth::detect().name());
+            }
+        }
+        let _ = writeln!(out, "theme = {}", str_value(self.theme.name()));
+        let columns = self.columns.as_deref().unwrap_or(&columns::DEFAULT);
+        let _ = writeln!(out, "columns = {}", str_value(&columns::join(columns)));
+        match self.sort {
+            Some(sort) => {
+                let _ = writeln!(out, "sort = {}", str_value(&sort.name()));
+            }
+            None => {
+                let _ = writeln!(out, "# sort = (in the order the system lists them)");
+            }
+        }
+
+        let _ = writeln!(out, "\n[units]");
+        let _ = writeln!(out, "{}", unit("size", self.units.size));
+        let _ = writeln!(out, "{}", unit("used", self.units.used));
+        let _ = writeln!(out, "{}", unit("rate", self.units.rate));
+        let _ = writeln!(out, "temperature = {}", str_value(self.units.temperature.name()));
+
+        let list = |items: &[String]| Value::List(items.iter().map(|i| str_value(i)).collect());
+        let _ = writeln!(out, "\n[thresholds]");
+        rule(&mut out, &self.thresholds.default);
+        let _ = writeln!(out, "expected_full = {}", list(&self.thresholds.expected_full));
+        let _ = writeln!(
+            out,
+            "expected_full_fstypes = {}",
+            list(&self.thresholds.expected_full_fstypes)
+        );
+        for (mountpoint, mount_rule) in &self.thresholds.mounts {
+            let _ = writeln!(out, "\n[thresholds.{}]", str_value(mountpoint));
+            rule(&mut out, mount_rule);
+        }
+
+        let _ = writeln!(out, "\n[scan]");
+        let _ = writeln!(out, "timeout_ms = {}", self.scan_timeout.as_millis());
+
+        let _ = writeln!(out, "\n[history]");
+        let _ = writeln!(out, "days = {}", self.history_days);
+        let _ = writeln!(out, "file = {}", str_value(&self.history_path().display().to_string()));
+
+        let _ = writeln!(out, "\n[history.retention]");
+        let _ = writeln!(out, "raw_days = {}", self.history_retention.raw_days);
+        let _ = writeln!(out, "hourly_days = {}", self.history_retention.hourly_days);
+        let _ = writeln!(out, "daily_days = {}", self.history_retention.daily_days);
+
+        for collector in &self.collectors {
+            let _ = writeln!(out, "\n[collectors.{}]", str_value(&collector.name));
+            let _ = writeln!(out, "command = {}", list(&collector.command));
+            let _ = writeln!(out, "timeout_ms = {}", collector.timeout.as_millis());
+        }
+
+        let _ = writeln!(out, "\n[power]");
+        let _ = writeln!(out, "battery_slowdown = {}", Value::Float(self.battery.slowdown));
+        let _ = writeln!(out, "battery_skip_collectors = {}", self.battery.skip_collectors);
+
+        let _ = writeln!(out, "\n[audit]");
+        match &self.audit_file {
+            Some(path) => {
+                let _ = writeln!(out, "file = {}", str_value(&path.display().to_string()));
+            }
+            None => {
+                let _ = writeln!(out, "# file = (disabled)");
+            }
+        }
+        let _ = writeln!(out, "acknowledged = {}", list(&self.acknowledged));
+        out
+    }
+}
+
+/// Config files in the order they are merged; later files win.
+pub fn search_paths() -> Vec<PathBuf> {
+    vec![PathBuf::from(SYSTEM_CONFIG), user_config_path()]
+}
+
+pub fn user_config_path() -> PathBuf {
+    if let Some(path) = env::var_os("PRETTYBLK_CONFIG") {
+        return PathBuf::from(path);
+    }
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("prettyblk")
+        .join("config.toml")
+}
+
+pub fn load() -> Result<Config, PrettyblkError> {
+    let mut config = Config::default();
+
+    for path in search_paths() {
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                return Err(PrettyblkError::Config {
+                    path,
+                    line: 0,
+                    message: e.to_string(),
+                });
+            }
+        };
+        let entries = parse(&text).map_err(|(line, message)| PrettyblkError::Config {
+            path: path.clone(),
+            line,
+            message,
+        })?;
+        config.apply(&path, &entries)?;
+        config.sources.push(path);
+    }
+
+    Ok(config)
+}
+
+pub fn run(action: &ConfigAction, config: &Config, force: bool) {
+    match action {
+        ConfigAction::Show => {
+            if config.sources.is_empty() {
+                println!("# no config files found, showing defaults");
+            }
+            for source in &config.sources {
+                println!("# source: {}", source.display());
+            }
+            println!("{}", config.to_toml());
+        }
+        ConfigAction::Path => {
+            for path in search_paths() {
+                let state = if config.sources.contains(&path) {
+                    "loaded"
+                } else {
+                    "not found"
+                };
+                println!("{} ({})", path.display(), state);
+            }
+        }
+        ConfigAction::Init => match init(force) {
+            Ok(path) => println!("Wrote starter config to {}", path.display()),
+            Err(e) => {
+                eprintln!("pblk: {}", e);
+                std::process::exit(1);
+            }
+        },
+    }
+}
+
+/// Sets each `key = value` of `settings` in the `[display]` table of the
+/// user config, replacing the line already setting it and keeping the rest
+/// of the file as it is. The file and the table are created when missing.
+pub fn save_display(settings: &[(&str, Value)]) -> io::Result<PathBuf> {
+    let path = user_config_path();
+    let mut text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    for (key, value) in settings {
+        let entries = parse(&text).map_err(|(line, message)| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{}: {}", path.display(), line, message),
+            )
+        })?;
+        let mut lines: Vec<String> = text.lines().map(String::from).collect();
+        let setting = format!("{} = {}", key, value);
+        let display: Vec<&Entry> = entries.iter().filter(|e| e.table == ["display"]).collect();
+        match display.iter().find(|e| e.key == *key) {
+            Some(entry) => lines[entry.line - 1] = setting,
+            None => match display.last().map(|e| e.line).or_else(|| {
+                lines
+                    .iter()
+                    .position(|line| strip_comment(line).trim() == "[display]")
+                    .map(|header| header + 1)
+            }) {
+                Some(after) => lines.insert(after, setting),
+                None => {
+                    if !lines.is_empty() {
+                        lines.push(String::new());
+                    }
+                    lines.extend(["[display]".to_string(), setting]);
+                }
+            },
+        }
+        text = lines.join("\n") + "\n";
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, text)?;
+    Ok(path)
+}
+
+/// Writes the commented starter config to the user config path.
+pub fn init(force: bool) -> io::Result<PathBuf> {
+    let path = user_config_path();
+    if path.exists() && !force {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists (use --force to overwrite)", path.display()),
+        ));
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, STARTER)?;
+    Ok(path)
+}
+
+fn qualified_key(entry: &Entry) -> String {
+    let mut parts = entry.table.clone();
+    parts.push(entry.key.clone());
+    parts.join(".")
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Parses the small TOML subset prettyblk uses: `[table]` / `[a."b.c"]`
+/// headers and `key = value` lines with strings, numbers, booleans and
+/// single-line arrays.
+pub fn parse(text: &str) -> Result<Vec<Entry>, (usize, String)> {
+    let mut entries = Vec::new();
+    let mut table = Vec::new();
+
+    for (i, raw) in text.lines().enumerate() {
+        let line_no = i + 1;
+        let line = strip_comment(raw).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header
+                .strip_suffix(']')
+                .ok_or((line_no, "unterminated table header".to_string()))?;
+            table = split_key(header).map_err(|e| (line_no, e))?;
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or((line_no, "expected `key = value`".to_string()))?;
+        let key = split_key(key.trim()).map_err(|e| (line_no, e))?;
+        let value = parse_value(value.trim()).map_err(|e| (line_no, e))?;
+        let (last, prefix) = key.split_last().ok_or((line_no, "empty key".to_string()))?;
+
+        let mut full_table = table.clone();
+        full_table.extend_from_slice(prefix);
+        entries.push(Entry {
+            table: full_table,
+            key: last.clone(),
+            value,
+            line: line_no,
+        });
+    }
+
+    Ok(entries)
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '\\' if in_string => escaped = !escaped,
+            '"' if !escaped => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => escaped = false,
+        }
+    }
+    line
+}
+
+fn split_key(key: &str) -> Result<Vec<String>, String> {
+    let mut parts = Vec::new();
+    let mut rest = key.trim();
+
+    while !rest.is_empty() {
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').ok_or("unterminated quoted key")?;
+            parts.push(quoted[..end].to_string());
+            rest = quoted[end + 1..].trim_start();
+        } else {
+            let end = rest.find('.').unwrap_or(rest.len());
+            let part = rest[..end].trim();
+            if part.is_empty() {
+                return Err(format!("invalid key '{}'", key));
+            }
+            parts.push(part.to_string());
+            rest = &rest[end..];
+        }
+        if let Some(after) = rest.strip_prefix('.') {
+            rest = after.trim_start();
+        } else if !rest.is_empty() {
+            return Err(format!("invalid key '{}'", key));
+        }
+    }
+
+    Ok(parts)
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    if let Some(inner) = text.strip_prefix('[') {
+        let inner = inner.strip_suffix(']').ok_or("unterminated array")?;
+        return split_array(inner)
+            .into_iter()
+            .map(|item| parse_value(item.trim()))
+            .collect::<Result<_, _>>()
+            .map(Value::List);
+    }
+    if let Some(inner) = text.strip_prefix('"') {
+        let inner = inner.strip_suffix('"').ok_or("unterminated string")?;
+        return Ok(Value::Str(unescape(inner)));
+    }
+    match text {
+        "true" => return Ok(Value::Bool(true)),
+        "false" => return Ok(Value::Bool(false)),
+        _ => {}
+    }
+    let digits = text.replace('_', "");
+    if let Ok(i) = digits.parse() {
+        return Ok(Value::Int(i));
+    }
+    if let Ok(x) = digits.parse() {
+        return Ok(Value::Float(x));
+    }
+    Err(format!("invalid value '{}'", text))
+}
+
+fn split_array(inner: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, c) in inner.char_indices() {
+        match c {
+            '\\' if in_string => escaped = !escaped,
+            '"' if !escaped => in_string = !in_string,
+            ',' if !in_string => {
+                items.push(&inner[start..i]);
+                start = i + 1;
+            }
+            _ => escaped = false,
+        }
+    }
+    items.push(&inner[start..]);
+    items.retain(|item| !item.trim().is_empty());
+    items
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_and_temperatures_have_units_of_their_own() {
+        let entries = parse("[units]\nrate = \"MiB\"\ntemperature = \"°F\"\n").unwrap();
+        let mut config = Config::default();
+        assert!(config.apply(Path::new("config.toml"), &entries).is_ok());
+        assert!(config.units.rate == SizeUnit::parse("MiB").unwrap());
+        assert_eq!(config.units.temperature, TemperatureUnit::Fahrenheit);
+        assert!(config.units.size == DEFAULT_SIZE_UNIT);
+
+        let entries = parse("[units]\ntemperature = \"K\"\n").unwrap();
+        assert!(Config::default().apply(Path::new("config.toml"), &entries).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+//! Device-mapper snapshots, including LVM snapshots: which device each one
+//! was taken of and how full its copy-on-write space is. A snapshot whose
+//! copy-on-write space runs out is invalidated by the kernel.
+
+use crate::{
+    Drive,
+    platform::linux::{block_name, dm_device, dm_maps, dmsetup, sysfs_links},
+    privilege,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum State {
+    /// Sectors of copy-on-write space in use, out of `total`
+    Active { used: u64, total: u64 },
+    /// The kernel's reason, e.g. `Invalid` or `Overflow`; reads now fail
+    Invalid(String),
+}
+
+#[derive(Clone)]
+pub struct Snapshot {
+    /// The `dm-N` of the snapshot
+    pub device: String,
+    /// The snapshot's map name, e.g. `vg0-root_snap`
+    pub name: String,
+    /// The map name of the device it was taken of, e.g. `vg0-root`
+    pub origin: String,
+    /// Kernel name of the origin
+    origin_device: String,
+    pub state: State,
+}
+
+impl Snapshot {
+    /// Share of the copy-on-write space in use, `None` once invalid.
+    pub fn ratio(&self) -> Option<f64> {
+        match self.state {
+            State::Active { used, total } => Some(used as f64 / total.max(1) as f64),
+            State::Invalid(_) => None,
+        }
+    }
+}
+
+/// The state from a `dmsetup status` line of a snapshot:
+/// `name: start length snapshot used/total metadata`, or the reason in
+/// place of the counts once the snapshot stopped working.
+fn parse_status(status: &str) -> Option<State> {
+    let field = status.split_whitespace().nth(4)?;
+    match field.split_once('/') {
+        Some((used, total)) => Some(State::Active {
+            used: used.parse().ok()?,
+            total: total.parse().ok()?,
+        }),
+        None if field == "Unknown" => None,
+        None => Some(State::Invalid(field.to_string())),
+    }
+}
+
+/// The device a snapshot was taken of, from the origin in its table. LVM
+/// moves the origin's data to a hidden `<lv>-real` map when the first
+/// snapshot is taken; the volume seen by users is the `<lv>` stacked on it.
+fn origin(device: &str, maps: &[(String, String)]) -> Option<(String, String)> {
+    let name = block_name(device)?;
+    let map = |name: &str| {
+        maps.iter()
+            .find(|(device, _)| device == name)
+            .map(|(_, map)| map.clone())
+    };
+    let Some(real) = map(&name) else {
+        return Some((name.clone(), name));
+    };
+    let visible = real.strip_suffix("-real").and_then(|lv| {
+        sysfs_links(&format!("/sys/block/{}/holders", name))
+            .into_iter()
+            .find(|holder| map(holder).as_deref() == Some(lv))
+            .map(|holder| (holder, lv.to_string()))
+    });
+    Some(visible.unwrap_or((name, real)))
+}
+
+/// One snapshot from its `dmsetup table` line,
+/// `name: start length snapshot origin cow persistent chunk_size`.
+fn snapshot(table: &str, status: &str, maps: &[(String, String)]) -> Option<Snapshot> {
+    let table: Vec<&str> = table.split_whitespace().collect();
+    let name = table.first()?.strip_suffix(':')?;
+    let (origin_device, origin) = origin(table.get(4)?, maps)?;
+    Some(Snapshot {
+        device: dm_device(name)?,
+        name: name.to_string(),
+        origin,
+        origin_device,
+        state: parse_status(status)?,
+    })
+}
+
+/// All snapshots, if there are any and dmsetup can tell.
+fn snapshots(maps: &[(String, String)]) -> Vec<Snapshot> {
+    let (Some(tables), Some(statuses)) = (dmsetup("table", "snapshot"), dmsetup("status", "snapshot")) else {
+        // LVM names the copy-on-write store of each snapshot `<snapshot>-cow`
+        if !privilege::is_root() && maps.iter().any(|(_, map)| map.ends_with("-cow")) {
+            privilege::deny("snapshot usage".to_string());
+        }
+        return Vec::new();
+    };
+    tables
+        .lines()
+        .filter_map(|table| {
+            let map = table.split_whitespace().next()?;
+            let status = statuses
+                .lines()
+                .find(|line| line.split_whitespace().next() == Some(map))?;
+            snapshot(table, status, maps)
+        })
+        .collect()
+}
+
+/// Attaches each snapshot to its row, and its name to the row of its origin.
+pub fn annotate(drives: &mut [Drive]) {
+    let maps = dm_maps();
+    if maps.is_empty() {
+        return;
+    }
+    let snapshots = snapshots(&maps);
+    for partition in drives.iter_mut().flat_map(|d| d.partitions.iter_mut()) {
+        let name = partition.dev_name().to_string();
+        partition.snapshot = snapshots.iter().find(|s| s.device == name).cloned();
+        partition.snapshots = snapshots
+            .iter()
+            .filter(|s| s.origin_device == name)
+            .map(|s| s.name.clone())
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_lines() {
+        assert_eq!(
+            parse_status("vg0-snap: 0 2097152 snapshot 2048/409600 16"),
+            Some(State::Active {
+                used: 2048,
+                total: 409600
+            })
+        );
+        assert_eq!(
+            parse_status("vg0-snap: 0 2097152 snapshot Overflow"),
+            Some(State::Invalid("Overflow".to_string()))
+        );
+        assert_eq!(parse_status("vg0-snap: 0 2097152 snapshot Unknown"), None);
+    }
+}
@@ -0,0 +1,246 @@
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::IsTerminal,
+    path::{Path, PathBuf},
+};
+
+use colored::*;
+
+use crate::{
+    config::Config,
+    glyph, privilege,
+    source::{StatvfsProvider, SystemStatvfs},
+};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+    affects: &'static str,
+}
+
+impl Check {
+    fn new(name: &'static str, affects: &'static str, result: Result<String, (Status, String)>) -> Check {
+        let (status, detail) = match result {
+            Ok(detail) => (Status::Ok, detail),
+            Err((status, detail)) => (status, detail),
+        };
+        Check {
+            name,
+            status,
+            detail,
+            affects,
+        }
+    }
+}
+
+fn check_sysfs() -> Result<String, (Status, String)> {
+    let entries = fs::read_dir("/sys/block").map_err(|e| (Status::Fail, format!("cannot read /sys/block: {}", e)))?;
+    Ok(format!("{} block devices listed", entries.count()))
+}
+
+fn check_mounts() -> Result<String, (Status, String)> {
+    let content =
+        fs::read_to_string("/proc/mounts").map_err(|e| (Status::Fail, format!("cannot read /proc/mounts: {}", e)))?;
+
+    let mountpoints: Vec<&str> = content
+        .lines()
+        .filter(|line| line.starts_with("/dev/"))
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .collect();
+    let failed = mountpoints.iter().filter(|m| SystemStatvfs.statvfs(m).is_err()).count();
+
+    if failed > 0 {
+        Err((
+            Status::Warn,
+            format!("statvfs failed on {} of {} mounts", failed, mountpoints.len()),
+        ))
+    } else {
+        Ok(format!("{} device mounts, all readable", mountpoints.len()))
+    }
+}
+
+#[cfg(unix)]
+fn is_block_device(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    fs::metadata(path).is_ok_and(|m| m.file_type().is_block_device())
+}
+
+#[cfg(windows)]
+fn is_block_device(_path: &Path) -> bool {
+    false
+}
+
+fn block_nodes() -> Vec<PathBuf> {
+    fs::read_dir("/sys/block")
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| Path::new("/dev").join(entry.file_name()))
+        .filter(|path| is_block_device(path))
+        .collect()
+}
+
+fn check_dev() -> Result<String, (Status, String)> {
+    let nodes = block_nodes();
+    if nodes.is_empty() {
+        return Err((Status::Warn, "no block device nodes found in /dev".to_string()));
+    }
+    let readable = nodes.iter().filter(|node| File::open(node).is_ok()).count();
+    if readable < nodes.len() {
+        let hint = if privilege::is_root() {
+            ""
+        } else {
+            " (run as root for raw reads)"
+        };
+        Err((
+            Status::Warn,
+            format!("{} of {} device nodes readable{}", readable, nodes.len(), hint),
+        ))
+    } else {
+        Ok(format!("{} device nodes readable", nodes.len()))
+    }
+}
+
+fn check_dbus() -> Result<String, (Status, String)> {
+    let address =
+        env::var("DBUS_SYSTEM_BUS_ADDRESS").unwrap_or_else(|_| "unix:path=/run/dbus/system_bus_socket".to_string());
+    let socket = address
+        .split(';')
+        .filter_map(|a| a.strip_prefix("unix:path="))
+        .map(|a| a.split(',').next().unwrap_or(a))
+        .find(|path| Path::new(path).exists());
+
+    match socket {
+        Some(path) => Ok(format!("system bus at {}", path)),
+        None => Err((Status::Warn, "system bus socket not found".to_string())),
+    }
+}
+
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+fn check_smart() -> Result<String, (Status, String)> {
+    let smartctl = find_in_path("smartctl");
+    let root = privilege::is_root();
+
+    match (smartctl, root) {
+        (Some(path), true) => Ok(format!("{} available, running as root", path.display())),
+        #[cfg(feature = "smart")]
+        (Some(path), false) if crate::smart::helper().is_some() => {
+            Ok(format!("{} available through pblk-smart-helper", path.display()))
+        }
+        (Some(path), false) => Err((
+            Status::Warn,
+            format!(
+                "{} found but SMART queries need root or pblk-smart-helper",
+                path.display()
+            ),
+        )),
+        (None, _) => Err((Status::Warn, "smartctl not found in PATH".to_string())),
+    }
+}
+
+fn check_config(config: &Config) -> Result<String, (Status, String)> {
+    if config.sources.is_empty() {
+        return Ok("no config files, using defaults".to_string());
+    }
+    let sources: Vec<String> = config.sources.iter().map(|p| p.display().to_string()).collect();
+    Ok(format!("loaded {}", sources.join(", ")))
+}
+
+fn check_history(path: &Path) -> Result<String, (Status, String)> {
+    let writable = |p: &Path| OpenOptions::new().append(true).open(p).is_ok();
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let existing_dir = dir.ancestors().find(|d| d.exists()).unwrap_or(Path::new("/"));
+
+    let ok = if path.exists() {
+        writable(path)
+    } else {
+        // Creating the file needs write access to the nearest existing ancestor
+        fs::metadata(existing_dir)
+            .map(|m| !m.permissions().readonly())
+            .unwrap_or(false)
+    };
+
+    if ok {
+        Ok(format!("{} writable", path.display()))
+    } else {
+        Err((Status::Warn, format!("{} is not writable", path.display())))
+    }
+}
+
+fn check_terminal() -> Result<String, (Status, String)> {
+    let tty = std::io::stdout().is_terminal();
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|var| env::var(var).ok().filter(|v| !v.is_empty()))
+        .unwrap_or_default();
+    let utf8 = locale.to_lowercase().replace('-', "").contains("utf8");
+
+    match (tty, utf8) {
+        (true, true) => Ok("interactive terminal with UTF-8 locale".to_string()),
+        (false, _) => Err((Status::Warn, "stdout is not a terminal, colors disabled".to_string())),
+        (true, false) => Err((
+            Status::Warn,
+            format!("locale '{}' may not render block characters", locale),
+        )),
+    }
+}
+
+/// Runs the environment checks and prints a report. Returns false when a
+/// check failed hard enough that the core chart cannot work.
+pub fn run(config: &Config, history_file: &Path) -> bool {
+    let checks = [
+        Check::new("/sys", "drive and partition discovery", check_sysfs()),
+        Check::new("/proc", "mountpoints and usage bars", check_mounts()),
+        Check::new("/dev", "raw device probing", check_dev()),
+        Check::new("D-Bus", "desktop integration", check_dbus()),
+        Check::new("SMART", "drive health", check_smart()),
+        Check::new("config", "settings", check_config(config)),
+        Check::new("history", "--record and history", check_history(history_file)),
+        Check::new("terminal", "colors and glyphs", check_terminal()),
+    ];
+
+    let name_width = checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+    for check in &checks {
+        let marker = match check.status {
+            Status::Ok => glyph::glyphs().ok.green(),
+            Status::Warn => "!".yellow(),
+            Status::Fail => glyph::glyphs().fail.red(),
+        };
+        println!(
+            "{} {:width$}  {} {}",
+            marker,
+            check.name.bold(),
+            check.detail,
+            format!("({})", check.affects).dimmed(),
+            width = name_width
+        );
+    }
+
+    let unavailable: Vec<&str> = checks
+        .iter()
+        .filter(|c| c.status != Status::Ok)
+        .map(|c| c.affects)
+        .collect();
+    if !unavailable.is_empty() {
+        println!("\nLimited or unavailable: {}", unavailable.join(", "));
+    }
+
+    checks.iter().all(|c| c.status != Status::Fail)
+}
@@ -0,0 +1,85 @@
+//! What went wrong reading a value, kept on the drive or partition it
+//! belongs to, so an unreadable size or usage is not mistaken for zero.
+
+use std::{error::Error, fmt, io, path::PathBuf};
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PrettyblkError {
+    /// A sysfs attribute could not be read or did not hold a number
+    Sysfs { path: PathBuf, source: io::Error },
+    /// The mount table could not be read
+    Mounts { source: io::Error },
+    /// statvfs failed on a mountpoint or did not answer in time
+    Statvfs { mountpoint: String, source: io::Error },
+    /// smartctl or its helper could not be run, was refused or timed out
+    Smart { device: String, source: io::Error },
+    /// A drive cannot be laid out as a partition bar
+    Render { drive: String, source: io::Error },
+    /// A config file could not be read or holds an invalid setting; `line`
+    /// is 0 when the whole file is at fault
+    Config {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    /// A platform tool such as sysctl or diskutil printed something unexpected
+    Platform { what: String, source: io::Error },
+}
+
+impl PrettyblkError {
+    /// The message followed by those of its sources, the way `pblk` prints
+    /// it: `cannot read /sys/block/sda/size: Permission denied`.
+    pub fn chain(&self) -> String {
+        let mut message = self.to_string();
+        let mut source = self.source();
+        while let Some(error) = source {
+            message.push_str(&format!(": {}", error));
+            source = error.source();
+        }
+        message
+    }
+}
+
+impl fmt::Display for PrettyblkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PrettyblkError::Sysfs { path, .. } => write!(f, "cannot read {}", path.display()),
+            PrettyblkError::Mounts { .. } => write!(f, "cannot read the mount table"),
+            PrettyblkError::Statvfs { mountpoint, .. } => write!(f, "cannot query usage of {}", mountpoint),
+            PrettyblkError::Smart { device, .. } => write!(f, "cannot query SMART health of {}", device),
+            PrettyblkError::Render { drive, .. } => write!(f, "cannot lay out the bar of {}", drive),
+            PrettyblkError::Config { path, line, message } => write!(f, "{}:{}: {}", path.display(), line, message),
+            PrettyblkError::Platform { what, .. } => write!(f, "cannot read {}", what),
+        }
+    }
+}
+
+impl Error for PrettyblkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PrettyblkError::Sysfs { source, .. }
+            | PrettyblkError::Mounts { source }
+            | PrettyblkError::Statvfs { source, .. }
+            | PrettyblkError::Smart { source, .. }
+            | PrettyblkError::Render { source, .. }
+            | PrettyblkError::Platform { source, .. } => Some(source),
+            PrettyblkError::Config { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_chain_ends_with_the_system_error() {
+        let error = PrettyblkError::Sysfs {
+            path: PathBuf::from("/sys/block/sda/size"),
+            source: io::Error::new(io::ErrorKind::InvalidData, "not a number"),
+        };
+        assert_eq!(error.to_string(), "cannot read /sys/block/sda/size");
+        assert_eq!(error.chain(), "cannot read /sys/block/sda/size: not a number");
+    }
+}
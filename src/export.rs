@@ -0,0 +1,169 @@
+use std::fmt::Write;
+
+use colored::Color;
+
+use crate::{ChartOptions, Drive, PALETTE, theme};
+
+const WIDTH: usize = 800;
+const MARGIN: usize = 16;
+const BAR_HEIGHT: usize = 24;
+const ROW_HEIGHT: usize = 22;
+const USAGE_WIDTH: usize = 200;
+const FONT: &str = "font-family=\"monospace\" font-size=\"13\"";
+
+fn hex(color: Color) -> String {
+    let (r, g, b) = theme::rgb(color);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn drive_height(drive: &Drive) -> usize {
+    ROW_HEIGHT + BAR_HEIGHT + 8 + drive.partitions.len() * ROW_HEIGHT + MARGIN
+}
+
+/// Draws one drive at vertical offset `y`: title, stacked partition bar, and
+/// a row per partition with its usage bar.
+fn draw_drive(out: &mut String, drive: &Drive, options: &ChartOptions, mut y: usize) {
+    let units = &options.units;
+    let bar_width = WIDTH - 2 * MARGIN;
+    let total = drive.size.max(1) as f64;
+
+    let _ = writeln!(
+        out,
+        r#"<text x="{}" y="{}" {} font-weight="bold">Drive: {} ({:.2} {})</text>"#,
+        MARGIN,
+        y + 15,
+        FONT,
+        escape(&drive.name),
+        units.size.value(drive.size * 512),
+        units.size
+    );
+    y += ROW_HEIGHT;
+
+    let _ = writeln!(
+        out,
+        r##"<rect x="{}" y="{}" width="{}" height="{}" fill="none" stroke="#5c6370"/>"##,
+        MARGIN, y, bar_width, BAR_HEIGHT
+    );
+    let mut offset = 0.0;
+    let slots = drive.color_slots();
+    for (partition, slot) in drive.partitions.iter().zip(&slots).filter(|(p, _)| p.depth == 0) {
+        let width = partition.size as f64 / total * bar_width as f64;
+        let _ = writeln!(
+            out,
+            r#"<rect x="{:.1}" y="{}" width="{:.1}" height="{}" fill="{}"><title>{}</title></rect>"#,
+            MARGIN as f64 + offset,
+            y,
+            width.min(bar_width as f64 - offset),
+            BAR_HEIGHT,
+            hex(PALETTE[slot % PALETTE.len()]),
+            escape(partition.dev_name())
+        );
+        offset += width;
+    }
+    y += BAR_HEIGHT + 8;
+
+    for (partition, slot) in drive.partitions.iter().zip(slots) {
+        let color = PALETTE[slot % PALETTE.len()];
+        let indent = partition.depth * 12;
+        let text_y = y + 15;
+        let total_bytes = partition.size * 512;
+        let _ = writeln!(
+            out,
+            r#"<rect x="{}" y="{}" width="10" height="10" fill="{}"/>"#,
+            MARGIN + 8 + indent,
+            y + 6,
+            hex(color)
+        );
+        let _ = writeln!(
+            out,
+            r#"<text x="{}" y="{}" {}>{}</text>"#,
+            MARGIN + 26 + indent,
+            text_y,
+            FONT,
+            escape(partition.display_name())
+        );
+
+        let usage_x = MARGIN + 160;
+        let label = match partition.used_bytes() {
+            Some(used) => {
+                let ratio = (used as f64 / total_bytes.max(1) as f64).clamp(0.0, 1.0);
+                let severity = options.thresholds.classify(
+                    partition.mountpoint.as_deref(),
+                    partition.fstype.as_deref(),
+                    used,
+                    total_bytes,
+                );
+                let _ = writeln!(
+                    out,
+                    r##"<rect x="{}" y="{}" width="{}" height="12" fill="#3e4451"/>"##,
+                    usage_x,
+                    y + 5,
+                    USAGE_WIDTH
+                );
+                let _ = writeln!(
+                    out,
+                    r#"<rect x="{}" y="{}" width="{:.1}" height="12" fill="{}"/>"#,
+                    usage_x,
+                    y + 5,
+                    ratio * USAGE_WIDTH as f64,
+                    hex(severity.color(color))
+                );
+                units.used_of(used, total_bytes)
+            }
+            None => "Unmounted".to_string(),
+        };
+        let _ = writeln!(
+            out,
+            r#"<text x="{}" y="{}" {}>{}  {}</text>"#,
+            usage_x + USAGE_WIDTH + 12,
+            text_y,
+            FONT,
+            escape(&label),
+            escape(partition.display_mountpoint().unwrap_or("-"))
+        );
+        y += ROW_HEIGHT;
+    }
+}
+
+pub fn svg(drives: &[Drive], options: &ChartOptions) -> String {
+    let height: usize = MARGIN + drives.iter().map(drive_height).sum::<usize>();
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = WIDTH,
+        h = height
+    );
+    let _ = writeln!(
+        out,
+        r##"<rect width="100%" height="100%" fill="#282c34"/><g fill="#dcdfe4">"##
+    );
+
+    let mut y = MARGIN;
+    for drive in drives {
+        draw_drive(&mut out, drive, options, y);
+        y += drive_height(drive);
+    }
+
+    out.push_str("</g>\n</svg>\n");
+    out
+}
+
+pub fn html(drives: &[Drive], options: &ChartOptions) -> String {
+    format!(
+        concat!(
+            "<!DOCTYPE html>\n",
+            "<html>\n<head>\n<meta charset=\"utf-8\">\n<title>prettyblk</title>\n",
+            "<style>body {{ background: #21252b; margin: 2em; }}</style>\n",
+            "</head>\n<body>\n{}</body>\n</html>\n"
+        ),
+        svg(drives, options)
+    )
+}
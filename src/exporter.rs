@@ -0,0 +1,149 @@
+use std::{
+    fmt::Write as _,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    Drive,
+    severity::{Severity, Thresholds},
+};
+
+const METRICS: [(&str, &str, &str); 7] = [
+    ("prettyblk_drive_size_bytes", "gauge", "Capacity of the drive"),
+    ("prettyblk_partition_size_bytes", "gauge", "Capacity of the partition"),
+    (
+        "prettyblk_partition_used_bytes",
+        "gauge",
+        "Used space of the filesystem or swap area",
+    ),
+    (
+        "prettyblk_partition_free_bytes",
+        "gauge",
+        "Free space reported by statvfs",
+    ),
+    ("prettyblk_partition_inodes_used", "gauge", "Inodes in use"),
+    ("prettyblk_partition_inodes_total", "gauge", "Inodes available in total"),
+    (
+        "prettyblk_partition_severity",
+        "gauge",
+        "Threshold level of the usage: 0 ok, 1 warning, 2 critical",
+    ),
+];
+
+/// How long a client may take to send its request or read the response.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A scraper's request line is well under 100 bytes.
+const MAX_REQUEST_LINE: u64 = 8192;
+
+/// Escapes a label value per the Prometheus text exposition format.
+fn label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Renders drives and partitions in the Prometheus text format.
+pub fn render(drives: &[Drive], thresholds: &Thresholds) -> String {
+    let mut samples: Vec<Vec<String>> = vec![Vec::new(); METRICS.len()];
+
+    for drive in drives {
+        samples[0].push(format!("{{drive=\"{}\"}} {}", label(&drive.name), drive.size * 512));
+
+        for partition in &drive.partitions {
+            let labels = format!(
+                "{{drive=\"{}\",partition=\"{}\",mountpoint=\"{}\",fstype=\"{}\"}}",
+                label(&drive.name),
+                label(partition.dev_name()),
+                label(partition.display_mountpoint().unwrap_or("")),
+                label(partition.fstype.as_deref().unwrap_or(""))
+            );
+            let total = partition.size * 512;
+            samples[1].push(format!("{} {}", labels, total));
+
+            if let Some(used) = partition.used_bytes() {
+                samples[2].push(format!("{} {}", labels, used));
+                let severity = thresholds.classify(
+                    partition.mountpoint.as_deref(),
+                    partition.fstype.as_deref(),
+                    used,
+                    total,
+                );
+                let level = match severity {
+                    Severity::Ok => 0,
+                    Severity::Warning => 1,
+                    Severity::Critical => 2,
+                };
+                samples[6].push(format!("{} {}", labels, level));
+            }
+            if let Some(free) = partition.free {
+                samples[3].push(format!("{} {}", labels, free));
+            }
+            if let Some(inodes) = &partition.inodes {
+                samples[4].push(format!("{} {}", labels, inodes.used));
+                samples[5].push(format!("{} {}", labels, inodes.total));
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for ((name, kind, help), lines) in METRICS.iter().zip(samples) {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        for line in lines {
+            let _ = writeln!(out, "{}{}", name, line);
+        }
+    }
+    out
+}
+
+fn respond(mut stream: TcpStream, collect: &impl Fn() -> String) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new((&stream).take(MAX_REQUEST_LINE)).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+
+    let (status, content_type, body) = match path {
+        "/metrics" => ("200 OK", "text/plain; version=0.0.4", collect()),
+        "/" => (
+            "200 OK",
+            "text/html",
+            "<html><body><a href=\"/metrics\">Metrics</a></body></html>\n".to_string(),
+        ),
+        _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+/// Serves `/metrics` on `address` until the process is killed. Every scrape
+/// runs a fresh scan through `collect`, each connection on its own thread
+/// so a client that stalls does not hold up the others.
+pub fn serve(address: &str, collect: impl Fn() -> String + Sync) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    warn!("pblk: serving metrics on http://{}/metrics", listener.local_addr()?);
+    let collect = &collect;
+    thread::scope(|scope| {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    scope.spawn(move || {
+                        if let Err(e) = respond(stream, collect) {
+                            warn!("pblk: exporter request failed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("pblk: exporter request failed: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use crate::{Drive, identity::Identity};
+
+const COLUMNS: [&str; 6] = ["drive", "partition", "size_bytes", "used_bytes", "mountpoint", "fstype"];
+
+/// Identity columns that follow `COLUMNS` unless left out.
+const IDENTITY_COLUMNS: [&str; 3] = ["hostname", "kernel", "timestamp"];
+
+pub fn print_delimited(drives: &[Drive], separator: char, identity: Option<&Identity>) {
+    let escape = |field: &str| escape_field(field, separator);
+    let mut header = COLUMNS.to_vec();
+    let mut stamp = Vec::new();
+    if let Some(identity) = identity {
+        header.extend(IDENTITY_COLUMNS);
+        stamp = vec![
+            escape(&identity.hostname),
+            escape(identity.kernel.as_deref().unwrap_or("")),
+            identity.timestamp.to_string(),
+        ];
+    }
+
+    println!("{}", header.join(&separator.to_string()));
+
+    for drive in drives {
+        for partition in &drive.partitions {
+            let mut row = vec![
+                escape(&drive.name),
+                escape(partition.dev_name()),
+                (partition.size * 512).to_string(),
+                partition.used_bytes().map(|u| u.to_string()).unwrap_or_default(),
+                escape(partition.display_mountpoint().unwrap_or("")),
+                escape(partition.fstype.as_deref().unwrap_or("")),
+            ];
+            row.extend(stamp.iter().cloned());
+            println!("{}", row.join(&separator.to_string()));
+        }
+    }
+}
+
+/// `dm-0` → `dm_0`: device names as shell variable name prefixes.
+fn key(name: &str) -> String {
+    let key: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    match key.starts_with(|c: char| c.is_ascii_digit()) {
+        true => format!("_{}", key),
+        false => key,
+    }
+}
+
+/// Single-quotes `value` for POSIX shells unless it is plain enough without.
+fn shell_quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/._-+:,".contains(c));
+    match plain {
+        true => value.to_string(),
+        false => format!("'{}'", value.replace('\'', "'\\''")),
+    }
+}
+
+/// Prints `sda1_used_bytes=123` style assignments for every drive and
+/// partition, plus `drives` and `partitions` listing the name prefixes and,
+/// from `identity`, `hostname`, `kernel` and `timestamp`.
+/// With `nul` each record ends in a NUL byte and values are not quoted, for
+/// `xargs -0` and `read -d ''`; otherwise the output can be `eval`ed.
+pub fn print_assignments(drives: &[Drive], nul: bool, identity: Option<&Identity>) {
+    let mut records: Vec<(String, String)> = Vec::new();
+    let mut field = |prefix: &str, name: &str, value: String| {
+        records.push((format!("{}_{}", prefix, name), value));
+    };
+
+    let mut partition_keys = Vec::new();
+    for drive in drives {
+        let prefix = key(&drive.name);
+        field(&prefix, "size_bytes", (drive.size * 512).to_string());
+        for partition in &drive.partitions {
+            let prefix = key(partition.dev_name());
+            field(&prefix, "drive", drive.name.clone());
+            field(&prefix, "size_bytes", (partition.size * 512).to_string());
+            if let Some(used) = partition.used_bytes() {
+                field(&prefix, "used_bytes", used.to_string());
+            }
+            if let Some(free) = partition.free {
+                field(&prefix, "free_bytes", free.to_string());
+            }
+            if let Some(mountpoint) = partition.display_mountpoint() {
+                field(&prefix, "mountpoint", mountpoint.to_string());
+            }
+            if let Some(fstype) = &partition.fstype {
+                field(&prefix, "fstype", fstype.clone());
+            }
+            partition_keys.push(prefix);
+        }
+    }
+    let drive_keys: Vec<String> = drives.iter().map(|d| key(&d.name)).collect();
+    records.insert(0, ("drives".to_string(), drive_keys.join(" ")));
+    records.insert(1, ("partitions".to_string(), partition_keys.join(" ")));
+    if let Some(identity) = identity {
+        let stamp = [
+            ("hostname", identity.hostname.clone()),
+            ("kernel", identity.kernel.clone().unwrap_or_default()),
+            ("timestamp", identity.timestamp.to_string()),
+        ];
+        records.splice(0..0, stamp.map(|(key, value)| (key.to_string(), value)));
+    }
+
+    let mut out = String::new();
+    for (key, value) in records {
+        match nul {
+            true => out.push_str(&format!("{}={}\0", key, value)),
+            false => out.push_str(&format!("{}={}\n", key, shell_quote(&value))),
+        }
+    }
+    print!("{}", out);
+}
+
+pub fn escape_field(field: &str, separator: char) -> String {
+    if separator == '\t' {
+        // TSV has no quoting, so control characters are replaced instead
+        return field.replace(['\t', '\n', '\r'], " ");
+    }
+
+    if field.contains([separator, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
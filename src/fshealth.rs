@@ -0,0 +1,122 @@
+//! `--fs-health`: whether unmounted filesystems were left dirty or have
+//! errors recorded, read from their superblocks without changing anything,
+//! so a filesystem that needs fsck is caught before it is mounted. ext2/3/4
+//! and btrfs are covered; XFS keeps its clean state in the log, which only
+//! xfs_repair reads.
+
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+};
+
+use crate::{Drive, image, privilege};
+
+/// Where ext2/3/4 keep their superblock.
+const EXT_SUPERBLOCK: u64 = 1024;
+/// `s_state`: cleanly unmounted, and errors detected
+const EXT_VALID: u16 = 0x1;
+const EXT_ERROR: u16 = 0x2;
+/// `s_feature_incompat` bit for a journal that waits to be replayed
+const EXT_RECOVER: u32 = 0x4;
+
+/// Where btrfs keeps its primary superblock.
+const BTRFS_SUPERBLOCK: u64 = 0x10000;
+/// `flags` bit the kernel sets after an error forced the filesystem read-only
+const BTRFS_ERROR: u64 = 1 << 2;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Health {
+    /// The filesystem from its superblock, e.g. `ext4`
+    pub fstype: String,
+    /// Not cleanly unmounted, so a journal or log waits to be replayed
+    pub dirty: bool,
+    /// Errors recorded; btrfs and older ext only flag them, counted as one
+    pub errors: u32,
+}
+
+fn read<const N: usize>(file: &mut File, offset: u64) -> Option<[u8; N]> {
+    let mut buffer = [0; N];
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    file.read_exact(&mut buffer).ok()?;
+    Some(buffer)
+}
+
+fn ext(file: &mut File) -> Option<(bool, u32)> {
+    let state = u16::from_le_bytes(read(file, EXT_SUPERBLOCK + 0x3a)?);
+    let incompat = u32::from_le_bytes(read(file, EXT_SUPERBLOCK + 0x60)?);
+    let error_count = u32::from_le_bytes(read(file, EXT_SUPERBLOCK + 0x194)?);
+    let dirty = state & EXT_VALID == 0 || incompat & EXT_RECOVER != 0;
+    Some((dirty, error_count.max((state & EXT_ERROR != 0) as u32)))
+}
+
+fn btrfs(file: &mut File) -> Option<(bool, u32)> {
+    let flags = u64::from_le_bytes(read(file, BTRFS_SUPERBLOCK + 0x38)?);
+    // A log tree is left behind when fsynced data was not yet committed
+    let log_root = u64::from_le_bytes(read(file, BTRFS_SUPERBLOCK + 0x60)?);
+    Some((log_root != 0, (flags & BTRFS_ERROR != 0) as u32))
+}
+
+/// The health of the filesystem at the start of `file`, `None` for other
+/// filesystems or when the superblock cannot be read.
+pub fn check(file: &mut File) -> Option<Health> {
+    let fstype = image::filesystem(file)?;
+    let (dirty, errors) = match fstype.as_str() {
+        "ext2" | "ext3" | "ext4" => ext(file)?,
+        "btrfs" => btrfs(file)?,
+        _ => return None,
+    };
+    Some(Health { fstype, dirty, errors })
+}
+
+/// Checks every unmounted partition and stacked device. Mounted ones are
+/// skipped, as a mounted filesystem is never marked clean on disk.
+pub fn annotate(drives: &mut [Drive]) {
+    for partition in drives.iter_mut().flat_map(|d| d.partitions.iter_mut()) {
+        if partition.mountpoint.is_some() || partition.swap.is_some() {
+            continue;
+        }
+        let path = match cfg!(windows) {
+            true => format!(r"\\.\{}", partition.dev_name()),
+            false => format!("/dev/{}", partition.dev_name()),
+        };
+        match File::open(&path) {
+            Ok(mut file) => partition.fs_health = check(&mut file),
+            Err(e) if privilege::refused(&e) => privilege::deny_on("filesystem health", partition.dev_name()),
+            Err(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, io::Write, process};
+
+    use super::*;
+
+    #[test]
+    fn ext4_with_a_journal_to_replay_is_dirty() {
+        let path = env::temp_dir().join(format!("prettyblk-fshealth-test-{}.img", process::id()));
+        let mut superblock = vec![0; 4096];
+        superblock[1080..1082].copy_from_slice(&[0x53, 0xef]);
+        superblock[1120] = 0x40 | EXT_RECOVER as u8;
+        superblock[1082] = EXT_VALID as u8;
+        fs::File::create(&path).unwrap().write_all(&superblock).unwrap();
+        let health = check(&mut File::open(&path).unwrap()).unwrap();
+        assert_eq!(
+            health,
+            Health {
+                fstype: "ext4".to_string(),
+                dirty: true,
+                errors: 0
+            }
+        );
+
+        superblock[1120] = 0x40;
+        superblock[1082] |= EXT_ERROR as u8;
+        superblock[1428] = 3;
+        fs::write(&path, &superblock).unwrap();
+        let health = check(&mut File::open(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!((health.dirty, health.errors), (false, 3));
+    }
+}
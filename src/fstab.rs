@@ -0,0 +1,128 @@
+use std::{
+    fs::{canonicalize, read_to_string},
+    path::Path,
+};
+
+use crate::{Drive, Partition, swap};
+
+/// Where the system is configured to mount a partition that is not
+/// currently mounted, from `etc/fstab` and `etc/crypttab` under the root.
+pub struct Intended {
+    /// Mountpoint, or `[SWAP]` for swap areas; `None` for a crypttab mapping
+    /// whose filesystem is not in fstab
+    pub mountpoint: Option<String>,
+    /// The dm-crypt mapping that has to be opened first
+    pub crypt: Option<String>,
+    /// Marked `noauto`, so not being mounted is expected
+    pub noauto: bool,
+}
+
+/// One line of fstab or crypttab: the whitespace separated fields with
+/// octal escapes decoded, and the comma separated options in the fourth.
+struct Line {
+    fields: Vec<String>,
+    options: Vec<String>,
+}
+
+impl Line {
+    fn field(&self, index: usize) -> &str {
+        self.fields.get(index).map_or("", String::as_str)
+    }
+
+    fn noauto(&self) -> bool {
+        self.options.iter().any(|o| o == "noauto")
+    }
+}
+
+fn parse(content: &str) -> Vec<Line> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let fields: Vec<String> = line.split_whitespace().map(swap::decode_octal_escapes).collect();
+            let options = fields
+                .get(3)
+                .map(|o| o.split(',').map(String::from).collect())
+                .unwrap_or_default();
+            Line { fields, options }
+        })
+        .filter(|line| line.fields.len() >= 2)
+        .collect()
+}
+
+/// The kernel name of the device an fstab source refers to, following the
+/// `/dev/disk/by-*` links udev maintains for `UUID=` style tags.
+fn resolve(source: &str) -> Option<String> {
+    let path = match source.split_once('=') {
+        Some(("UUID", uuid)) => format!("/dev/disk/by-uuid/{}", uuid),
+        Some(("LABEL", label)) => format!("/dev/disk/by-label/{}", label.replace(' ', "\\x20")),
+        Some(("PARTUUID", uuid)) => format!("/dev/disk/by-partuuid/{}", uuid),
+        Some(("PARTLABEL", label)) => format!("/dev/disk/by-partlabel/{}", label.replace(' ', "\\x20")),
+        _ if source.starts_with("/dev/") => source.to_string(),
+        _ => return None,
+    };
+    let device = canonicalize(path).ok()?;
+    Some(device.file_name()?.to_str()?.to_string())
+}
+
+fn is_unmounted(partition: &Partition) -> bool {
+    partition.mountpoint.is_none() && partition.swap.is_none()
+}
+
+/// Notes the intended mountpoint of every unmounted partition that fstab or
+/// crypttab knows about. `root` is `/`, or with --chroot a system image
+/// mounted in a rescue environment; its tags still resolve against the
+/// running system's devices, which are the image's disks.
+pub fn annotate(drives: &mut [Drive], root: &Path) {
+    // fstab: source, mountpoint, type, options
+    let fstab = parse(&read_to_string(root.join("etc/fstab")).unwrap_or_default());
+    // crypttab: mapping name, encrypted device, key file, options
+    let crypttab = parse(&read_to_string(root.join("etc/crypttab")).unwrap_or_default());
+    if fstab.is_empty() && crypttab.is_empty() {
+        return;
+    }
+
+    let mountpoint = |line: &Line| match line.field(2) {
+        "swap" => "[SWAP]".to_string(),
+        _ => line.field(1).to_string(),
+    };
+    let open: Vec<String> = drives
+        .iter()
+        .flat_map(|d| &d.partitions)
+        .filter_map(|p| p.alias.clone())
+        .collect();
+
+    for partition in drives.iter_mut().flat_map(|d| d.partitions.iter_mut()) {
+        if !is_unmounted(partition) {
+            continue;
+        }
+        let dev = partition.dev_name().to_string();
+        let refers = |source: &str| resolve(source).as_deref() == Some(dev.as_str());
+
+        if let Some(line) = fstab.iter().find(|line| refers(line.field(0))) {
+            partition.intended = Some(Intended {
+                mountpoint: Some(mountpoint(line)),
+                crypt: None,
+                noauto: line.noauto(),
+            });
+            continue;
+        }
+
+        // While a mapping is open its filesystem shows up on the stacked
+        // device instead, so only closed ones are noted on the partition
+        let Some(crypt) = crypttab
+            .iter()
+            .find(|line| refers(line.field(1)) && !open.iter().any(|name| name == line.field(0)))
+        else {
+            continue;
+        };
+        let mapped = format!("/dev/mapper/{}", crypt.field(0));
+        let line = fstab.iter().find(|line| line.field(0) == mapped);
+        partition.intended = Some(Intended {
+            mountpoint: line.map(mountpoint),
+            crypt: Some(crypt.field(0).to_string()),
+            noauto: crypt.noauto() || line.is_some_and(Line::noauto),
+        });
+    }
+}
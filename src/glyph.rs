@@ -0,0 +1,114 @@
+use crate::{Drive, lang};
+
+/// Characters of the charts beyond ASCII, and stand-ins for serial consoles
+/// and terminals without UTF-8 that would show them as mojibake.
+pub struct Glyphs {
+    /// Fill of successive partition segments
+    pub segments: [&'static str; 4],
+    /// Used and free cells of a usage bar
+    pub used: &'static str,
+    pub free: &'static str,
+    /// Used and free cells of an inode bar
+    pub inodes_used: &'static str,
+    pub inodes_free: &'static str,
+    /// Color swatch before each partition row
+    pub swatch: &'static str,
+    /// Lead of detail lines below a row
+    pub detail: &'static str,
+    pub sum: &'static str,
+    pub arrow: &'static str,
+    /// Tree connectors: sibling follows, last sibling, ancestor continues
+    pub tee: &'static str,
+    pub last: &'static str,
+    pub pipe: &'static str,
+    #[cfg_attr(not(feature = "history"), allow(dead_code))]
+    pub sparks: [char; 8],
+    /// Left edge of the history plot
+    #[cfg_attr(not(feature = "history"), allow(dead_code))]
+    pub axis: &'static str,
+    /// The history plot is drawn in braille dots, or else in `*`
+    #[cfg_attr(not(feature = "history"), allow(dead_code))]
+    pub braille: bool,
+    /// Check results of `pblk doctor`
+    #[cfg_attr(not(feature = "doctor"), allow(dead_code))]
+    pub ok: &'static str,
+    #[cfg_attr(not(feature = "doctor"), allow(dead_code))]
+    pub fail: &'static str,
+}
+
+const UNICODE: Glyphs = Glyphs {
+    segments: ["█", "▓", "▒", "░"],
+    used: "█",
+    free: "░",
+    inodes_used: "━",
+    inodes_free: "─",
+    swatch: "■",
+    detail: "↳",
+    sum: "Σ",
+    arrow: "→",
+    tee: "├─",
+    last: "└─",
+    pipe: "│ ",
+    sparks: ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'],
+    axis: "┤",
+    braille: true,
+    ok: "✔",
+    fail: "✘",
+};
+
+const ASCII: Glyphs = Glyphs {
+    segments: ["#", "=", "+", ":"],
+    used: "#",
+    free: "-",
+    inodes_used: "=",
+    inodes_free: "-",
+    swatch: "*",
+    detail: "+",
+    sum: "=",
+    arrow: "->",
+    tee: "|-",
+    last: "`-",
+    pipe: "| ",
+    sparks: ['_', '.', ',', '-', '~', '=', '*', '#'],
+    axis: "|",
+    braille: false,
+    ok: "+",
+    fail: "x",
+};
+
+/// The glyphs for the current locale, see [`lang::Locale::ascii`].
+pub fn glyphs() -> &'static Glyphs {
+    match lang::ascii() {
+        true => &ASCII,
+        false => &UNICODE,
+    }
+}
+
+const HDD: [&str; 3] = [".---------.", "| (  o  ).|", "'---------'"];
+const SSD: [&str; 3] = [".---------.", "| [#####] |", "'---------'"];
+const NVME: [&str; 3] = [".=========.", "|[] [] [] |", "'^-^-^-^-^'"];
+const USB: [&str; 3] = ["  .------. ", "==|######| ", "  '------' "];
+
+/// A three line ASCII picture of the kind of drive, 11 columns wide.
+pub fn icon(drive: &Drive) -> [&'static str; 3] {
+    if drive.usb {
+        USB
+    } else if drive.name.starts_with("nvme") {
+        NVME
+    } else if drive.rotational == Some(false) {
+        SSD
+    } else {
+        HDD
+    }
+}
+
+/// The second and third line beside the icon: model and media kind.
+pub fn caption(drive: &Drive) -> [String; 2] {
+    let kind = match (drive.rotational, drive.usb) {
+        (_, true) => "USB drive",
+        (Some(true), _) => "rotational",
+        (Some(false), _) => "solid state",
+        (None, _) => "",
+    };
+    [drive.model.clone().unwrap_or_default(), kind.to_string()]
+}
@@ -0,0 +1,103 @@
+//! `--assume-growth`: when a filesystem fills up at a write rate given on
+//! the command line, for new log and metrics partitions that have no
+//! history to fit a trend to yet.
+
+use std::collections::HashMap;
+
+use crate::{Drive, Partition, units};
+
+/// `/var=2G/day`: a mountpoint and the bytes written to it per day.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assumption {
+    pub mountpoint: String,
+    pub bytes_per_day: u64,
+}
+
+impl Assumption {
+    pub fn parse(spec: &str) -> Result<Assumption, String> {
+        let Some((mountpoint, rate)) = spec.split_once('=') else {
+            return Err(format!("expected MOUNTPOINT=SIZE/day, got '{}'", spec));
+        };
+        let bytes_per_day = rate
+            .strip_suffix("/day")
+            .and_then(units::parse_size)
+            .filter(|&bytes| bytes > 0)
+            .ok_or_else(|| format!("invalid growth '{}' for {}, expected e.g. 2G/day", rate, mountpoint))?;
+        let mountpoint = match mountpoint.trim_end_matches('/') {
+            "" => "/",
+            trimmed => trimmed,
+        };
+        Ok(Assumption {
+            mountpoint: mountpoint.to_string(),
+            bytes_per_day,
+        })
+    }
+}
+
+/// Gives the partitions mounted at each assumption's mountpoint its growth
+/// and warns about mountpoints that nothing is mounted at.
+pub fn annotate(drives: &mut [Drive], assumptions: &[Assumption]) {
+    for assumption in assumptions {
+        let mut found = false;
+        for partition in drives.iter_mut().flat_map(|d| d.partitions.iter_mut()) {
+            if partition
+                .mountpoint
+                .iter()
+                .chain(&partition.also_mounted)
+                .any(|path| *path == assumption.mountpoint)
+            {
+                partition.assumed_growth = Some(assumption.bytes_per_day);
+                found = true;
+            }
+        }
+        if !found {
+            warn!(
+                "pblk: nothing is mounted at {} to assume growth for",
+                assumption.mountpoint
+            );
+        }
+    }
+}
+
+/// Days until `partition` is full at its assumed growth.
+pub fn days_left(partition: &Partition) -> Option<f64> {
+    let rate = partition.assumed_growth?;
+    let used = partition.used_bytes()?;
+    Some(partition.size_bytes().saturating_sub(used) as f64 / rate as f64)
+}
+
+/// Days until full at the assumed growth, keyed by mountpoint like the
+/// forecasts fitted to the history.
+pub fn forecasts(drives: &[Drive]) -> HashMap<String, f64> {
+    drives
+        .iter()
+        .flat_map(|d| &d.partitions)
+        .filter_map(|p| Some((p.mountpoint.clone()?, days_left(p)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Mount;
+
+    #[test]
+    fn a_new_log_partition_fills_at_the_assumed_rate() {
+        let assumption = Assumption::parse("/var/log/=2G/day").unwrap();
+        assert_eq!(
+            assumption,
+            Assumption {
+                mountpoint: "/var/log".to_string(),
+                bytes_per_day: 2 << 30
+            }
+        );
+        assert!(Assumption::parse("/var/log=2G").is_err());
+
+        let mount = Mount::new("/var/log", "xfs", &[]);
+        let mut partition = Partition::new("sdb/sdb1".to_string(), 100 << 21, Some(&mount));
+        partition.used = Some(10 << 30);
+        let mut drives = [Drive::new("sdb", 100 << 21, vec![partition])];
+        annotate(&mut drives, &[assumption]);
+        assert_eq!(forecasts(&drives), HashMap::from([("/var/log".to_string(), 45.0)]));
+    }
+}
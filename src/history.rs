@@ -0,0 +1,486 @@
+use std::{
+    cmp::max,
+    collections::{BTreeMap, HashMap},
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+};
+
+use colored::*;
+
+use crate::{
+    Drive,
+    clock::{Clock, format_date},
+    config::Retention,
+    glyph, plot, text,
+    units::Units,
+};
+
+const SECONDS_PER_HOUR: u64 = 3_600;
+const SECONDS_PER_DAY: u64 = 86_400;
+const PLOT_HEIGHT: usize = 10;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    pub timestamp: u64,
+    pub device: String,
+    pub mountpoint: String,
+    pub size: u64,
+    pub used: u64,
+}
+
+impl Sample {
+    fn parse(line: &str) -> Option<Sample> {
+        let mut fields = line.split('\t');
+        Some(Sample {
+            timestamp: fields.next()?.parse().ok()?,
+            device: fields.next()?.to_string(),
+            mountpoint: fields.next()?.to_string(),
+            size: fields.next()?.parse().ok()?,
+            used: fields.next()?.parse().ok()?,
+        })
+    }
+}
+
+/// Appends one sample per mounted partition to the history file, then
+/// compacts samples that have aged past `retention.raw_days`.
+pub fn record(path: &Path, drives: &[Drive], retention: &Retention, clock: &dyn Clock) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(sample_lines(drives, clock.now()).as_bytes())?;
+    drop(file);
+
+    let samples = load(path)?;
+    let compacted = compact(&samples, retention, clock.now());
+    if compacted == samples {
+        return Ok(());
+    }
+    // Rewrite through a temporary file so an interrupted run never truncates the history
+    let temporary = path.with_extension("tsv.tmp");
+    fs::write(&temporary, format_samples(&compacted))?;
+    fs::rename(&temporary, path)
+}
+
+fn format_samples(samples: &[Sample]) -> String {
+    samples
+        .iter()
+        .map(|s| {
+            format!(
+                "{}\t{}\t{}\t{}\t{}\n",
+                s.timestamp, s.device, s.mountpoint, s.size, s.used
+            )
+        })
+        .collect()
+}
+
+/// Keeps samples newer than `raw_days` as they are, averages older ones per
+/// device into hourly buckets until `hourly_days` and daily buckets until
+/// `daily_days`, and drops anything older. A bucket is stamped with its start
+/// and keeps the latest size, so compacting twice changes nothing.
+fn compact(samples: &[Sample], retention: &Retention, now: u64) -> Vec<Sample> {
+    let cutoff = |days: u64| now.saturating_sub(days.saturating_mul(SECONDS_PER_DAY));
+    let (raw_since, hourly_since, daily_since) = (
+        cutoff(retention.raw_days),
+        cutoff(retention.hourly_days),
+        cutoff(retention.daily_days),
+    );
+
+    let mut kept = Vec::new();
+    // (bucket start, device, mountpoint) -> (sum of used, count, latest size)
+    let mut buckets: BTreeMap<(u64, &str, &str), (u128, u64, u64)> = BTreeMap::new();
+    for sample in samples {
+        let bucket = match sample.timestamp {
+            t if t >= raw_since => {
+                kept.push(sample.clone());
+                continue;
+            }
+            t if t >= hourly_since => SECONDS_PER_HOUR,
+            t if t >= daily_since => SECONDS_PER_DAY,
+            _ => continue,
+        };
+        let start = sample.timestamp - sample.timestamp % bucket;
+        let key = (start, sample.device.as_str(), sample.mountpoint.as_str());
+        let (sum, count, size) = buckets.entry(key).or_default();
+        *sum += sample.used as u128;
+        *count += 1;
+        *size = sample.size;
+    }
+
+    let mut out: Vec<Sample> = buckets
+        .into_iter()
+        .map(|((timestamp, device, mountpoint), (sum, count, size))| Sample {
+            timestamp,
+            device: device.to_string(),
+            mountpoint: mountpoint.to_string(),
+            size,
+            used: (sum / count as u128) as u64,
+        })
+        .collect();
+    out.extend(kept);
+    out.sort_by_key(|s| s.timestamp);
+    out
+}
+
+fn sample_lines(drives: &[Drive], timestamp: u64) -> String {
+    let mut out = String::new();
+    for drive in drives {
+        for partition in &drive.partitions {
+            if let (Some(used), Some(mountpoint)) = (partition.used, &partition.mountpoint) {
+                out.push_str(&format!(
+                    "{}\t{}\t{}\t{}\t{}\n",
+                    timestamp,
+                    partition.dev_name(),
+                    mountpoint,
+                    partition.size * 512,
+                    used
+                ));
+            }
+        }
+    }
+    out
+}
+
+pub fn load(path: &Path) -> io::Result<Vec<Sample>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(content.lines().filter_map(Sample::parse).collect())
+}
+
+/// Finds the samples belonging to `target`, which may be a device name,
+/// a `/dev` path, a mountpoint, or any path below a recorded mountpoint.
+fn select<'a>(samples: &'a [Sample], target: &str) -> Vec<&'a Sample> {
+    let device = target.strip_prefix("/dev/").unwrap_or(target);
+    let by_device: Vec<&Sample> = samples.iter().filter(|s| s.device == device).collect();
+    if !by_device.is_empty() {
+        return by_device;
+    }
+
+    let contains = |mountpoint: &str| {
+        target == mountpoint
+            || mountpoint == "/"
+            || target
+                .strip_prefix(mountpoint)
+                .is_some_and(|rest| rest.starts_with('/'))
+    };
+    let best = samples
+        .iter()
+        .map(|s| s.mountpoint.as_str())
+        .filter(|m| target.starts_with('/') && contains(m))
+        .max_by_key(|m| m.len());
+
+    match best {
+        Some(mountpoint) => samples.iter().filter(|s| s.mountpoint == mountpoint).collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Samples of `target` from the last `days` days, oldest first.
+fn recent<'a>(samples: &'a [Sample], target: &str, since: u64) -> Vec<&'a Sample> {
+    select(samples, target)
+        .into_iter()
+        .filter(|s| s.timestamp >= since)
+        .collect()
+}
+
+pub fn print_history(
+    path: &Path,
+    target: &str,
+    days: u64,
+    width: usize,
+    units: &Units,
+    clock: &dyn Clock,
+) -> io::Result<()> {
+    let format_size = |bytes: u64| units.used.format(bytes);
+    let samples = load(path)?;
+    let until = clock.now();
+    let since = until.saturating_sub(days.saturating_mul(SECONDS_PER_DAY));
+    let selected = recent(&samples, target, since);
+
+    let Some(last) = selected.last() else {
+        println!(
+            "No history recorded for {} in the last {} days (run `pblk --record` periodically)",
+            target, days
+        );
+        return Ok(());
+    };
+
+    let max_used = selected.iter().map(|s| s.used).max().unwrap_or(0);
+    let y_max = max(last.size, max_used).max(1) as f64;
+    let points: Vec<(f64, f64)> = selected.iter().map(|s| (s.timestamp as f64, s.used as f64)).collect();
+
+    println!(
+        "\n{} {} on {} (last {} days)",
+        "History:".bold().blue(),
+        text::isolate(&last.mountpoint).bold(),
+        last.device,
+        days
+    );
+
+    let label_width = 9;
+    let plot_width = width.saturating_sub(label_width + 2).max(10);
+    let rows = plot::braille_chart(
+        &points,
+        (since as f64, until as f64),
+        (0.0, y_max),
+        plot_width,
+        PLOT_HEIGHT,
+    );
+
+    for (i, row) in rows.iter().enumerate() {
+        let label = match i {
+            0 => format_size(y_max as u64),
+            _ if i == PLOT_HEIGHT - 1 => format_size(0),
+            _ if i == PLOT_HEIGHT / 2 => format_size(y_max as u64 / 2),
+            _ => String::new(),
+        };
+        println!(
+            "{:>width$} {}{}",
+            label,
+            glyph::glyphs().axis,
+            row.green(),
+            width = label_width
+        );
+    }
+
+    let start = format_date(since);
+    let end = format_date(until);
+    println!(
+        "{:>lw$}  {}{:>rest$}",
+        "",
+        start,
+        end,
+        lw = label_width,
+        rest = plot_width.saturating_sub(start.len())
+    );
+    println!(
+        "  {} samples, currently {} used of {}",
+        selected.len(),
+        format_size(last.used),
+        units.size.format(last.size)
+    );
+    Ok(())
+}
+
+/// Usage of every recorded mountpoint between `since` and `until` as a
+/// sparkline of `width` cells, keyed by mountpoint. Each cell shows the
+/// latest sample of its slice of time on a 0-100% scale; slices without a
+/// sample stay blank, so gaps in recording are visible.
+pub fn sparklines(samples: &[Sample], since: u64, until: u64, width: usize) -> HashMap<String, String> {
+    if width == 0 {
+        return HashMap::new();
+    }
+    let span = until.saturating_sub(since).max(1);
+    let sparks = glyph::glyphs().sparks;
+    let mut cells: HashMap<String, Vec<Option<f64>>> = HashMap::new();
+    for sample in samples.iter().filter(|s| (since..=until).contains(&s.timestamp)) {
+        let slot = (((sample.timestamp - since) as u128 * width as u128 / span as u128) as usize).min(width - 1);
+        let line = cells
+            .entry(sample.mountpoint.clone())
+            .or_insert_with(|| vec![None; width]);
+        line[slot] = Some(sample.used as f64 / sample.size.max(1) as f64);
+    }
+    cells
+        .into_iter()
+        .map(|(mountpoint, line)| {
+            let spark = line
+                .iter()
+                .map(|cell| match cell {
+                    Some(ratio) => sparks[((ratio.clamp(0.0, 1.0) * 7.0).round()) as usize],
+                    None => ' ',
+                })
+                .collect();
+            (mountpoint, spark)
+        })
+        .collect()
+}
+
+/// Filesystems projected to fill up later than this are left out of forecasts.
+const FORECAST_HORIZON_DAYS: f64 = 365.0;
+
+/// Days until each recorded mountpoint is full if usage keeps growing like
+/// it did between `since` and `until`, keyed by mountpoint. Growth is the
+/// slope of a least-squares line through the samples, so one large copy
+/// that was deleted again does not dominate. Mountpoints that are not
+/// growing, or sampled over less than an hour, get no forecast.
+pub fn forecasts(samples: &[Sample], since: u64, until: u64) -> HashMap<String, f64> {
+    let mut series: HashMap<&str, Vec<&Sample>> = HashMap::new();
+    for sample in samples.iter().filter(|s| (since..=until).contains(&s.timestamp)) {
+        series.entry(&sample.mountpoint).or_default().push(sample);
+    }
+    series
+        .into_iter()
+        .filter_map(|(mountpoint, samples)| {
+            // Rows are out of order after a clock step or merged history files
+            let first = samples.iter().min_by_key(|s| s.timestamp)?;
+            let last = samples.iter().max_by_key(|s| s.timestamp)?;
+            if last.timestamp.saturating_sub(first.timestamp) < SECONDS_PER_HOUR {
+                return None;
+            }
+            let n = samples.len() as f64;
+            let mean_t = samples
+                .iter()
+                .map(|s| (s.timestamp - first.timestamp) as f64)
+                .sum::<f64>()
+                / n;
+            let mean_u = samples.iter().map(|s| s.used as f64).sum::<f64>() / n;
+            let (mut covariance, mut variance) = (0.0, 0.0);
+            for sample in &samples {
+                let t = (sample.timestamp - first.timestamp) as f64 - mean_t;
+                covariance += t * (sample.used as f64 - mean_u);
+                variance += t * t;
+            }
+            let bytes_per_day = covariance / variance * SECONDS_PER_DAY as f64;
+            if bytes_per_day <= 0.0 {
+                return None;
+            }
+            let days = last.size.saturating_sub(last.used) as f64 / bytes_per_day;
+            (days <= FORECAST_HORIZON_DAYS).then(|| (mountpoint.to_string(), days))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+    use crate::{Partition, clock::FakeClock};
+
+    fn drive(used: u64) -> Drive {
+        let mut partition = Partition::new("sdt/sdt1".to_string(), 2048, None);
+        partition.used = Some(used);
+        partition.mountpoint = Some("/data".to_string());
+        Drive::new("sdt", 0, vec![partition])
+    }
+
+    #[test]
+    fn samples_are_stamped_by_the_clock() {
+        let path = env::temp_dir().join(format!("prettyblk-history-test-{}.tsv", process::id()));
+        let clock = FakeClock::at(1_700_000_000);
+        record(&path, &[drive(100)], &Retention::default(), &clock).unwrap();
+        clock.advance(3600);
+        record(&path, &[drive(250)], &Retention::default(), &clock).unwrap();
+
+        let samples = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let stamps: Vec<(u64, u64)> = samples.iter().map(|s| (s.timestamp, s.used)).collect();
+        assert_eq!(stamps, [(1_700_000_000, 100), (1_700_003_600, 250)]);
+        assert_eq!(samples[0].size, 2048 * 512);
+    }
+
+    #[test]
+    fn window_follows_the_clock() {
+        let clock = FakeClock::at(30 * SECONDS_PER_DAY);
+        let samples: Vec<Sample> = (0..30)
+            .map(|day| Sample {
+                timestamp: day * SECONDS_PER_DAY,
+                device: "sdt1".to_string(),
+                mountpoint: "/data".to_string(),
+                size: 1000,
+                used: day,
+            })
+            .collect();
+
+        let since = clock.now() - 7 * SECONDS_PER_DAY;
+        let week: Vec<u64> = recent(&samples, "/data", since).iter().map(|s| s.used).collect();
+        assert_eq!(week, [23, 24, 25, 26, 27, 28, 29]);
+
+        clock.advance(3 * SECONDS_PER_DAY);
+        let since = clock.now() - 7 * SECONDS_PER_DAY;
+        assert_eq!(recent(&samples, "sdt1", since).len(), 4);
+    }
+
+    #[test]
+    fn old_samples_are_downsampled_then_dropped() {
+        let retention = Retention {
+            raw_days: 1,
+            hourly_days: 3,
+            daily_days: 10,
+        };
+        let clock = FakeClock::at(20 * SECONDS_PER_DAY);
+        // One sample every 15 minutes for the last 15 days
+        let samples: Vec<Sample> = (0..15 * 96)
+            .map(|i| Sample {
+                timestamp: 5 * SECONDS_PER_DAY + i * 900,
+                device: "sdt1".to_string(),
+                mountpoint: "/data".to_string(),
+                size: 1000,
+                used: i,
+            })
+            .collect();
+
+        let compacted = compact(&samples, &retention, clock.now());
+        let between = |from: u64, to: u64| {
+            let range = from * SECONDS_PER_DAY..to * SECONDS_PER_DAY;
+            compacted.iter().filter(|s| range.contains(&s.timestamp)).count()
+        };
+        assert_eq!(between(19, 20), 96);
+        assert_eq!(between(17, 19), 48);
+        assert_eq!(between(10, 17), 7);
+        assert_eq!(between(0, 10), 0);
+
+        // Each hourly bucket holds the rounded-down mean of its four samples
+        let hourly = compacted.iter().find(|s| s.timestamp == 17 * SECONDS_PER_DAY).unwrap();
+        let first = (12 * SECONDS_PER_DAY) / 900;
+        assert_eq!(hourly.used, first + 1);
+
+        assert_eq!(compact(&compacted, &retention, clock.now()), compacted);
+    }
+
+    #[test]
+    fn sparklines_keep_gaps_blank() {
+        let sample = |timestamp, used| Sample {
+            timestamp,
+            device: "sdt1".to_string(),
+            mountpoint: "/data".to_string(),
+            size: 700,
+            used,
+        };
+        // The last slice shows its latest sample; samples after `until` are ignored
+        let samples = [
+            sample(0, 0),
+            sample(25, 600),
+            sample(31, 100),
+            sample(39, 700),
+            sample(50, 0),
+        ];
+        let lines = sparklines(&samples, 0, 40, 4);
+        assert_eq!(lines["/data"], "▁ ▇█");
+    }
+
+    #[test]
+    fn forecasts_follow_the_growth_trend() {
+        let sample = |mountpoint: &str, day: u64, used| Sample {
+            timestamp: day * SECONDS_PER_DAY,
+            device: "sdt1".to_string(),
+            mountpoint: mountpoint.to_string(),
+            size: 1000,
+            used,
+        };
+        // /data grows 10 a day with a spike on day 2, /static does not grow;
+        // day 3 was recorded before a clock step back
+        let samples = [
+            sample("/data", 3, 530),
+            sample("/data", 0, 500),
+            sample("/data", 1, 510),
+            sample("/data", 2, 700),
+            sample("/data", 4, 540),
+            sample("/static", 0, 900),
+            sample("/static", 4, 900),
+        ];
+        let forecasts = forecasts(&samples, 0, 4 * SECONDS_PER_DAY);
+        assert_eq!(forecasts.len(), 1);
+        assert!((forecasts["/data"] - 46.0).abs() < 1.0, "{}", forecasts["/data"]);
+    }
+
+    #[test]
+    fn dates_are_utc_days() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(1_700_000_000), "2023-11-14");
+    }
+}
@@ -0,0 +1,65 @@
+//! Which machine output was collected on and when, stamped on reports and
+//! machine-readable output so files gathered from many hosts stay
+//! attributable. `--no-identity` leaves it out.
+
+use crate::clock::{Clock, format_time};
+
+pub struct Identity {
+    pub hostname: String,
+    /// Kernel name and release, e.g. `Linux 6.8.0-45-generic`; unknown on
+    /// Windows
+    pub kernel: Option<String>,
+    /// When the data was collected, in unix seconds
+    pub timestamp: u64,
+}
+
+impl Identity {
+    pub fn collect(clock: &dyn Clock) -> Identity {
+        Identity {
+            hostname: hostname(),
+            kernel: kernel(),
+            timestamp: clock.now(),
+        }
+    }
+
+    /// `db1 (Linux 6.8.0-45-generic), collected 2026-03-02 04:00 UTC`
+    pub fn describe(&self) -> String {
+        match &self.kernel {
+            Some(kernel) => format!(
+                "{} ({}), collected {}",
+                self.hostname,
+                kernel,
+                format_time(self.timestamp)
+            ),
+            None => format!("{}, collected {}", self.hostname, format_time(self.timestamp)),
+        }
+    }
+}
+
+#[cfg(unix)]
+pub fn hostname() -> String {
+    nix::unistd::gethostname()
+        .ok()
+        .and_then(|name| name.into_string().ok())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+#[cfg(windows)]
+pub fn hostname() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| "localhost".to_string())
+}
+
+#[cfg(unix)]
+fn kernel() -> Option<String> {
+    let uname = nix::sys::utsname::uname().ok()?;
+    Some(format!(
+        "{} {}",
+        uname.sysname().to_string_lossy(),
+        uname.release().to_string_lossy()
+    ))
+}
+
+#[cfg(windows)]
+fn kernel() -> Option<String> {
+    None
+}
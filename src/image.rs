@@ -0,0 +1,185 @@
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use crate::{Drive, Partition, ptable, winfs};
+
+/// Filesystem signatures: byte offset into the partition, magic, name.
+const SIGNATURES: [(u64, &[u8], &str); 11] = [
+    (0, b"XFSB", "xfs"),
+    (0, b"hsqs", "squashfs"),
+    (3, b"NTFS    ", "ntfs"),
+    (3, b"EXFAT   ", "exfat"),
+    (3, b"-FVE-FS-", "BitLocker"),
+    (54, b"FAT1", "vfat"),
+    (82, b"FAT32", "vfat"),
+    (1024, &[0xe2, 0xe1, 0xf5, 0xe0], "erofs"),
+    (1080, &[0x53, 0xef], "ext"),
+    (4086, b"SWAPSPACE2", "swap"),
+    (0x10040, b"_BHRfS_M", "btrfs"),
+];
+
+fn matches(file: &mut File, offset: u64, magic: &[u8]) -> bool {
+    let mut buffer = vec![0; magic.len()];
+    file.seek(SeekFrom::Start(offset)).is_ok() && file.read_exact(&mut buffer).is_ok() && buffer == magic
+}
+
+fn le_u32(file: &mut File, offset: u64) -> u32 {
+    let mut buffer = [0; 4];
+    match file
+        .seek(SeekFrom::Start(offset))
+        .and_then(|_| file.read_exact(&mut buffer))
+    {
+        Ok(()) => u32::from_le_bytes(buffer),
+        Err(_) => 0,
+    }
+}
+
+/// The filesystem starting `start` bytes into the image, from its superblock magic.
+fn probe(file: &mut File, start: u64) -> Option<String> {
+    let (_, _, name) = SIGNATURES
+        .iter()
+        .find(|(offset, magic, _)| matches(file, start.saturating_add(*offset), magic))?;
+    if *name != "ext" {
+        return Some(name.to_string());
+    }
+    // The ext generations share a magic and differ in feature flags
+    let features = (
+        le_u32(file, start.saturating_add(1116)),
+        le_u32(file, start.saturating_add(1120)),
+    );
+    let name = match (features.0 & 0x4, features.1 & 0x40) {
+        (_, 0x40) => "ext4",
+        (0x4, _) => "ext3",
+        _ => "ext2",
+    };
+    Some(name.to_string())
+}
+
+/// The filesystem at the start of `file`, e.g. `squashfs`.
+pub fn filesystem(file: &mut File) -> Option<String> {
+    probe(file, 0)
+}
+
+/// Whether the start of `file` carries anything blkid would recognize: a
+/// filesystem, a partition table, an LVM physical volume or an md superblock.
+pub fn has_signature(file: &mut File) -> bool {
+    probe(file, 0).is_some()
+        || matches(file, 510, &[0x55, 0xaa])
+        || matches(file, 512, b"LABELONE")
+        || matches(file, 4096, &[0xfc, 0x4e, 0x2b, 0xa9])
+}
+
+/// Reads the partition table of the raw disk image at `path` into a drive
+/// named after the file, so it can be charted without attaching it to a
+/// loop device. Partitions are named like loop partitions, `disk.imgp1`.
+pub fn drive(path: &Path) -> Result<Drive, String> {
+    let fail = |e: std::io::Error| format!("cannot read {}: {}", path.display(), e);
+    let mut file = File::open(path).map_err(fail)?;
+    let bytes = file.metadata().map_err(fail)?.len();
+    if matches(&mut file, 0, b"QFI\xfb") {
+        return Err(format!(
+            "{} is a qcow2 image; convert it first with qemu-img convert -O raw",
+            path.display()
+        ));
+    }
+    let table =
+        ptable::read_image(&mut file).ok_or_else(|| format!("{} has no GPT or MBR partition table", path.display()))?;
+
+    let name = path
+        .file_name()
+        .map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned());
+    let mut entries: Vec<(u32, ptable::Entry)> = table.entries.into_iter().collect();
+    entries.sort_by_key(|(number, _)| *number);
+    let partitions = entries
+        .into_iter()
+        .map(|(number, entry)| {
+            let mut partition = Partition::new(format!("{}/{}p{}", name, name, number), entry.sectors, None);
+            partition.fstype = probe(&mut file, entry.start.saturating_mul(512));
+            partition.windows = partition
+                .fstype
+                .as_deref()
+                .and_then(|fstype| winfs::Windows::of(fstype, &[]));
+            partition.start = Some(entry.start);
+            partition.table_entry = Some(entry);
+            partition
+        })
+        .collect();
+
+    let mut drive = Drive::new(&name, bytes / 512, partitions);
+    drive.table = Some(table.kind);
+    Ok(drive)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, io::Write};
+
+    use super::*;
+
+    #[test]
+    fn mbr_images_are_read_without_a_loop_device() {
+        // 8 MiB image: a FAT32 partition from 1 MiB and an ext4 one from 4 MiB
+        let mut image = vec![0u8; 8 << 20];
+        let mut slot = |i: usize, kind: u8, start: u32, sectors: u32| {
+            let entry = &mut image[446 + i * 16..462 + i * 16];
+            entry[4] = kind;
+            entry[8..12].copy_from_slice(&start.to_le_bytes());
+            entry[12..16].copy_from_slice(&sectors.to_le_bytes());
+        };
+        slot(0, 0x0c, 2048, 6144);
+        slot(1, 0x83, 8192, 8192);
+        image[510..512].copy_from_slice(&[0x55, 0xaa]);
+        image[2048 * 512 + 82..][..5].copy_from_slice(b"FAT32");
+        image[8192 * 512 + 1080..][..2].copy_from_slice(&[0x53, 0xef]);
+        image[8192 * 512 + 1120] = 0x40;
+
+        let path = env::temp_dir().join(format!("prettyblk-image-{}.img", std::process::id()));
+        File::create(&path).and_then(|mut f| f.write_all(&image)).unwrap();
+        let drive = drive(&path);
+        fs::remove_file(&path).unwrap();
+
+        let drive = drive.unwrap();
+        assert_eq!(drive.size_bytes(), 8 << 20);
+        let rows: Vec<(&str, u64, Option<&str>)> = drive
+            .partitions()
+            .iter()
+            .map(|p| (p.dev_name(), p.size_bytes(), p.fstype()))
+            .collect();
+        let name = path.file_name().unwrap().to_string_lossy();
+        assert_eq!(
+            rows,
+            [
+                (&*format!("{}p1", name), 3 << 20, Some("vfat")),
+                (&*format!("{}p2", name), 4 << 20, Some("ext4")),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_gpt_headers_are_refused_without_aborting() {
+        let path = env::temp_dir().join(format!("prettyblk-image-gpt-{}.img", std::process::id()));
+        let header = |entries_lba: u64, entry_size: u32, last: u64| {
+            let mut image = vec![0u8; 1 << 20];
+            image[446 + 4] = 0xee;
+            image[510..512].copy_from_slice(&[0x55, 0xaa]);
+            image[512..520].copy_from_slice(b"EFI PART");
+            image[512 + 72..512 + 80].copy_from_slice(&entries_lba.to_le_bytes());
+            image[512 + 80..512 + 84].copy_from_slice(&128u32.to_le_bytes());
+            image[512 + 84..512 + 88].copy_from_slice(&entry_size.to_le_bytes());
+            image[1024] = 0xaf;
+            image[1024 + 32..1024 + 40].copy_from_slice(&2048u64.to_le_bytes());
+            image[1024 + 40..1024 + 48].copy_from_slice(&last.to_le_bytes());
+            File::create(&path).and_then(|mut f| f.write_all(&image)).unwrap();
+            drive(&path).map(|drive| drive.partitions().len())
+        };
+        assert_eq!(header(2, 128, 4095), Ok(1));
+        assert!(header(2, 0x7fff_ffff, 4095).is_err());
+        assert!(header(2, 130, 4095).is_err());
+        assert!(header(1 << 62, 128, 4095).is_err());
+        assert!(header(2, 128, u64::MAX).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,184 @@
+use std::{collections::BTreeMap, fs::read_to_string, process::Command};
+
+use colored::*;
+
+use crate::{Drive, cli::Format, format::escape_field, identity::hostname, text, units::Units};
+
+const COLUMNS: [&str; 5] = ["model", "firmware", "size_bytes", "count", "hosts"];
+
+/// Drives that share a model, firmware and size, counted across hosts.
+#[derive(Default)]
+struct Group {
+    count: u64,
+    hosts: Vec<String>,
+}
+
+type Key = (String, String, u64);
+
+fn attribute(drive: &str, names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .filter_map(|name| read_to_string(format!("/sys/block/{}/device/{}", drive, name)).ok())
+        .map(|value| value.trim().to_string())
+        .find(|value| !value.is_empty())
+}
+
+/// Model, firmware and size of the physical drives on this host. SCSI and
+/// SATA disks report `model` and `rev`, NVMe controllers `model` and
+/// `firmware_rev`. A multipath map is one disk, described by its first path.
+fn local(drives: &[Drive]) -> Vec<Key> {
+    drives
+        .iter()
+        .filter(|d| d.origin.is_none())
+        .map(|drive| {
+            let device = match &drive.multipath {
+                Some(paths) => paths.first().map_or(drive.name.as_str(), |p| p.name.as_str()),
+                None => drive.name.as_str(),
+            };
+            (
+                attribute(device, &["model"]).unwrap_or_else(|| "unknown".to_string()),
+                attribute(device, &["firmware_rev", "rev"]).unwrap_or_default(),
+                drive.size * 512,
+            )
+        })
+        .collect()
+}
+
+/// Runs `pblk inventory` on `host` over ssh and reads back its TSV rows.
+fn remote(host: &str) -> Result<Vec<(Key, Group)>, String> {
+    let output = Command::new("ssh")
+        .args([
+            "-o",
+            "BatchMode=yes",
+            "--",
+            host,
+            "pblk",
+            "inventory",
+            "--format",
+            "tsv",
+        ])
+        .output()
+        .map_err(|e| format!("cannot run ssh: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    let mut rows = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines().skip(1) {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [model, firmware, size, count, hosts] = fields.as_slice() else {
+            return Err(format!("unexpected inventory line '{}'", line));
+        };
+        let (Ok(size), Ok(count)) = (size.parse(), count.parse()) else {
+            return Err(format!("unexpected inventory line '{}'", line));
+        };
+        let group = Group {
+            count,
+            hosts: hosts.split(", ").map(String::from).collect(),
+        };
+        rows.push(((model.to_string(), firmware.to_string(), size), group));
+    }
+    Ok(rows)
+}
+
+/// Prints drive models, firmware versions and sizes with how many of each
+/// there are on this host and on every `--host`. Returns false if a host
+/// could not be queried.
+pub fn run(drives: &[Drive], hosts: &[String], format: Format, units: &Units) -> bool {
+    let mut groups: BTreeMap<Key, Group> = BTreeMap::new();
+    let mut add = |key: Key, count: u64, hosts: &[String]| {
+        let group = groups.entry(key).or_default();
+        group.count += count;
+        for host in hosts {
+            if !group.hosts.contains(host) {
+                group.hosts.push(host.clone());
+            }
+        }
+    };
+
+    let mut ok = true;
+    if hosts.is_empty() {
+        let host = [hostname()];
+        for key in local(drives) {
+            add(key, 1, &host);
+        }
+    }
+    for host in hosts {
+        match remote(host) {
+            Ok(rows) => {
+                for (key, group) in rows {
+                    add(key, group.count, &group.hosts);
+                }
+            }
+            Err(e) => {
+                eprintln!("pblk: inventory of {}: {}", host, e);
+                ok = false;
+            }
+        }
+    }
+
+    let mut rows: Vec<(Key, Group)> = groups.into_iter().collect();
+    rows.sort_by(|(a_key, a), (b_key, b)| b.count.cmp(&a.count).then(a_key.cmp(b_key)));
+
+    let separator = match format {
+        Format::Pretty => None,
+        Format::Csv => Some(','),
+        Format::Tsv => Some('\t'),
+        Format::Env | Format::Kv => {
+            eprintln!("pblk: inventory supports --format pretty, csv and tsv");
+            return false;
+        }
+    };
+    if let Some(separator) = separator {
+        println!("{}", COLUMNS.join(&separator.to_string()));
+        for ((model, firmware, size), group) in &rows {
+            let row = [
+                escape_field(model, separator),
+                escape_field(firmware, separator),
+                size.to_string(),
+                group.count.to_string(),
+                escape_field(&group.hosts.join(", "), separator),
+            ];
+            println!("{}", row.join(&separator.to_string()));
+        }
+        return ok;
+    }
+
+    let total: u64 = rows.iter().map(|(_, group)| group.count).sum();
+    println!(
+        "\n{} {} drives, {} kinds",
+        "Inventory:".bold().blue(),
+        total,
+        rows.len()
+    );
+    let sizes: Vec<String> = rows.iter().map(|((_, _, size), _)| units.size.format(*size)).collect();
+    let column = |width: fn(&Key) -> usize, header: &str| {
+        rows.iter()
+            .map(|(key, _)| width(key))
+            .max()
+            .unwrap_or(0)
+            .max(header.len())
+    };
+    let model_width = column(|(model, _, _)| text::width(model), "MODEL");
+    let firmware_width = column(|(_, firmware, _)| text::width(firmware), "FIRMWARE");
+    let size_width = sizes.iter().map(|size| size.len()).max().unwrap_or(0).max(4);
+    println!(
+        "  {:>5}  {}  {}  {}  {}",
+        "COUNT".dimmed(),
+        text::pad_right("MODEL", model_width).dimmed(),
+        text::pad_left("SIZE", size_width).dimmed(),
+        text::pad_right("FIRMWARE", firmware_width).dimmed(),
+        "HOSTS".dimmed()
+    );
+    for (((model, firmware, _), group), size) in rows.iter().zip(&sizes) {
+        println!(
+            "  {:>5}  {}  {}  {}  {}",
+            group.count,
+            text::pad_right(&text::isolate(model), model_width),
+            text::pad_left(size, size_width),
+            text::pad_right(firmware, firmware_width),
+            group.hosts.join(", ")
+        );
+    }
+    ok
+}
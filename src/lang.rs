@@ -0,0 +1,238 @@
+//! Translated labels and decimal separators, chosen with `--lang` or the
+//! locale environment variables.
+
+use std::{env, sync::OnceLock};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lang {
+    English,
+    German,
+    French,
+    Spanish,
+    Portuguese,
+}
+
+const ALL: [Lang; 5] = [
+    Lang::English,
+    Lang::German,
+    Lang::French,
+    Lang::Spanish,
+    Lang::Portuguese,
+];
+
+/// A label of the charts that is translated. Each translation comes with
+/// an ASCII spelling for terminals without UTF-8.
+#[derive(Clone, Copy)]
+pub enum Label {
+    Drive,
+    Unmounted,
+    NoMedia,
+    /// After the per-drive sum in `--summary`
+    Mounted,
+    Total,
+    Raw,
+    Formatted,
+    Used,
+    Free,
+    /// A disk with nothing on it
+    Unused,
+}
+
+impl Lang {
+    /// A language code such as `de`, or a locale such as `de_DE.UTF-8`.
+    /// `C` and `POSIX` are English.
+    pub fn parse(value: &str) -> Option<Lang> {
+        let code = value
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match code.as_str() {
+            "c" | "posix" => Some(Lang::English),
+            _ => ALL.into_iter().find(|lang| lang.code() == code),
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Lang::English => "en",
+            Lang::German => "de",
+            Lang::French => "fr",
+            Lang::Spanish => "es",
+            Lang::Portuguese => "pt",
+        }
+    }
+
+    pub fn codes() -> Vec<&'static str> {
+        ALL.iter().map(|lang| lang.code()).collect()
+    }
+
+    fn decimal_separator(self) -> char {
+        match self {
+            Lang::English => '.',
+            _ => ',',
+        }
+    }
+
+    /// The label in this language, and its ASCII spelling.
+    fn label(self, label: Label) -> (&'static str, &'static str) {
+        use Label::*;
+        use Lang::*;
+        let same = |text| (text, text);
+        match (self, label) {
+            (English, Drive) => same("Drive:"),
+            (English, Unmounted) => same("Unmounted"),
+            (English, NoMedia) => same("no media"),
+            (English, Mounted) => same("mounted"),
+            (English, Total) => same("Total:"),
+            (English, Raw) => same("raw"),
+            (English, Formatted) => same("formatted"),
+            (English, Used) => same("used"),
+            (English, Free) => same("free"),
+            (English, Unused) => same("unused"),
+
+            (German, Drive) => same("Laufwerk:"),
+            (German, Unmounted) => ("Nicht eingehängt", "Nicht eingehaengt"),
+            (German, NoMedia) => same("kein Medium"),
+            (German, Mounted) => ("eingehängt", "eingehaengt"),
+            (German, Total) => same("Gesamt:"),
+            (German, Raw) => same("roh"),
+            (German, Formatted) => same("formatiert"),
+            (German, Used) => same("belegt"),
+            (German, Free) => same("frei"),
+            (German, Unused) => same("unbenutzt"),
+
+            (French, Drive) => same("Disque :"),
+            (French, Unmounted) => ("Non monté", "Non monte"),
+            (French, NoMedia) => same("aucun support"),
+            (French, Mounted) => ("monté", "monte"),
+            (French, Total) => same("Total :"),
+            (French, Raw) => same("brut"),
+            (French, Formatted) => ("formaté", "formate"),
+            (French, Used) => ("utilisé", "utilise"),
+            (French, Free) => same("libre"),
+            (French, Unused) => ("inutilisé", "inutilise"),
+
+            (Spanish, Drive) => same("Disco:"),
+            (Spanish, Unmounted) => same("Sin montar"),
+            (Spanish, NoMedia) => same("sin medio"),
+            (Spanish, Mounted) => same("montado"),
+            (Spanish, Total) => same("Total:"),
+            (Spanish, Raw) => same("bruto"),
+            (Spanish, Formatted) => same("formateado"),
+            (Spanish, Used) => same("usado"),
+            (Spanish, Free) => same("libre"),
+            (Spanish, Unused) => same("sin usar"),
+
+            (Portuguese, Drive) => same("Disco:"),
+            (Portuguese, Unmounted) => ("Não montado", "Nao montado"),
+            (Portuguese, NoMedia) => ("sem mídia", "sem midia"),
+            (Portuguese, Mounted) => same("montado"),
+            (Portuguese, Total) => same("Total:"),
+            (Portuguese, Raw) => same("bruto"),
+            (Portuguese, Formatted) => same("formatado"),
+            (Portuguese, Used) => same("usado"),
+            (Portuguese, Free) => same("livre"),
+            (Portuguese, Unused) => ("não usado", "nao usado"),
+        }
+    }
+}
+
+/// The language and whether the terminal can show more than ASCII.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Locale {
+    pub lang: Lang,
+    /// Labels use their ASCII spellings and charts the glyphs of `--ascii`
+    pub ascii: bool,
+}
+
+/// The first of the locale variables `names` that is set.
+fn locale_var(names: [&str; 3]) -> String {
+    names
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default()
+}
+
+/// Terminals that predate UTF-8, as serial consoles tend to announce themselves.
+fn ascii_terminal(term: &str) -> bool {
+    term == "dumb" || term.starts_with("vt") || term.starts_with("ansi")
+}
+
+impl Locale {
+    /// The language from `LC_ALL`, `LC_MESSAGES` or `LANG` and the character
+    /// set from `LC_ALL`, `LC_CTYPE` or `LANG`, the first one set in each
+    /// case. Languages without a translation fall back to English, and
+    /// character sets other than UTF-8 or old terminal types to ASCII.
+    pub fn from_env() -> Locale {
+        let ctype = locale_var(["LC_ALL", "LC_CTYPE", "LANG"]);
+        let term = env::var("TERM").unwrap_or_default();
+        Locale {
+            lang: Lang::parse(&locale_var(["LC_ALL", "LC_MESSAGES", "LANG"])).unwrap_or(Lang::English),
+            ascii: !utf8(&ctype) || ascii_terminal(&term),
+        }
+    }
+}
+
+/// Whether a locale like `de_DE.UTF-8` names a UTF-8 character set. An
+/// unset locale is taken as UTF-8, which is what terminals default to.
+fn utf8(locale: &str) -> bool {
+    let Some((_, charset)) = locale.split('@').next().unwrap_or_default().split_once('.') else {
+        return locale.is_empty();
+    };
+    matches!(charset.to_ascii_lowercase().as_str(), "utf-8" | "utf8")
+}
+
+static LOCALE: OnceLock<Locale> = OnceLock::new();
+
+/// Sets the locale for the rest of the process. Without a call everything
+/// is English, so library users get the same output as before.
+pub fn set(locale: Locale) {
+    let _ = LOCALE.set(locale);
+}
+
+fn current() -> Locale {
+    LOCALE.get().copied().unwrap_or(Locale {
+        lang: Lang::English,
+        ascii: false,
+    })
+}
+
+/// Whether only ASCII should be printed.
+pub fn ascii() -> bool {
+    current().ascii
+}
+
+/// `label` translated into the current language.
+pub fn text(label: Label) -> &'static str {
+    let locale = current();
+    let (text, ascii) = locale.lang.label(label);
+    if locale.ascii { ascii } else { text }
+}
+
+/// `value` with `decimals` digits after the current decimal separator.
+pub fn number(value: f64, decimals: usize) -> String {
+    let text = format!("{:.*}", decimals, value);
+    match current().lang.decimal_separator() {
+        '.' => text,
+        separator => text.replace('.', &separator.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locales_name_a_language_and_charset() {
+        assert_eq!(Lang::parse("de_DE.UTF-8"), Some(Lang::German));
+        assert_eq!(Lang::parse("pt-BR"), Some(Lang::Portuguese));
+        assert_eq!(Lang::parse("C.UTF-8"), Some(Lang::English));
+        assert_eq!(Lang::parse("ja_JP.eucJP"), None);
+        assert!(utf8("fr_FR.utf8@euro"));
+        assert!(!utf8("fr_FR@euro"));
+        assert!(!utf8("C"));
+        assert!(utf8(""));
+    }
+}
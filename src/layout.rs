@@ -0,0 +1,78 @@
+use colored::*;
+
+use crate::{Drive, Partition, text, units::SizeUnit};
+
+/// Partitions should start on a multiple of this many 512-byte sectors
+/// (1 MiB), which suits every erase block and RAID stripe in common use.
+pub const ALIGNMENT: u64 = 2048;
+
+/// Partitions whose start is known and not a multiple of [`ALIGNMENT`].
+pub fn misaligned(drive: &Drive) -> impl Iterator<Item = &Partition> {
+    drive
+        .partitions
+        .iter()
+        .filter(|p| p.start.is_some_and(|start| start % ALIGNMENT != 0))
+}
+
+/// Prints the partitions of every drive in on-disk order with their first
+/// and last sector, and the unpartitioned gaps between them.
+pub fn print(drives: &[Drive]) {
+    // Alignment is about MiB boundaries, so sizes are shown in MiB too
+    let mib = SizeUnit::parse("MiB").unwrap();
+    for drive in drives {
+        let mut partitions: Vec<(u64, &Partition)> =
+            drive.partitions.iter().filter_map(|p| Some((p.start?, p))).collect();
+        if partitions.is_empty() {
+            continue;
+        }
+        partitions.sort_by_key(|(start, _)| *start);
+
+        println!(
+            "\n{} {} ({} sectors)",
+            "Drive:".bold().blue(),
+            drive.display_name().bold(),
+            drive.size
+        );
+        let name_width = partitions
+            .iter()
+            .map(|(_, p)| text::width(p.dev_name()))
+            .max()
+            .unwrap_or(0)
+            .max(4);
+        println!(
+            "  {:name_width$} {:>12} {:>12} {:>10}",
+            "name".dimmed(),
+            "start".dimmed(),
+            "end".dimmed(),
+            "size".dimmed()
+        );
+        let row = |name: ColoredString, start: u64, sectors: u64, note: Option<ColoredString>| {
+            println!(
+                "  {:name_width$} {:>12} {:>12} {:>10}{}",
+                name,
+                start,
+                start + sectors - 1,
+                mib.format(sectors * 512),
+                note.map_or_else(String::new, |note| format!(" {}", note))
+            );
+        };
+
+        let mut cursor = 0;
+        for (start, partition) in partitions {
+            if start > cursor {
+                row("free".dimmed(), cursor, start - cursor, None);
+            }
+            let note = (start % ALIGNMENT != 0).then(|| "not 1 MiB aligned".yellow());
+            row(
+                text::isolate(partition.dev_name()).normal(),
+                start,
+                partition.size.max(1),
+                note,
+            );
+            cursor = cursor.max(start + partition.size);
+        }
+        if drive.size > cursor {
+            row("free".dimmed(), cursor, drive.size - cursor, None);
+        }
+    }
+}
@@ -0,0 +1,2190 @@
+//! Drive discovery and usage behind the `pblk` binary.
+//!
+//! [`source::Sources`] decides where a scan gets its mount table and
+//! filesystem usage from, so both can be replaced:
+//!
+//! ```no_run
+//! use std::{collections::HashMap, sync::Arc};
+//!
+//! use prettyblk::{source::{FsStat, Sources}, Mount};
+//!
+//! let mounts = HashMap::from([("/dev/sda1".to_string(), Mount::new("/srv", "xfs", &["rw"]))]);
+//! let sources = Sources {
+//!     mounts: Box::new(mounts),
+//!     statvfs: Arc::new(|_: &str| Ok(FsStat { blocks: 100, blocks_free: 25, block_size: 4096, ..FsStat::default() })),
+//!     ..Sources::system()
+//! };
+//! for drive in sources.scan() {
+//!     for partition in drive.partitions() {
+//!         println!("{} {:?} {:?}", partition.dev_name(), partition.mountpoint(), partition.used_bytes());
+//!     }
+//! }
+//! ```
+//!
+//! What could not be read is kept as a [`PrettyblkError`], e.g.
+//! [`Partition::size_error`] and [`Partition::usage_error`], and
+//! [`source::Sources::try_scan`] fails when there is no mount table.
+//!
+//! [`bar::geometry`] lays out the partition bar of a drive the way the
+//! terminal charts do, as offsets and widths a GUI can scale to pixels.
+
+/// Prints a warning to stderr unless --quiet was given. Errors that end the
+/// run with a failure status use `eprintln!` and are always shown.
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        if !crate::QUIET.load(std::sync::atomic::Ordering::Relaxed) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+mod alert;
+#[cfg(test)]
+mod arbitrary;
+mod audit;
+pub mod bar;
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+mod bay;
+mod bench;
+mod btrfs;
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+mod cache;
+mod child;
+mod cli;
+mod clock;
+mod collectors;
+mod columns;
+mod compare;
+mod compression;
+mod config;
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+mod cow;
+#[cfg(feature = "doctor")]
+mod doctor;
+pub mod error;
+mod export;
+#[cfg(feature = "exporter")]
+mod exporter;
+mod format;
+mod fshealth;
+mod fstab;
+mod glyph;
+mod growth;
+#[cfg(feature = "history")]
+mod history;
+mod identity;
+mod image;
+mod inventory;
+mod json;
+mod lang;
+mod layout;
+mod loops;
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+mod memory;
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+mod multipath;
+mod pager;
+mod pct;
+mod pick;
+#[cfg(feature = "exporter")]
+mod pidfile;
+mod plan;
+mod platform;
+#[cfg(feature = "history")]
+mod plot;
+mod power;
+mod privilege;
+mod ptable;
+mod quota;
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+mod remote;
+mod report;
+mod scan;
+mod severity;
+#[cfg(feature = "smart")]
+mod smart;
+mod snapshot;
+pub mod source;
+mod swap;
+mod text;
+mod theme;
+mod tree;
+mod tuning;
+mod udev;
+mod units;
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+mod verity;
+mod watch;
+mod winfs;
+#[cfg(feature = "zfs")]
+mod zfs;
+
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use colored::*;
+use terminal_size::{Width, terminal_size};
+
+use cli::{Args, Command, Export, Format};
+use clock::SystemClock;
+use columns::{Column, Sort, SortKey};
+pub use error::PrettyblkError;
+use lang::Label;
+use severity::{Severity, Thresholds};
+use units::Units;
+
+pub struct Drive {
+    name: String,
+    size: u64,
+    /// Why `size` is zero when it could not be read
+    size_error: Option<PrettyblkError>,
+    partitions: Vec<Partition>,
+    table: Option<&'static str>,
+    alias: Option<String>,
+    multipath: Option<Vec<multipath::PathState>>,
+    /// The kernel refuses writes to the whole device (`/sys/block/<dev>/ro`)
+    read_only: bool,
+    /// Set for virtual drives reported by an external collector
+    origin: Option<collectors::Origin>,
+    /// The drive is a logical volume of this hardware RAID controller family
+    raid_controller: Option<&'static str>,
+    /// The media can be ejected (`/sys/block/<dev>/removable`), e.g. SD cards
+    removable: bool,
+    /// Attached over USB, which covers thumb drives that do not set `removable`
+    usb: bool,
+    model: Option<String>,
+    /// Spinning media (`queue/rotational`), unknown for virtual drives
+    rotational: Option<bool>,
+    /// A card reader or optical drive with nothing inserted
+    no_media: bool,
+    /// The iSCSI target or NBD server a network block device reads from
+    remote: Option<remote::Target>,
+    /// No partitions, holders or signatures: a spare for a new array or pool
+    unused: bool,
+    /// The enclosure or PCIe slot the drive sits in
+    bay: Option<bay::Bay>,
+    /// Cache tiers whose cache device is this drive or one of its partitions
+    caching: Vec<cache::Tier>,
+    /// The drive's health verdict, filled in for --smart
+    #[cfg(feature = "smart")]
+    smart: Option<smart::Health>,
+    /// Why --smart has no verdict, unless the drive simply gave none
+    #[cfg(feature = "smart")]
+    smart_error: Option<PrettyblkError>,
+}
+
+pub struct Partition {
+    name: String,
+    size: u64,
+    /// Why `size` is zero when it could not be read
+    size_error: Option<PrettyblkError>,
+    /// First sector on the drive, for partitions whose table says so
+    start: Option<u64>,
+    used: Option<u64>,
+    /// Free bytes as reported by statvfs, including the root reserve
+    free: Option<u64>,
+    /// statvfs did not answer within the scan timeout
+    stalled: bool,
+    /// Why a mounted filesystem has no usage
+    usage_error: Option<PrettyblkError>,
+    inodes: Option<Inodes>,
+    /// Where the filesystem was mounted first; usage is measured there
+    mountpoint: Option<String>,
+    /// Further places it is mounted, bind mounts and other btrfs subvolumes
+    also_mounted: Vec<String>,
+    fstype: Option<String>,
+    pool: Option<btrfs::PoolMember>,
+    swap: Option<swap::Swap>,
+    swap_files: Vec<swap::Swap>,
+    loops: Vec<loops::Loop>,
+    zfs_pool: Option<String>,
+    table_entry: Option<ptable::Entry>,
+    alias: Option<String>,
+    mount_options: Vec<String>,
+    read_only: bool,
+    /// Where fstab or crypttab would mount this partition if it is not mounted
+    intended: Option<fstab::Intended>,
+    /// Partition UUID, or the filesystem UUID where the table has none
+    uuid: Option<String>,
+    /// Set on a bcache or dm-cache device, naming its cache and backing devices
+    cache: Option<cache::Tier>,
+    /// Set on a dm snapshot, naming its origin
+    snapshot: Option<cow::Snapshot>,
+    /// Map names of the snapshots taken of this device
+    snapshots: Vec<String>,
+    /// Devices built on this one, e.g. `md0 (raid1)` or `vg0-root (LVM)`
+    holders: Vec<String>,
+    /// Kernel names of the devices this one is built on
+    slaves: Vec<String>,
+    /// What transparent compression saves, filled in for --verbose
+    compression: Option<compression::Savings>,
+    /// Superblock state of an unmounted filesystem, filled in for --fs-health
+    fs_health: Option<fshealth::Health>,
+    /// Set on a dm-verity map, naming its data and hash devices
+    verity: Option<verity::Verity>,
+    /// What a dm-verity map keeps on this device: data, hashes or both
+    verity_backing: Option<&'static str>,
+    /// A read-only image filesystem (squashfs, erofs) found on a device
+    /// that is not mounted
+    image_fs: Option<String>,
+    /// NTFS, exFAT or BitLocker on a device that is not mounted
+    windows: Option<winfs::Windows>,
+    /// Top quota consumers, filled in for --quota
+    quota: Vec<quota::Report>,
+    /// Bytes written per day as given to --assume-growth
+    assumed_growth: Option<u64>,
+    /// Nesting below the drive: 0 for partitions, 1 for a device stacked on a
+    /// partition (crypt, LVM, md), 2 for one stacked on that, and so on.
+    /// Stacked devices directly follow the device they are built on.
+    depth: usize,
+}
+
+pub struct Inodes {
+    used: u64,
+    total: u64,
+}
+
+#[derive(Clone)]
+pub struct Mount {
+    path: String,
+    fstype: String,
+    options: Vec<String>,
+    /// Other paths the same device is mounted at, in mount order
+    also: Vec<String>,
+}
+
+impl Mount {
+    pub fn new(path: &str, fstype: &str, options: &[&str]) -> Mount {
+        Mount {
+            path: path.to_string(),
+            fstype: fstype.to_string(),
+            options: options.iter().map(|o| o.to_string()).collect(),
+            also: Vec::new(),
+        }
+    }
+
+    /// The primary path, then the others.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.path.as_str()).chain(self.also.iter().map(String::as_str))
+    }
+}
+
+/// 64-bit FNV-1a, which unlike the std hashers is the same in every build.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Largest size in 512-byte sectors whose byte count still fits in a u64;
+/// larger sizes are capped so byte arithmetic cannot overflow.
+const MAX_SECTORS: u64 = u64::MAX / 512;
+
+pub const PALETTE: [Color; 5] = [Color::Green, Color::Yellow, Color::Blue, Color::Magenta, Color::Cyan];
+
+impl Partition {
+    /// A partition of `size` 512-byte sectors. `name` is the device name,
+    /// or `drive/partition` for partitions that live under their drive in
+    /// sysfs.
+    pub fn new(_name: String, size: u64, mount: Option<&Mount>) -> Partition {
+        Partition {
+            name: _name.clone(),
+            size: size.min(MAX_SECTORS),
+            size_error: None,
+            start: None,
+            used: None,
+            free: None,
+            stalled: false,
+            usage_error: None,
+            inodes: None,
+            mountpoint: mount.map(|m| m.path.clone()),
+            also_mounted: mount.map(|m| m.also.clone()).unwrap_or_default(),
+            fstype: mount.map(|m| m.fstype.clone()),
+            pool: None,
+            swap: None,
+            swap_files: Vec::new(),
+            loops: Vec::new(),
+            zfs_pool: None,
+            table_entry: None,
+            alias: None,
+            mount_options: mount.map(|m| m.options.clone()).unwrap_or_default(),
+            read_only: false,
+            intended: None,
+            uuid: None,
+            cache: None,
+            snapshot: None,
+            snapshots: Vec::new(),
+            holders: Vec::new(),
+            slaves: Vec::new(),
+            compression: None,
+            fs_health: None,
+            verity: None,
+            verity_backing: None,
+            image_fs: None,
+            windows: None,
+            quota: Vec::new(),
+            assumed_growth: None,
+            depth: 0,
+        }
+    }
+
+    /// The device's directory under `/sys/block`, for both partitions
+    /// (`sda/sda1`) and stacked devices (`dm-0`).
+    pub fn sysfs_dir(&self) -> String {
+        format!("/sys/block/{}", self.name)
+    }
+
+    pub fn dev_name(&self) -> &str {
+        self.name.split('/').next_back().unwrap_or(&self.name)
+    }
+
+    /// Name shown in partition rows; device-mapper partitions use their map name.
+    pub fn display_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+
+    pub fn mountpoint(&self) -> Option<&str> {
+        self.mountpoint.as_deref()
+    }
+
+    pub fn fstype(&self) -> Option<&str> {
+        self.fstype.as_deref()
+    }
+
+    pub fn size_bytes(&self) -> u64 {
+        self.size * 512
+    }
+
+    /// Why [`Partition::size_bytes`] is zero, `None` if the size was read.
+    pub fn size_error(&self) -> Option<&PrettyblkError> {
+        self.size_error.as_ref()
+    }
+
+    /// Why a mounted filesystem has no [`Partition::used_bytes`], e.g. a
+    /// refused or stalled statvfs.
+    pub fn usage_error(&self) -> Option<&PrettyblkError> {
+        self.usage_error.as_ref()
+    }
+
+    /// Free bytes of the mounted filesystem, including the root reserve.
+    pub fn free_bytes(&self) -> Option<u64> {
+        self.free
+    }
+
+    /// Used bytes of the filesystem or swap area on this partition.
+    pub fn used_bytes(&self) -> Option<u64> {
+        self.swap.as_ref().map(|s| s.used).or(self.used)
+    }
+
+    /// Mountpoint as shown to the user; active swap is rendered like lsblk does.
+    pub fn display_mountpoint(&self) -> Option<&str> {
+        match self.swap {
+            Some(_) => Some("[SWAP]"),
+            None => self.mountpoint.as_deref(),
+        }
+    }
+}
+
+pub struct ChartOptions {
+    pub width: usize,
+    pub swap_files: bool,
+    pub loops: bool,
+    pub inodes: bool,
+    pub units: Units,
+    pub thresholds: Thresholds,
+    pub verbose: bool,
+    pub summary: bool,
+    pub colors: theme::Depth,
+    pub theme: theme::Theme,
+    /// Draw a small picture of each drive beside its header
+    pub glyphs: bool,
+    /// Rows nested deeper than this many levels below the drive are hidden
+    pub max_depth: Option<usize>,
+    /// List stacked devices like partitions, without tree connectors
+    pub flat: bool,
+    /// Fields of each partition row, in order
+    pub columns: Vec<Column>,
+    /// Draw the partition bar of each drive and the inode bars
+    pub bars: bool,
+    /// Usage sparklines from the history, keyed by mountpoint
+    pub trends: HashMap<String, String>,
+    /// Days until full for growing filesystems, keyed by mountpoint
+    pub forecasts: HashMap<String, f64>,
+    /// Width of the terminal, `None` when not printing to one
+    pub terminal: Option<usize>,
+    /// Columns of the usage bar in partition rows
+    pub bar_width: usize,
+}
+
+impl ChartOptions {
+    /// The terminal is too narrow for partition rows to fit on one line.
+    pub fn narrow(&self) -> bool {
+        self.terminal.is_some_and(|columns| columns < NARROW)
+    }
+}
+
+impl Drive {
+    /// A drive of `size` 512-byte sectors.
+    pub fn new(_name: &str, size: u64, partitions: Vec<Partition>) -> Drive {
+        Drive {
+            name: _name.to_string(),
+            size: size.min(MAX_SECTORS),
+            size_error: None,
+            partitions,
+            table: None,
+            alias: None,
+            multipath: None,
+            read_only: false,
+            origin: None,
+            raid_controller: None,
+            removable: false,
+            usb: false,
+            model: None,
+            rotational: None,
+            no_media: false,
+            remote: None,
+            bay: None,
+            unused: false,
+            caching: Vec::new(),
+            #[cfg(feature = "smart")]
+            smart: None,
+            #[cfg(feature = "smart")]
+            smart_error: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn size_bytes(&self) -> u64 {
+        self.size * 512
+    }
+
+    /// Why [`Drive::size_bytes`] is zero, `None` if the size was read.
+    pub fn size_error(&self) -> Option<&PrettyblkError> {
+        self.size_error.as_ref()
+    }
+
+    /// Why the SMART query of the drive failed; a drive in standby gives no
+    /// verdict without an error.
+    #[cfg(feature = "smart")]
+    pub fn smart_error(&self) -> Option<&PrettyblkError> {
+        self.smart_error.as_ref()
+    }
+
+    /// Partitions followed by the devices stacked on them.
+    pub fn partitions(&self) -> &[Partition] {
+        &self.partitions
+    }
+
+    /// Palette slot of each entry in `partitions`; stacked devices share the
+    /// slot of the partition they are built on. Slots come from a hash of the
+    /// partition UUID (the device name without one), so a partition keeps its
+    /// color across runs and when other devices come and go. A partition that
+    /// would match its left neighbour takes the next color instead.
+    pub fn color_slots(&self) -> Vec<usize> {
+        let mut slot = None;
+        self.partitions
+            .iter()
+            .map(|p| {
+                if p.depth == 0 || slot.is_none() {
+                    let key = p.uuid.as_deref().unwrap_or(p.dev_name());
+                    let hashed = (fnv1a(key.as_bytes()) % PALETTE.len() as u64) as usize;
+                    slot = Some(match slot == Some(hashed) {
+                        true => (hashed + 1) % PALETTE.len(),
+                        false => hashed,
+                    });
+                }
+                slot.unwrap_or(0)
+            })
+            .collect()
+    }
+
+    /// Name shown in headers, e.g. `mpatha (dm-0)` for mapped devices.
+    pub fn display_name(&self) -> String {
+        match &self.alias {
+            Some(alias) => format!("{} ({})", text::isolate(alias), self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+fn drive_chart(drive: &Drive, options: &ChartOptions) -> String {
+    let mut out = String::new();
+    let glyphs = glyph::glyphs();
+    let width = options.width;
+
+    let table = match (options.verbose, drive.table) {
+        (true, Some(kind)) => format!(", {}", if kind == "gpt" { "GPT" } else { "MBR" }),
+        _ => String::new(),
+    };
+    let read_only = match drive.read_only {
+        true => format!(" {}", "read-only".bold().red()),
+        false => String::new(),
+    };
+    let removable = match (drive.usb, drive.removable) {
+        (true, true) => format!(" {}", "USB removable".bold().yellow()),
+        (true, false) => format!(" {}", "USB".bold().yellow()),
+        (false, true) => format!(" {}", "removable".bold().yellow()),
+        (false, false) => String::new(),
+    };
+    let unused = match drive.unused {
+        true => format!(" {}", lang::text(Label::Unused).bold().green()),
+        false => String::new(),
+    };
+    let remote = match &drive.remote {
+        Some(target) => format!(" {} {}", target.kind().bold().cyan(), target),
+        None => String::new(),
+    };
+    let bay = match &drive.bay {
+        Some(bay) => format!(" {}", options.theme.faint(&format!("in {}", bay))),
+        None => String::new(),
+    };
+    let size = match drive.no_media {
+        true => options.theme.faint(lang::text(Label::NoMedia)),
+        false => format!(
+            "{} {}{}",
+            lang::number(options.units.size.value(drive.size * 512), 2),
+            options.units.size,
+            table
+        ),
+    };
+    let header = format!(
+        "{} {} ({}){}{}{}{}{}",
+        lang::text(Label::Drive).bold().blue(),
+        drive.display_name().bold(),
+        size,
+        read_only,
+        removable,
+        unused,
+        remote,
+        bay
+    );
+    match options.glyphs {
+        true => {
+            let icon = glyph::icon(drive);
+            let [model, kind] = glyph::caption(drive);
+            let _ = writeln!(out, "\n{}  {}", icon[0].dimmed(), header);
+            let _ = writeln!(out, "{}  {}", icon[1].dimmed(), text::isolate(&model));
+            let _ = writeln!(out, "{}  {}", icon[2].dimmed(), kind.dimmed());
+        }
+        false => {
+            let _ = writeln!(out, "\n{}", header);
+        }
+    }
+    if let Some(controller) = drive.raid_controller {
+        let _ = writeln!(
+            out,
+            "  {} {} logical volume, the physical disks are hidden behind the controller",
+            options.theme.faint("hardware RAID"),
+            controller
+        );
+    }
+    if let Some(origin) = &drive.origin {
+        let state = match origin.state.as_deref() {
+            Some(state @ ("Optimal" | "Online" | "OK" | "healthy")) => format!(", {}", state),
+            Some(state) => format!(", {}", state.red()),
+            None => String::new(),
+        };
+        let _ = writeln!(
+            out,
+            "  {} {}{}",
+            options.theme.faint("collector"),
+            origin.collector,
+            state
+        );
+    }
+    if let Some(paths) = &drive.multipath {
+        let (label, preferred) = match paths.iter().any(|p| p.ana) {
+            true => ("nvme multipath", "optimized"),
+            false => ("multipath", "running"),
+        };
+        let list: Vec<String> = paths
+            .iter()
+            .map(|p| match p.state.as_str() {
+                state if state == preferred => p.name.clone(),
+                state if p.up() => format!("{} ({})", p.name, state.yellow()),
+                state => format!("{} ({})", p.name, state.red()),
+            })
+            .collect();
+        let _ = writeln!(
+            out,
+            "  {} {} paths, {} {}: {}",
+            options.theme.faint(label),
+            paths.len(),
+            paths.iter().filter(|p| p.state == preferred).count(),
+            preferred,
+            list.join(", ")
+        );
+    }
+    #[cfg(feature = "smart")]
+    if let Some(health) = &drive.smart {
+        let _ = writeln!(
+            out,
+            "  {} {}",
+            options.theme.faint("SMART"),
+            describe_smart(health, &options.units)
+        );
+    } else if let Some(PrettyblkError::Smart { source, .. }) = &drive.smart_error {
+        let _ = writeln!(out, "  {} unknown: {}", options.theme.faint("SMART"), source);
+    }
+    for tier in &drive.caching {
+        let _ = writeln!(
+            out,
+            "  {} {} caches {} for {}",
+            options.theme.faint(tier.kind),
+            tier.cache.as_deref().unwrap_or_default(),
+            tier.device,
+            tier.backing
+        );
+    }
+    let colors = options.theme.palette();
+
+    if options.bars && !drive.no_media {
+        let segments: Vec<String> = bar::geometry(drive, width)
+            .iter()
+            .map(|segment| match segment.palette {
+                Some(slot) => theme::paint(&segment.render(), colors[slot], options.colors),
+                None => segment.render(),
+            })
+            .collect();
+        let _ = writeln!(out, "[{}]", segments.concat());
+    }
+
+    let labels = row_labels(drive, options);
+    let name_width = labels.iter().flatten().map(|l| text::width(l)).max().unwrap_or(0);
+    let chart_width = options.bar_width;
+    let size_text_width = 18;
+    let narrow = options.narrow();
+    // Lines below a row start under its name, or under its bar when narrow
+    let indent = if narrow { 1 } else { name_width + 2 };
+
+    let slots = drive.color_slots();
+    let rows: Vec<(&Partition, Color, Vec<Cell>)> = drive
+        .partitions
+        .iter()
+        .zip(slots)
+        .zip(&labels)
+        .filter_map(|((partition, slot), label)| {
+            let label = label.as_ref()?;
+            let color = colors[slot % colors.len()];
+            let cells = options
+                .columns
+                .iter()
+                .map(|&column| row_cell(partition, column, label, color, options));
+            Some((partition, color, cells.collect()))
+        })
+        .collect();
+    let widths: Vec<usize> = options
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            let min = if narrow { 0 } else { column.min_width() };
+            rows.iter().map(|(_, _, cells)| cells[i].width).fold(min, usize::max)
+        })
+        .collect();
+    // Narrow terminals get the name and mountpoint on one line and the
+    // bar and numbers on the next
+    let (head, tail): (Vec<usize>, Vec<usize>) = (0..options.columns.len())
+        .partition(|&i| !narrow || matches!(options.columns[i], Column::Name | Column::Mountpoint));
+
+    for (partition, color, cells) in &rows {
+        let (partition, color) = (*partition, *color);
+        let units = &options.units;
+        let join = |indices: &[usize]| {
+            let fields: Vec<String> = indices
+                .iter()
+                .enumerate()
+                .map(|(n, &i)| {
+                    let (cell, column) = (&cells[i], options.columns[i]);
+                    let padding = " ".repeat(widths[i].saturating_sub(cell.width));
+                    match (column.right_aligned(), n == indices.len() - 1) {
+                        (true, _) => format!("{}{}", padding, cell.text),
+                        (false, true) => cell.text.clone(),
+                        (false, false) => format!("{}{}", cell.text, padding),
+                    }
+                })
+                .collect();
+            fields.join(" ")
+        };
+
+        let details = match (&partition.table_entry, options.verbose) {
+            (Some(entry), true) => {
+                let mut labels = vec![entry.type_name.clone()];
+                labels.extend(entry.flags.iter().map(|f| f.to_string()));
+                format!(" {}", options.theme.faint(&format!("[{}]", labels.join(", "))))
+            }
+            _ => String::new(),
+        };
+        let (mountpoint, fstype) = (partition.mountpoint.as_deref(), partition.fstype.as_deref());
+        let marker = partition
+            .used_bytes()
+            .map(|used| {
+                options
+                    .thresholds
+                    .classify(mountpoint, fstype, used, partition.size * 512)
+            })
+            .and_then(|severity| options.theme.marker(severity))
+            .map_or_else(String::new, |marker| format!(" {}", marker));
+        let forecast = mountpoint
+            .and_then(|m| options.forecasts.get(m))
+            .map_or_else(String::new, |&days| format!(" {}", describe_forecast(days, options)));
+
+        let _ = writeln!(
+            out,
+            "  {} {}{}{}{}{}",
+            theme::paint(glyphs.swatch, color, options.colors),
+            join(&head),
+            mount_flags(partition),
+            details,
+            marker,
+            forecast
+        );
+        if !tail.is_empty() {
+            let _ = writeln!(out, "    {}", join(&tail));
+        }
+
+        if options.inodes
+            && let Some(inodes) = &partition.inodes
+        {
+            let ratio = (inodes.used as f64 / inodes.total as f64).clamp(0.0, 1.0);
+            let filled = ((ratio * chart_width as f64).round() as usize).min(chart_width);
+            let bar = glyphs.inodes_used.repeat(filled) + &glyphs.inodes_free.repeat(chart_width - filled);
+            let count_str = format!("{} / {} inodes", format_count(inodes.used), format_count(inodes.total));
+            let _ = match options.bars {
+                true => writeln!(
+                    out,
+                    "  {:width$} {} {:>size_width$}",
+                    "",
+                    theme::paint(
+                        &bar,
+                        options
+                            .theme
+                            .severity(options.thresholds.classify_ratio(mountpoint, fstype, ratio), color),
+                        options.colors
+                    ),
+                    count_str,
+                    width = indent,
+                    size_width = size_text_width
+                ),
+                false => writeln!(out, "  {:width$} {}", "", count_str, width = indent),
+            };
+        }
+
+        if let Some(pool) = &partition.pool {
+            let _ = writeln!(
+                out,
+                "  {:width$} {}",
+                "",
+                options.theme.faint(
+                    &(format!(
+                        "btrfs {} data {}, unallocated {}, device {} of {}",
+                        pool.data_profile,
+                        units.used_of(pool.data_used, pool.data_total),
+                        units.size.format(pool.unallocated),
+                        pool.devid,
+                        pool.num_devices
+                    ))
+                ),
+                width = indent
+            );
+        }
+
+        if let Some(health) = &partition.fs_health {
+            let _ = writeln!(
+                out,
+                "  {:width$} {}",
+                "",
+                describe_health(health, options),
+                width = indent
+            );
+        }
+
+        if let Some(savings) = &partition.compression {
+            let _ = writeln!(
+                out,
+                "  {:width$} {}",
+                "",
+                options.theme.faint(&describe_savings(savings, units)),
+                width = indent
+            );
+        }
+
+        // The tree shows what is stacked on a device, except when it is
+        // flat or cut off by --depth, and only one of several components
+        let hidden = options.flat || options.max_depth.is_some_and(|max| partition.depth + 1 >= max);
+        if hidden && !partition.holders.is_empty() {
+            let text = format!("{} used by {}", glyphs.arrow, partition.holders.join(", "));
+            let _ = writeln!(out, "  {:width$} {}", "", options.theme.faint(&text), width = indent);
+        }
+        if partition.slaves.len() > 1 || (options.flat && !partition.slaves.is_empty()) {
+            let text = format!("built on {}", partition.slaves.join(", "));
+            let _ = writeln!(out, "  {:width$} {}", "", options.theme.faint(&text), width = indent);
+        }
+        if options.verbose && !partition.also_mounted.is_empty() {
+            let paths: Vec<_> = partition.also_mounted.iter().map(|path| text::isolate(path)).collect();
+            let text = format!("also mounted at {}", paths.join(", "));
+            let _ = writeln!(out, "  {:width$} {}", "", options.theme.faint(&text), width = indent);
+        }
+
+        if let Some(tier) = &partition.cache {
+            let _ = writeln!(
+                out,
+                "  {:width$} {}",
+                "",
+                options.theme.faint(&describe_cache(tier, units)),
+                width = indent
+            );
+        }
+
+        if let Some(verity) = &partition.verity {
+            let text = describe_verity(verity);
+            let text = match verity.corrupted {
+                Some(true) => theme::paint(&text, options.theme.severity(Severity::Critical, color), options.colors),
+                _ => options.theme.faint(&text),
+            };
+            let _ = writeln!(out, "  {:width$} {}", "", text, width = indent);
+        }
+
+        if let Some(snapshot) = &partition.snapshot {
+            let _ = writeln!(
+                out,
+                "  {:width$} {}",
+                "",
+                describe_snapshot(snapshot, options),
+                width = indent
+            );
+        }
+        if !partition.snapshots.is_empty() {
+            let _ = writeln!(
+                out,
+                "  {:width$} {}",
+                "",
+                options
+                    .theme
+                    .faint(&format!("origin of {}", partition.snapshots.join(", "))),
+                width = indent
+            );
+        }
+
+        let used_total = partition.used.unwrap_or(0).max(1);
+        for report in &partition.quota {
+            let consumers: Vec<String> = report
+                .consumers
+                .iter()
+                .map(|c| {
+                    let share = c.used as f64 / used_total as f64 * 100.0;
+                    format!(
+                        "{} {} ({:.0}%)",
+                        text::isolate(&c.name),
+                        units.used.format(c.used),
+                        share
+                    )
+                })
+                .collect();
+            let _ = writeln!(
+                out,
+                "  {:width$} {} {} {}",
+                "",
+                theme::paint(glyphs.detail, color, options.colors),
+                options.theme.faint(&format!("{:8}", report.kind)),
+                consumers.join(", "),
+                width = indent
+            );
+        }
+
+        if options.swap_files {
+            for file in &partition.swap_files {
+                let _ = writeln!(
+                    out,
+                    "  {:width$} {} swapfile {} {}",
+                    "",
+                    theme::paint(glyphs.detail, color, options.colors),
+                    file.path,
+                    units.used_of(file.used, file.size),
+                    width = indent
+                );
+            }
+        }
+
+        for device in &partition.loops {
+            let _ = writeln!(
+                out,
+                "  {:width$} {} {}",
+                "",
+                theme::paint(glyphs.detail, color, options.colors),
+                describe_loop(device, units),
+                width = indent
+            );
+        }
+    }
+
+    let (used, total) = mounted_usage(drive);
+    if options.summary && total > 0 {
+        let _ = writeln!(
+            out,
+            "  {} {} {}",
+            glyphs.sum.bold(),
+            options.units.used_of(used, total),
+            lang::text(Label::Mounted)
+        );
+    }
+    out
+}
+
+/// Prints the chart of every drive, side by side when the terminal is wide
+/// enough for more than one.
+fn print_drive_charts(drives: &[Drive], options: &ChartOptions) {
+    let charts: Vec<String> = drives.iter().map(|drive| drive_chart(drive, options)).collect();
+    let chart_width = charts
+        .iter()
+        .flat_map(|chart| chart.lines())
+        .map(text::visible_width)
+        .max()
+        .unwrap_or(0);
+    let per_row = match options.terminal {
+        Some(columns) => (columns + GUTTER) / (chart_width + GUTTER),
+        None => 1,
+    };
+    if per_row < 2 || charts.len() < 2 {
+        for chart in &charts {
+            print!("{}", chart);
+        }
+        return;
+    }
+
+    for row in charts.chunks(per_row) {
+        let lines: Vec<Vec<&str>> = row.iter().map(|chart| chart.lines().collect()).collect();
+        let height = lines.iter().map(Vec::len).max().unwrap_or(0);
+        for i in 0..height {
+            let mut line = String::new();
+            for (n, chart) in lines.iter().enumerate() {
+                let part = chart.get(i).copied().unwrap_or("");
+                line.push_str(part);
+                if n + 1 < lines.len() {
+                    let padding = chart_width - text::visible_width(part) + GUTTER;
+                    line.push_str(&" ".repeat(padding));
+                }
+            }
+            println!("{}", line.trim_end());
+        }
+    }
+}
+
+/// One field of a partition row. `text` may carry color escapes, so its
+/// terminal width is tracked separately for alignment.
+struct Cell {
+    text: String,
+    width: usize,
+}
+
+impl Cell {
+    fn plain(text: String) -> Cell {
+        Cell {
+            width: text::width(&text),
+            text,
+        }
+    }
+
+    /// `plain` as shown through `style`, which must not change its width.
+    fn styled(plain: &str, style: impl Fn(&str) -> String) -> Cell {
+        Cell {
+            width: text::width(plain),
+            text: style(plain),
+        }
+    }
+}
+
+/// Columns of the usage bar in partition rows.
+const CHART_WIDTH: usize = 20;
+
+/// Terminals narrower than this stack each partition row over two lines.
+const NARROW: usize = 60;
+
+/// Space between drives shown side by side.
+const GUTTER: usize = 4;
+
+fn row_cell(partition: &Partition, column: Column, label: &str, color: Color, options: &ChartOptions) -> Cell {
+    let units = &options.units;
+
+    // Secondary btrfs pool members show their device allocation; the
+    // filesystem usage is only attributed to the primary member
+    let secondary = partition.pool.as_ref().filter(|pool| !pool.primary);
+    let (used, total_bytes) = match secondary {
+        Some(pool) => (Some(pool.allocated), pool.device_size),
+        None => (partition.used_bytes(), partition.size * 512),
+    };
+    let (mountpoint, fstype) = (partition.mountpoint.as_deref(), partition.fstype.as_deref());
+    let dash = || Cell::plain("-".to_string());
+
+    match column {
+        Column::Name => Cell::plain(label.to_string()),
+        Column::Bar => {
+            if partition.zfs_pool.is_some() {
+                Cell::styled(&format!("{:width$}", "ZFS member", width = options.bar_width), |t| {
+                    options.theme.faint(t)
+                })
+            } else if let Some(u) = used {
+                let ratio = (u as f64 / total_bytes as f64).clamp(0.0, 1.0);
+                let filled = ((ratio * options.bar_width as f64).round() as usize).min(options.bar_width);
+                let severity = options.thresholds.classify(mountpoint, fstype, u, total_bytes);
+                let expected_full = options.thresholds.expected_full(mountpoint, fstype);
+                let text = match (options.colors, options.theme) {
+                    // Shades between the limits are hard to tell apart in high contrast
+                    (theme::Depth::TrueColor, theme::Theme::Default) if !expected_full => {
+                        let rule = options.thresholds.rule(mountpoint);
+                        let (warn, critical) = (rule.warn.unwrap_or(100.0), rule.critical.unwrap_or(100.0));
+                        theme::gradient_bar(filled, options.bar_width, warn, critical)
+                    }
+                    _ => {
+                        let glyphs = glyph::glyphs();
+                        let bar = glyphs.used.repeat(filled) + &glyphs.free.repeat(options.bar_width - filled);
+                        theme::paint(&bar, options.theme.severity(severity, color), options.colors)
+                    }
+                };
+                Cell {
+                    text,
+                    width: options.bar_width,
+                }
+            } else if partition.verity.as_ref().is_some_and(|v| v.corrupted == Some(true)) {
+                Cell::styled(
+                    &format!("{:width$}", "verity: corrupted", width = options.bar_width),
+                    |t| theme::paint(t, options.theme.severity(Severity::Critical, color), options.colors),
+                )
+            } else {
+                let label = match (&partition.verity, partition.verity_backing, &partition.image_fs) {
+                    (Some(_), _, Some(fs)) => format!("{}, verified", fs),
+                    (Some(_), _, None) => "verified".to_string(),
+                    (None, Some(backing), _) => backing.to_string(),
+                    (None, None, Some(fs)) => format!("{}, read-only", fs),
+                    (None, None, None) => match partition.windows {
+                        Some(windows) => windows.label().to_string(),
+                        None => lang::text(Label::Unmounted).to_string(),
+                    },
+                };
+                Cell::styled(&format!("{:width$}", label, width = options.bar_width), |t| {
+                    options.theme.faint(t)
+                })
+            }
+        }
+        Column::Trend => match mountpoint.and_then(|m| options.trends.get(m)) {
+            Some(spark) => Cell::styled(spark, |t| theme::paint(t, color, options.colors)),
+            None => Cell::plain(String::new()),
+        },
+        Column::Usage => match secondary {
+            Some(pool) => Cell::plain(format!("alloc {}", units.used_of(pool.allocated, pool.device_size))),
+            None => Cell::plain(units.used_of(used.unwrap_or(0), total_bytes)),
+        },
+        Column::Fstype => fstype.map_or_else(dash, |t| Cell::plain(text::isolate(t).into_owned())),
+        Column::Size => Cell::plain(units.size.format(total_bytes)),
+        Column::Used => used.map_or_else(dash, |u| Cell::plain(units.used.format(u))),
+        Column::Avail => match (used, secondary) {
+            (Some(u), None) => {
+                let free = partition.free.unwrap_or(total_bytes.saturating_sub(u));
+                Cell::plain(units.used.format(free))
+            }
+            (Some(u), Some(_)) => Cell::plain(units.used.format(total_bytes.saturating_sub(u))),
+            (None, _) => dash(),
+        },
+        Column::UsePercent => match used {
+            Some(u) => {
+                let percent = u as f64 / total_bytes.max(1) as f64 * 100.0;
+                let severity = options.thresholds.classify(mountpoint, fstype, u, total_bytes);
+                Cell::styled(&format!("{:.0}%", percent), |t| match severity {
+                    Severity::Ok => t.to_string(),
+                    _ => theme::paint(t, options.theme.severity(severity, color), options.colors),
+                })
+            }
+            None => dash(),
+        },
+        Column::Mountpoint => match (&partition.pool, &partition.zfs_pool) {
+            (Some(pool), _) if !pool.primary => {
+                Cell::plain(format!("(btrfs pool {})", text::isolate(&pool.mountpoint)))
+            }
+            (_, Some(zpool)) => Cell::plain(format!("(zfs pool {})", text::isolate(zpool))),
+            _ => match (partition.display_mountpoint(), &partition.intended) {
+                (Some(mountpoint), _) => Cell::plain(text::isolate(mountpoint).into_owned()),
+                (None, Some(intended)) => Cell::styled(&describe_intended(intended), |t| options.theme.faint(t)),
+                (None, None) => dash(),
+            },
+        },
+    }
+}
+
+/// The name column of each partition row, with tree connectors for stacked
+/// devices; `None` for rows below `--depth`.
+fn row_labels(drive: &Drive, options: &ChartOptions) -> Vec<Option<String>> {
+    let depths: Vec<usize> = drive.partitions.iter().map(|p| p.depth).collect();
+    let prefixes = tree::prefixes(&depths);
+    drive
+        .partitions
+        .iter()
+        .zip(prefixes)
+        .map(|(partition, prefix)| {
+            if options.max_depth.is_some_and(|max| partition.depth >= max) {
+                return None;
+            }
+            let name = text::isolate(partition.display_name());
+            Some(match options.flat {
+                true => name.into_owned(),
+                false => prefix + &name,
+            })
+        })
+        .collect()
+}
+
+/// Mount options worth seeing at a glance; `key=` entries match by prefix.
+const SHOWN_MOUNT_OPTIONS: [&str; 5] = ["ro", "noatime", "discard", "subvol=", "compress="];
+
+/// Relevant mount options of a partition, with read-only state in red. A
+/// filesystem remounted read-only after errors shows up here as `ro`.
+fn mount_flags(partition: &Partition) -> String {
+    let mut flags: Vec<String> = partition
+        .mount_options
+        .iter()
+        .filter(|option| {
+            SHOWN_MOUNT_OPTIONS.iter().any(|shown| match shown.ends_with('=') {
+                true => option.starts_with(shown),
+                false => option == shown,
+            })
+        })
+        .map(|option| match option.as_str() {
+            "ro" => option.bold().red().to_string(),
+            _ => option.dimmed().to_string(),
+        })
+        .collect();
+    if partition.read_only {
+        flags.push("device read-only".bold().red().to_string());
+    }
+    match flags.is_empty() {
+        true => String::new(),
+        false => format!(" {}", flags.join(&",".dimmed().to_string())),
+    }
+}
+
+/// E.g. "→ /backup (not mounted)" or "→ /srv via luks-srv (locked)".
+fn describe_intended(intended: &fstab::Intended) -> String {
+    let target = match (&intended.mountpoint, &intended.crypt) {
+        (Some(mountpoint), Some(crypt)) => format!("{} via {}", text::isolate(mountpoint), crypt),
+        (Some(mountpoint), None) => text::isolate(mountpoint).into_owned(),
+        (None, Some(crypt)) => crypt.clone(),
+        (None, None) => String::new(),
+    };
+    let state = match (intended.noauto, &intended.crypt) {
+        (true, _) => "noauto",
+        (false, Some(_)) => "locked",
+        (false, None) => "not mounted",
+    };
+    format!("{} {} ({})", glyph::glyphs().arrow, target, state)
+}
+
+/// e.g. `bcache writeback on sdb1, cache sdc: 87.5% hits (7 of 8), 1.2 MB dirty`
+fn describe_cache(tier: &cache::Tier, units: &Units) -> String {
+    let mut text = match &tier.mode {
+        Some(mode) => format!("{} {} on {}", tier.kind, mode, tier.backing),
+        None => format!("{} on {}", tier.kind, tier.backing),
+    };
+    match &tier.cache {
+        Some(cache) => text += &format!(", cache {}", cache),
+        None => return text + ", no cache device attached",
+    }
+    match (tier.hit_ratio(), tier.hits, tier.requests()) {
+        (Some(ratio), Some(hits), Some(requests)) => {
+            text += &format!(
+                ": {:.1}% hits ({} of {})",
+                ratio * 100.0,
+                format_count(hits),
+                format_count(requests)
+            );
+        }
+        (_, _, Some(_)) => text += ": no requests yet",
+        _ => text += ": hit counts unreadable",
+    }
+    if let Some(dirty) = tier.dirty.filter(|&dirty| dirty > 0) {
+        text += &format!(", {} dirty", units.used.format(dirty));
+    }
+    text
+}
+
+/// e.g. `zstd compression: 354.3 MB of data in 213.7 MB, 40% saved`
+fn describe_savings(savings: &compression::Savings, units: &Units) -> String {
+    format!(
+        "{} compression: {} of data in {}, {:.0}% saved",
+        savings.algorithms,
+        units.used.format(savings.logical),
+        units.used.format(savings.physical),
+        savings.saved() * 100.0
+    )
+}
+
+/// e.g. `ext4 not cleanly unmounted, 3 errors recorded: run fsck before
+/// mounting`, red with errors and yellow when only dirty.
+fn describe_health(health: &fshealth::Health, options: &ChartOptions) -> String {
+    let mut problems = Vec::new();
+    if health.dirty {
+        problems.push("not cleanly unmounted".to_string());
+    }
+    match health.errors {
+        0 => {}
+        1 => problems.push("errors recorded".to_string()),
+        n => problems.push(format!("{} errors recorded", n)),
+    }
+    let (severity, advice) = match (health.errors, health.dirty) {
+        (0, false) => return options.theme.faint(&format!("{} clean", health.fstype)),
+        (0, true) => (Severity::Warning, "check before mounting"),
+        _ => (Severity::Critical, "run fsck before mounting"),
+    };
+    let text = format!("{} {}: {}", health.fstype, problems.join(", "), advice);
+    theme::paint(&text, options.theme.severity(severity, Color::Red), options.colors)
+}
+
+/// e.g. `dm-verity on mmcblk0p3, hashes on mmcblk0p4: verified`
+fn describe_verity(verity: &verity::Verity) -> String {
+    let mut text = format!("dm-verity on {}", verity.data);
+    if let Some(hash) = verity.hash.as_ref().filter(|&hash| *hash != verity.data) {
+        text += &format!(", hashes on {}", hash);
+    }
+    match verity.corrupted {
+        Some(true) => text + ": corruption detected",
+        Some(false) => text + ": verified",
+        None => text,
+    }
+}
+
+/// e.g. `snapshot of vg0-root, copy-on-write 0.1 / 1.0 GB (12%)`, colored
+/// by the usage thresholds as the kernel drops a snapshot that fills up.
+fn describe_snapshot(snapshot: &cow::Snapshot, options: &ChartOptions) -> String {
+    let (used, total) = match &snapshot.state {
+        cow::State::Active { used, total } => (used * 512, total * 512),
+        cow::State::Invalid(reason) => {
+            let text = format!("snapshot of {}, invalid ({})", snapshot.origin, reason.to_lowercase());
+            return theme::paint(
+                &text,
+                options.theme.severity(Severity::Critical, Color::Red),
+                options.colors,
+            );
+        }
+    };
+    let ratio = snapshot.ratio().unwrap_or_default();
+    let text = format!(
+        "snapshot of {}, copy-on-write {} ({:.0}%)",
+        snapshot.origin,
+        options.units.used_of(used, total),
+        ratio * 100.0
+    );
+    // The snapshot's filesystem is a copy of the origin's, so its
+    // mountpoint rules do not apply to the copy-on-write space
+    match options.thresholds.classify_ratio(None, None, ratio) {
+        Severity::Ok => options.theme.faint(&text),
+        severity => theme::paint(&text, options.theme.severity(severity, Color::Red), options.colors),
+    }
+}
+
+fn describe_loop(device: &loops::Loop, units: &Units) -> String {
+    format!(
+        "{} {} {} {}",
+        device.name,
+        text::isolate(&device.backing_file),
+        units.size.format(device.size),
+        text::isolate(device.mountpoint.as_deref().unwrap_or("-"))
+    )
+}
+
+/// Used and total bytes of the mounted filesystems and active swap on a
+/// drive. Secondary btrfs members are skipped so pools count once.
+fn mounted_usage(drive: &Drive) -> (u64, u64) {
+    drive
+        .partitions
+        .iter()
+        .filter(|p| p.pool.as_ref().is_none_or(|pool| pool.primary))
+        .filter_map(|p| Some((p.used_bytes()?, p.size * 512)))
+        .fold((0, 0), |(used, total), (u, t)| {
+            (used.saturating_add(u), total.saturating_add(t))
+        })
+}
+
+/// Use% of the drive's fullest filesystem, `None` with nothing mounted.
+fn fullest_usage(drive: &Drive) -> Option<f64> {
+    drive
+        .partitions
+        .iter()
+        .filter_map(|p| Some(p.used_bytes()? as f64 / (p.size * 512).max(1) as f64 * 100.0))
+        .reduce(f64::max)
+}
+
+/// Orders `drives` by `sort`. Drives with nothing mounted go last when
+/// sorting by use, in either direction.
+fn sort_drives(drives: &mut [Drive], sort: Sort) {
+    drives.sort_by(|a, b| {
+        let order = match sort.key {
+            SortKey::Name => a.name.cmp(&b.name),
+            SortKey::Size => a.size.cmp(&b.size),
+            SortKey::Use => match (fullest_usage(a), fullest_usage(b)) {
+                (Some(a), Some(b)) => a.total_cmp(&b),
+                (a, b) => return b.is_some().cmp(&a.is_some()),
+            },
+        };
+        match sort.descending {
+            true => order.reverse(),
+            false => order,
+        }
+    });
+}
+
+/// Leaves out empty card readers unless --all is given and what the
+/// --removable-only and size and usage filters exclude, then applies
+/// --sort.
+fn select_drives(drives: &mut Vec<Drive>, args: &Args, config: &config::Config) {
+    if !args.all {
+        drives.retain(|d| !d.no_media);
+    }
+    if args.removable_only {
+        drives.retain(|d| d.removable || d.usb);
+        if drives.is_empty() {
+            warn!("pblk: no removable or USB drives found");
+        }
+    }
+    let before = drives.len();
+    drives.retain(|drive| within_limits(drive, args));
+    if drives.is_empty() && before > 0 {
+        warn!("pblk: no drives within the given size and usage limits");
+    }
+    if let Some(sort) = args.sort.or(config.sort) {
+        sort_drives(drives, sort);
+    }
+}
+
+/// Whether `drive` passes the --min/--max-size and --min/--max-usage
+/// filters. Usage is that of the drive's fullest filesystem; drives without
+/// one only pass when no usage bound is set.
+fn within_limits(drive: &Drive, args: &Args) -> bool {
+    let size = drive.size * 512;
+    if args.min_size.is_some_and(|min| size < min) || args.max_size.is_some_and(|max| size > max) {
+        return false;
+    }
+    if args.min_usage.is_none() && args.max_usage.is_none() {
+        return true;
+    }
+    let Some(percent) = fullest_usage(drive) else {
+        return false;
+    };
+    args.min_usage.is_none_or(|min| percent >= min) && args.max_usage.is_none_or(|max| percent <= max)
+}
+
+/// Usage that cannot be right, such as more bytes used than a filesystem
+/// holds. Charts clamp these at full; the messages say the numbers are off.
+/// Members of multi-device btrfs pools and ZFS are left out, their usage is
+/// that of the whole pool.
+fn implausible_usage(drives: &[Drive], units: &Units) -> Vec<String> {
+    let mut problems = Vec::new();
+    for partition in drives.iter().flat_map(|d| &d.partitions) {
+        if partition.pool.is_some() || partition.zfs_pool.is_some() {
+            continue;
+        }
+        let size = partition.size * 512;
+        if let Some(used) = partition.used_bytes().filter(|&used| used > size) {
+            problems.push(format!(
+                "{} reports {} used",
+                partition.dev_name(),
+                units.used_of(used, size)
+            ));
+        }
+        if let Some(inodes) = partition.inodes.as_ref().filter(|inodes| inodes.used > inodes.total) {
+            problems.push(format!(
+                "{} reports {} inodes used of {}",
+                partition.dev_name(),
+                format_count(inodes.used),
+                format_count(inodes.total)
+            ));
+        }
+    }
+    problems
+}
+
+fn print_summary(drives: &[Drive], options: &ChartOptions) {
+    let units = &options.units;
+    let raw = drives.iter().map(|d| d.size * 512).fold(0, u64::saturating_add);
+    let formatted = drives
+        .iter()
+        .flat_map(|d| &d.partitions)
+        .filter(|p| p.fstype.is_some() || p.zfs_pool.is_some())
+        .map(|p| p.size * 512)
+        .fold(0, u64::saturating_add);
+    let (used, mounted): (u64, u64) = drives.iter().map(mounted_usage).fold((0, 0), |(used, total), (u, t)| {
+        (used.saturating_add(u), total.saturating_add(t))
+    });
+
+    let spares: Vec<&Drive> = drives.iter().filter(|d| d.unused).collect();
+    let unused = match spares.is_empty() {
+        true => String::new(),
+        false => {
+            let names: Vec<String> = spares.iter().map(|d| d.display_name()).collect();
+            let bytes = spares.iter().map(|d| d.size * 512).sum();
+            format!(
+                ", {} {} ({})",
+                lang::text(Label::Unused),
+                units.size.format(bytes),
+                names.join(", ")
+            )
+        }
+    };
+
+    println!(
+        "\n{} {} {}, {} {}, {} {}, {} {}{}",
+        lang::text(Label::Total).bold().blue(),
+        lang::text(Label::Raw),
+        units.size.format(raw),
+        lang::text(Label::Formatted),
+        units.size.format(formatted),
+        lang::text(Label::Used),
+        units.used.format(used),
+        lang::text(Label::Free),
+        // A btrfs pool's usage can exceed the size of its primary device
+        units.used.format(mounted.saturating_sub(used)),
+        unused
+    );
+}
+
+fn format_count(count: u64) -> String {
+    match count {
+        0..1_000 => count.to_string(),
+        1_000..1_000_000 => format!("{:.1}k", count as f64 / 1e3),
+        1_000_000..1_000_000_000 => format!("{:.1}M", count as f64 / 1e6),
+        _ => format!("{:.1}G", count as f64 / 1e9),
+    }
+}
+
+fn terminal_columns() -> Option<usize> {
+    terminal_size()
+        .or_else(watch::terminal_size)
+        .map(|(Width(w), _)| w as usize)
+}
+
+fn get_terminal_width(max_width: usize) -> usize {
+    if let Some(w) = terminal_columns() {
+        w.saturating_sub(10).min(max_width)
+    } else {
+        80.min(max_width)
+    }
+}
+
+#[cfg(not(all(feature = "history", feature = "doctor", feature = "exporter")))]
+fn missing_feature(feature: &str) -> ! {
+    eprintln!("pblk: this build does not include the `{}` feature", feature);
+    std::process::exit(2);
+}
+
+#[cfg(feature = "smart")]
+fn annotate_smart(drives: &mut [Drive]) {
+    smart::annotate(drives);
+}
+
+#[cfg(not(feature = "smart"))]
+fn annotate_smart(_drives: &mut [Drive]) {
+    missing_feature("smart")
+}
+
+/// Discovers drives and fills in usage, btrfs pools and swap.
+fn scan_drives(config: &config::Config) -> Vec<Drive> {
+    let sources = source::Sources {
+        timeout: config.scan_timeout,
+        ..source::Sources::system()
+    };
+    let mut drives = sources.scan();
+    #[cfg(all(feature = "btrfs", unix))]
+    btrfs::annotate(&mut drives);
+    swap::annotate(&mut drives);
+    fstab::annotate(&mut drives, &config.root);
+    cache::annotate(&mut drives);
+    cow::annotate(&mut drives);
+    verity::annotate(&mut drives);
+    // External commands are the slowest probes, and easy to do without on battery
+    if !(config.battery.skip_collectors && power::on_battery()) {
+        drives.extend(collectors::collect(&config.collectors));
+    }
+    drives
+}
+
+/// Set from --quiet before anything else runs.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Entry point of the `pblk` binary.
+#[doc(hidden)]
+pub fn run() {
+    let mut args = Args::parse();
+    QUIET.store(args.quiet || args.silent, Ordering::Relaxed);
+    let locale = lang::Locale::from_env();
+    lang::set(lang::Locale {
+        lang: args.lang.unwrap_or(locale.lang),
+        ascii: args.ascii || locale.ascii,
+    });
+    let mut config = config::load().unwrap_or_else(|e| {
+        eprintln!("pblk: {}", e);
+        std::process::exit(2);
+    });
+    config.thresholds.default.warn = args.warn.or(config.thresholds.default.warn);
+    config.thresholds.default.critical = args.critical.or(config.thresholds.default.critical);
+    if let Some(root) = &args.chroot {
+        if !root.join("etc/fstab").is_file() {
+            warn!(
+                "pblk: {} has no etc/fstab to check the partitions against",
+                root.display()
+            );
+        }
+        config.root = root.clone();
+    }
+
+    match &args.command {
+        #[cfg(feature = "history")]
+        Command::History { target, days } => {
+            let history_file = config.history_path();
+            let days = days.unwrap_or(config.history_days);
+            let width = get_terminal_width(config.max_width);
+            if let Err(e) = history::print_history(&history_file, target, days, width, &config.units, &SystemClock) {
+                eprintln!("pblk: cannot read {}: {}", history_file.display(), e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        #[cfg(not(feature = "history"))]
+        Command::History { .. } => missing_feature("history"),
+        Command::Config(action) => {
+            config::run(action, &config, args.force);
+            return;
+        }
+        Command::Compare { first, second } => {
+            if !compare::run(first, second, &config.units) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        #[cfg(feature = "doctor")]
+        Command::Doctor => {
+            if !doctor::run(&config, &config.history_path()) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        #[cfg(not(feature = "doctor"))]
+        Command::Doctor => missing_feature("doctor"),
+        Command::Plan { add, to } => {
+            let drives = scan_drives(&config);
+            if !plan::run(&drives, *add, to, &config.units, &config.thresholds) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Command::Inventory { hosts } => {
+            let format = args.format.unwrap_or(config.format);
+            if !inventory::run(&scan_drives(&config), hosts, format, &config.units) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Command::Snapshot => {
+            let identity = (!args.no_identity).then(|| identity::Identity::collect(&SystemClock));
+            print!(
+                "{}",
+                snapshot::write(&scan_drives(&config), identity.as_ref(), &SystemClock)
+            );
+            return;
+        }
+        Command::Report { email } => {
+            let mut drives = scan_drives(&config);
+            if args.fs_health {
+                fshealth::annotate(&mut drives);
+            }
+            if args.smart {
+                annotate_smart(&mut drives);
+            }
+            growth::annotate(&mut drives, &args.assume_growth);
+            let report = report::render(
+                &drives,
+                &config.thresholds,
+                &config.acknowledged,
+                &config.units,
+                *email,
+                (!args.no_identity)
+                    .then(|| identity::Identity::collect(&SystemClock))
+                    .as_ref(),
+                &SystemClock,
+            );
+            print!("{}", report);
+            return;
+        }
+        Command::Locate { device, on } => {
+            let name = device.strip_prefix("/dev/").unwrap_or(device);
+            let Some(bay) = bay::find(name) else {
+                eprintln!("pblk: no enclosure or PCIe slot is known for {}", name);
+                std::process::exit(1);
+            };
+            if let Err(e) = bay::locate(&bay, *on) {
+                eprintln!("pblk: cannot switch the locate LED of {}: {}", name, e);
+                std::process::exit(1);
+            }
+            println!("{}: locate LED of {} {}", name, bay, if *on { "on" } else { "off" });
+            return;
+        }
+        Command::Bench { device } => {
+            let path = match device.starts_with(['/', '\\']) {
+                true => device.clone(),
+                false if cfg!(windows) => format!(r"\\.\{}", device),
+                false => format!("/dev/{}", device),
+            };
+            let name = device.rsplit(['/', '\\']).next().unwrap_or(device);
+            match bench::run(&path) {
+                Ok(speed) => println!(
+                    "{}: sequential read {}, random 4 KiB read {} IOPS ({})",
+                    name.bold(),
+                    config.units.rate.format_rate(speed.sequential),
+                    format_count(speed.iops as u64),
+                    config.units.rate.format_rate(speed.random())
+                ),
+                Err(e) => {
+                    eprintln!("pblk: cannot benchmark {}: {}", path, e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Command::Pct { target } => std::process::exit(pct::run(target, &config.thresholds, args.quiet)),
+        Command::Image { path } => {
+            match image::drive(path) {
+                Ok(drive) => print_drive_charts(&[drive], &chart_options(&args, &config)),
+                Err(e) => {
+                    eprintln!("pblk: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Command::Diff { path } => {
+            if let Err(e) = snapshot::diff(path, &scan_drives(&config), &config.units, &SystemClock) {
+                eprintln!("pblk: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Command::Show => {}
+    }
+
+    if args.exporter {
+        #[cfg(feature = "exporter")]
+        {
+            let address = args.listen.as_deref().unwrap_or("127.0.0.1:9633");
+            let pidfile = args.pidfile.clone().unwrap_or_else(pidfile::default_path);
+            let _guard =
+                pidfile::acquire(&pidfile, &format!("exporter on {}", address), args.force).unwrap_or_else(|e| {
+                    eprintln!("pblk: {}", e);
+                    std::process::exit(1);
+                });
+            let collect = || exporter::render(&scan_drives(&config), &config.thresholds);
+            if let Err(e) = exporter::serve(address, collect) {
+                eprintln!("pblk: cannot listen on {}: {}", address, e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        #[cfg(not(feature = "exporter"))]
+        missing_feature("exporter");
+    }
+
+    if args.tuning {
+        tuning::print(&scan_drives(&config));
+        return;
+    }
+    if args.layout {
+        layout::print(&scan_drives(&config));
+        return;
+    }
+
+    if args.silent {
+        let mut drives = scan_drives(&config);
+        if args.fs_health {
+            fshealth::annotate(&mut drives);
+        }
+        if args.smart {
+            annotate_smart(&mut drives);
+        }
+        select_drives(&mut drives, &args, &config);
+        let problems = audit::detect(&drives, &config.thresholds);
+        std::process::exit(audit::exit_status(&problems, &config.acknowledged));
+    }
+
+    if args.pick {
+        let mut drives = scan_drives(&config);
+        select_drives(&mut drives, &args, &config);
+        match pick::run(&drives, &config.units) {
+            Ok(Some(path)) => println!("{}", path),
+            Ok(None) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("pblk: cannot show the list to pick from: {}", e);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+
+    let mut alerts = alert::Alerts::new(args.on_threshold.clone(), args.notify);
+    match args.watch {
+        Some(interval) => {
+            let mut message = None;
+            let mut locating = Locating::default();
+            let mut drives = Vec::new();
+            watch::run(interval, config.battery.slowdown, |screen, keys| {
+                for &key in keys {
+                    message = match key {
+                        b'b' | b'l' => locating.press(key, &drives),
+                        _ => press(key, &mut args, &config),
+                    };
+                }
+                drives = screen.frame(|| {
+                    let drives = show(&args, &config);
+                    if watch::interactive() {
+                        let message = message.clone().or_else(|| locating.describe(&drives));
+                        println!("\n{}", watch_status(&args, &config, message.as_deref()));
+                    }
+                    drives
+                });
+                alerts.check(&drives, &config.thresholds);
+            })
+        }
+        None => {
+            let drives = pager::page(args.paging.unwrap_or(config.paging), || show(&args, &config));
+            alerts.check(&drives, &config.thresholds);
+            alerts.wait();
+        }
+    }
+}
+
+/// `passed, 41 °C, 8123 hours`, the verdict in red when the drive expects
+/// to fail.
+#[cfg(feature = "smart")]
+fn describe_smart(health: &smart::Health, units: &Units) -> String {
+    let mut text = match health.passed {
+        true => "passed".to_string(),
+        false => "FAILING".red().bold().to_string(),
+    };
+    if let Some(celsius) = health.temperature {
+        let _ = write!(text, ", {}", units.temperature.format(celsius));
+    }
+    if let Some(hours) = health.power_on_hours {
+        let _ = write!(text, ", {} hours", hours);
+    }
+    text
+}
+
+/// Filesystems forecast to fill up within this many days are shown in red.
+const IMMINENT_DAYS: f64 = 7.0;
+
+/// `full in ~N days` for a filesystem with `days` left at its current growth.
+fn describe_forecast(days: f64, options: &ChartOptions) -> String {
+    let text = match days.round() as u64 {
+        0 => "full in <1 day".to_string(),
+        1 => "full in ~1 day".to_string(),
+        n => format!("full in ~{} days", n),
+    };
+    match days < IMMINENT_DAYS {
+        true => theme::paint(&text, Color::Red, options.colors),
+        false => options.theme.faint(&text),
+    }
+}
+
+/// Sparklines of the last `days` days of recorded usage, keyed by mountpoint.
+#[cfg(feature = "history")]
+fn load_trends(config: &config::Config, days: u64) -> HashMap<String, String> {
+    use clock::Clock;
+
+    let path = config.history_path();
+    match history::load(&path) {
+        Ok(samples) => {
+            let until = SystemClock.now();
+            let since = until.saturating_sub(days.saturating_mul(86_400));
+            history::sparklines(&samples, since, until, columns::TREND_WIDTH)
+        }
+        Err(e) => {
+            warn!("pblk: cannot read {}: {}", path.display(), e);
+            HashMap::new()
+        }
+    }
+}
+
+#[cfg(not(feature = "history"))]
+fn load_trends(_config: &config::Config, _days: u64) -> HashMap<String, String> {
+    missing_feature("history")
+}
+
+/// When each filesystem fills up at its growth over the last `days` days.
+#[cfg(feature = "history")]
+fn load_forecasts(config: &config::Config, days: u64) -> HashMap<String, f64> {
+    use clock::Clock;
+
+    let path = config.history_path();
+    match history::load(&path) {
+        Ok(samples) => {
+            let until = SystemClock.now();
+            history::forecasts(&samples, until.saturating_sub(days.saturating_mul(86_400)), until)
+        }
+        Err(e) => {
+            warn!("pblk: cannot read {}: {}", path.display(), e);
+            HashMap::new()
+        }
+    }
+}
+
+#[cfg(not(feature = "history"))]
+fn load_forecasts(_config: &config::Config, _days: u64) -> HashMap<String, f64> {
+    missing_feature("history")
+}
+
+/// Partition row columns from --output, the config or the default layout.
+fn current_columns(args: &Args, config: &config::Config) -> Vec<Column> {
+    args.output
+        .clone()
+        .or_else(|| config.columns.clone())
+        .unwrap_or_else(|| columns::DEFAULT.to_vec())
+}
+
+/// Changes the view for a key pressed in --watch: `s` and `r` for the sort
+/// key and direction, `1`-`9` and `0` to toggle a column, `w` to save both
+/// to the config. Returns what happened, for the status line.
+fn press(key: u8, args: &mut Args, config: &config::Config) -> Option<String> {
+    let sort = args.sort.or(config.sort);
+    match key {
+        b's' => {
+            let next = match sort {
+                Some(sort) => Sort {
+                    key: sort.key.next(),
+                    ..sort
+                },
+                None => Sort {
+                    key: SortKey::Name,
+                    descending: false,
+                },
+            };
+            args.sort = Some(next);
+        }
+        b'r' => {
+            let sort = sort.unwrap_or(Sort {
+                key: SortKey::Name,
+                descending: false,
+            });
+            args.sort = Some(Sort {
+                descending: !sort.descending,
+                ..sort
+            });
+        }
+        b'0'..=b'9' => {
+            // 1 is the first column, 0 the tenth
+            let index = (key - b'0') as usize;
+            let column = columns::ALL[(index + 9) % 10];
+            let mut columns = current_columns(args, config);
+            match columns.iter().position(|&c| c == column) {
+                Some(at) if columns.len() > 1 => {
+                    columns.remove(at);
+                }
+                Some(_) => return Some("the last column cannot be hidden".to_string()),
+                None => {
+                    // Back where --output lists it, relative to the columns shown
+                    let rank = |c: &Column| columns::ALL.iter().position(|a| a == c);
+                    let at = columns
+                        .iter()
+                        .position(|c| rank(c) > rank(&column))
+                        .unwrap_or(columns.len());
+                    columns.insert(at, column);
+                }
+            }
+            args.output = Some(columns);
+        }
+        b'w' => {
+            let mut settings = vec![(
+                "columns",
+                config::Value::Str(columns::join(&current_columns(args, config))),
+            )];
+            if let Some(sort) = sort {
+                settings.push(("sort", config::Value::Str(sort.name())));
+            }
+            return Some(match config::save_display(&settings) {
+                Ok(path) => format!("saved to {}", path.display()),
+                Err(e) => format!("cannot save the view: {}", e),
+            });
+        }
+        _ => {}
+    }
+    None
+}
+
+/// The drive whose locate LED the `b` and `l` keys of --watch switch.
+#[derive(Default)]
+struct Locating {
+    /// Picked with `b` among the drives that sit in a known bay
+    selected: Option<String>,
+    /// Drives whose LED was switched on from the view
+    lit: Vec<String>,
+}
+
+impl Locating {
+    /// `b` selects the next drive in a bay, `l` switches the LED of the
+    /// selected one, or of the first if none is. Returns an error for the
+    /// status line.
+    fn press(&mut self, key: u8, drives: &[Drive]) -> Option<String> {
+        let in_bays: Vec<&Drive> = drives.iter().filter(|d| d.bay.is_some()).collect();
+        if in_bays.is_empty() {
+            return Some("no drive sits in a known bay".to_string());
+        }
+        let at = self
+            .selected
+            .as_ref()
+            .and_then(|name| in_bays.iter().position(|d| d.name == *name));
+        let drive = match key {
+            b'b' => in_bays[at.map_or(0, |at| (at + 1) % in_bays.len())],
+            _ => in_bays[at.unwrap_or(0)],
+        };
+        self.selected = Some(drive.name.clone());
+        if key != b'l' {
+            return None;
+        }
+        let on = !self.lit.contains(&drive.name);
+        let bay = drive.bay.as_ref()?;
+        if let Err(e) = bay::locate(bay, on) {
+            return Some(format!("cannot switch the locate LED of {}: {}", drive.name, e));
+        }
+        match on {
+            true => self.lit.push(drive.name.clone()),
+            false => self.lit.retain(|name| *name != drive.name),
+        }
+        None
+    }
+
+    /// `sdc in Slot 04 of enclosure 6:0:8:0, LED on` for the selected drive.
+    fn describe(&self, drives: &[Drive]) -> Option<String> {
+        let drive = drives.iter().find(|d| Some(&d.name) == self.selected.as_ref())?;
+        let led = match self.lit.contains(&drive.name) {
+            true => "LED on".yellow().bold().to_string(),
+            false => "LED off".to_string(),
+        };
+        Some(format!("{} in {}, {}", drive.name, drive.bay.as_ref()?, led))
+    }
+}
+
+/// The last line of a --watch frame: the sort order and the keys.
+fn watch_status(args: &Args, config: &config::Config, message: Option<&str>) -> String {
+    let sort = args
+        .sort
+        .or(config.sort)
+        .map_or_else(|| "unsorted".to_string(), |sort| format!("sort {}", sort.name()));
+    let keys = "s sort, r reverse, 1-0 columns, w save, b bay, l locate, q quit";
+    match message {
+        Some(message) => format!("{}  {}  {}", sort, message, keys.dimmed()),
+        None => format!("{}  {}", sort, keys.dimmed()),
+    }
+}
+
+/// How the charts are drawn, from the command line and the config.
+fn chart_options(args: &Args, config: &config::Config) -> ChartOptions {
+    let mut columns = current_columns(args, config);
+    if args.no_bars {
+        columns.retain(|&c| c != Column::Bar);
+    }
+    if args.history && !columns.contains(&Column::Trend) {
+        let at = columns.iter().position(|&c| c == Column::Bar).map_or(1, |bar| bar + 1);
+        columns.insert(at.min(columns.len()), Column::Trend);
+    }
+    let trends = match columns.contains(&Column::Trend) {
+        true => load_trends(config, args.days.unwrap_or(config.history_days)),
+        false => HashMap::new(),
+    };
+    let forecasts = match args.forecast {
+        true => load_forecasts(config, args.days.unwrap_or(config.history_days)),
+        false => HashMap::new(),
+    };
+
+    ChartOptions {
+        width: get_terminal_width(config.max_width),
+        swap_files: args.swap_files,
+        loops: args.loops,
+        inodes: args.inodes,
+        units: config.units,
+        thresholds: config.thresholds.clone(),
+        verbose: args.verbose,
+        summary: args.summary,
+        colors: config.color_depth.unwrap_or_else(theme::Depth::detect),
+        theme: args.theme.unwrap_or(config.theme),
+        glyphs: args.glyphs || config.glyphs,
+        max_depth: args.depth,
+        flat: args.flat,
+        columns,
+        bars: !args.no_bars,
+        trends,
+        forecasts,
+        terminal: terminal_columns(),
+        bar_width: match terminal_columns() {
+            // Leaves room for the indent and a short size after the bar
+            Some(columns) if columns < NARROW => columns.saturating_sub(21).clamp(5, CHART_WIDTH),
+            _ => CHART_WIDTH,
+        },
+    }
+}
+
+/// Scans the drives and prints them as requested, once.
+fn show(args: &Args, config: &config::Config) -> Vec<Drive> {
+    let mut drives = scan_drives(config);
+    let other_loops = match args.loops {
+        true => loops::annotate(&mut drives, &platform::native().mounts().unwrap_or_default()),
+        false => Vec::new(),
+    };
+    if args.verbose {
+        ptable::annotate(&mut drives);
+        compression::annotate(&mut drives);
+    }
+    if args.quota {
+        quota::annotate(&mut drives);
+    }
+    if args.fs_health {
+        fshealth::annotate(&mut drives);
+    }
+    if args.smart {
+        annotate_smart(&mut drives);
+    }
+    growth::annotate(&mut drives, &args.assume_growth);
+    #[cfg(feature = "zfs")]
+    let mut pools = zfs::read_pools();
+    #[cfg(feature = "zfs")]
+    if args.verbose {
+        for pool in &mut pools {
+            pool.compression = compression::zfs(&pool.name);
+        }
+    }
+    #[cfg(feature = "zfs")]
+    zfs::annotate(&mut drives, &pools);
+
+    if args.record {
+        #[cfg(feature = "history")]
+        {
+            let path = config.history_path();
+            if let Err(e) = history::record(&path, &drives, &config.history_retention, &SystemClock) {
+                warn!("pblk: cannot record history to {}: {}", path.display(), e);
+            }
+        }
+        #[cfg(not(feature = "history"))]
+        missing_feature("history");
+    }
+
+    select_drives(&mut drives, args, config);
+
+    let mut options = chart_options(args, config);
+    options.forecasts.extend(growth::forecasts(&drives));
+    for problem in implausible_usage(&drives, &options.units) {
+        warn!("pblk: {}, the numbers shown for it are clamped", problem);
+    }
+
+    let identity = (!args.no_identity).then(|| identity::Identity::collect(&SystemClock));
+    match (args.export, args.format.unwrap_or(config.format)) {
+        _ if args.problems => audit::print(&audit::detect(&drives, &options.thresholds), &config.acknowledged),
+        (Some(Export::Svg), _) => print!("{}", export::svg(&drives, &options)),
+        (Some(Export::Html), _) => print!("{}", export::html(&drives, &options)),
+        (None, Format::Csv) => format::print_delimited(&drives, ',', identity.as_ref()),
+        (None, Format::Tsv) => format::print_delimited(&drives, '\t', identity.as_ref()),
+        (None, Format::Env) => format::print_assignments(&drives, false, identity.as_ref()),
+        (None, Format::Kv) => format::print_assignments(&drives, true, identity.as_ref()),
+        (None, Format::Pretty) => {
+            print_drive_charts(&drives, &options);
+            #[cfg(feature = "zfs")]
+            zfs::print_pools(&pools, &options);
+            if !other_loops.is_empty() {
+                println!("\n{}", "Loop devices:".bold().blue());
+                for device in &other_loops {
+                    println!("  {}", describe_loop(device, &options.units));
+                }
+            }
+            if args.memory_backed {
+                memory::print(&options.units);
+            }
+            if options.summary {
+                print_summary(&drives, &options);
+            }
+        }
+    }
+
+    if let Some(path) = args.audit.clone().or_else(|| config.audit_file.clone()) {
+        let problems = audit::detect(&drives, &options.thresholds);
+        if let Err(e) = audit::append(&path, &problems, &config.acknowledged, &SystemClock) {
+            warn!("pblk: cannot write audit log {}: {}", path.display(), e);
+        }
+    }
+
+    // Goes to stderr so it is seen even when the output is piped or exported
+    let mib = units::SizeUnit::parse("MiB").unwrap();
+    for (mountpoint, free) in severity::system_space_warnings(&drives) {
+        warn!(
+            "{} only {} free on {}; package upgrades and journald fail below {}",
+            "WARNING:".bold().red(),
+            mib.format(free),
+            mountpoint.bold(),
+            mib.format(severity::SYSTEM_RESERVE)
+        );
+    }
+    privilege::hint();
+    drives
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arbitrary::Rng;
+
+    #[test]
+    fn charts_render_for_any_drive_and_width() {
+        let columns = columns::parse_list("name,bar,trend,usage,fstype,size,used,avail,use%,mountpoint").unwrap();
+        for case in 0..arbitrary::CASES {
+            let mut rng = Rng::new(case);
+            let drive = arbitrary::drive(&mut rng);
+            let picked = columns.iter().copied().filter(|_| rng.chance(2)).collect();
+            let options = ChartOptions {
+                width: rng.below(200) as usize,
+                swap_files: true,
+                loops: true,
+                inodes: rng.chance(2),
+                units: Units::default(),
+                thresholds: Thresholds::default(),
+                verbose: rng.chance(2),
+                summary: rng.chance(2),
+                colors: rng.pick(&[theme::Depth::Basic, theme::Depth::Ansi256, theme::Depth::TrueColor]),
+                theme: rng.pick(&[theme::Theme::Default, theme::Theme::HighContrast]),
+                glyphs: rng.chance(2),
+                max_depth: rng.chance(3).then(|| rng.below(3) as usize),
+                flat: rng.chance(2),
+                columns: picked,
+                bars: rng.chance(4),
+                trends: HashMap::new(),
+                forecasts: HashMap::from([("/home".to_string(), rng.below(400) as f64 / 3.0)]),
+                terminal: rng.chance(2).then(|| rng.below(200) as usize),
+                bar_width: rng.below(100) as usize,
+            };
+            drive_chart(&drive, &options);
+        }
+    }
+
+    #[test]
+    fn usage_beyond_the_size_is_reported() {
+        let mount = Mount::new("/", "ext4", &[]);
+        let mut full = Partition::new("sda/sda1".to_string(), 8, Some(&mount));
+        full.used = Some(8 * 512);
+        let mut over = Partition::new("sda/sda2".to_string(), 8, Some(&mount));
+        over.used = Some(8 * 512 + 1);
+        over.inodes = Some(Inodes { used: 11, total: 10 });
+        let drive = Drive::new("sda", 16, vec![full, over]);
+        let problems = implausible_usage(&[drive], &Units::default());
+        assert_eq!(problems.len(), 2);
+        assert!(
+            problems.iter().all(|problem| problem.starts_with("sda2 ")),
+            "{:?}",
+            problems
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use std::{
+    collections::HashMap,
+    fs::{read_dir, read_to_string},
+};
+
+use crate::{Drive, Mount, platform::linux::read_size, swap};
+
+/// A loop device and the file it exposes as a block device.
+pub struct Loop {
+    pub name: String,
+    pub backing_file: String,
+    pub size: u64,
+    pub mountpoint: Option<String>,
+}
+
+fn read_loops(mountpoints: &HashMap<String, Mount>) -> Vec<Loop> {
+    let mut loops: Vec<Loop> = read_dir("/sys/block")
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().to_str().map(String::from))
+        .filter(|name| name.starts_with("loop"))
+        .filter_map(|name| {
+            // Detached loop devices have no backing_file attribute
+            let backing_file = read_to_string(format!("/sys/block/{}/loop/backing_file", name)).ok()?;
+            Some(Loop {
+                backing_file: backing_file.trim_end().to_string(),
+                size: read_size(&name).unwrap_or(0) * 512,
+                mountpoint: mountpoints.get(&format!("/dev/{}", name)).map(|m| m.path.clone()),
+                name,
+            })
+        })
+        .collect();
+    loops.sort_by(|a, b| a.name.len().cmp(&b.name.len()).then(a.name.cmp(&b.name)));
+    loops
+}
+
+/// Attaches each attached loop device to the partition holding its backing
+/// file. Loop devices whose file lives elsewhere (tmpfs, an unlisted disk)
+/// are returned.
+pub fn annotate(drives: &mut [Drive], mountpoints: &HashMap<String, Mount>) -> Vec<Loop> {
+    let mut unplaced = Vec::new();
+    for device in read_loops(mountpoints) {
+        // The kernel appends " (deleted)" once the file is unlinked
+        let Some(dev) = swap::file_device(&device.backing_file) else {
+            unplaced.push(device);
+            continue;
+        };
+        let holder = drives
+            .iter_mut()
+            .flat_map(|d| d.partitions.iter_mut())
+            .find(|p| swap::device_number(&p.sysfs_dir()) == Some(dev));
+        match holder {
+            Some(partition) => partition.loops.push(device),
+            None => unplaced.push(device),
+        }
+    }
+    unplaced
+}
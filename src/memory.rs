@@ -0,0 +1,163 @@
+use std::{collections::HashSet, fs::read_to_string};
+
+use colored::*;
+
+use crate::{
+    platform::linux,
+    source::{StatvfsProvider, SystemStatvfs},
+    swap,
+    units::Units,
+};
+
+/// tmpfs mounts smaller than this (e.g. `/run/lock`) are not worth listing.
+const MIN_TMPFS_SIZE: u64 = 64 * 1024 * 1024;
+
+/// A configured zram device; sizes in bytes.
+pub struct Zram {
+    pub name: String,
+    pub disk_size: u64,
+    /// What is stored, `None` when `mm_stat` could not be read
+    pub usage: Option<ZramUsage>,
+    pub algorithm: String,
+    pub swap: bool,
+}
+
+pub struct ZramUsage {
+    /// Uncompressed size of the data stored
+    pub original: u64,
+    /// What that data takes after compression
+    pub compressed: u64,
+    /// RAM actually used, including allocator overhead
+    pub memory: u64,
+}
+
+/// The first three fields of `mm_stat`: orig_data_size compr_data_size
+/// mem_used_total.
+fn parse_mm_stat(stat: &str) -> Option<ZramUsage> {
+    let mut fields = stat.split_whitespace().map(|f| f.parse::<u64>().ok());
+    Some(ZramUsage {
+        original: fields.next()??,
+        compressed: fields.next()??,
+        memory: fields.next()??,
+    })
+}
+
+/// A tmpfs or ramfs mount; sizes in bytes.
+pub struct Tmpfs {
+    pub mountpoint: String,
+    pub size: u64,
+    pub used: u64,
+}
+
+/// zram devices that have a disk size set, from `/sys/block/zram*`.
+pub fn zram_devices() -> Vec<Zram> {
+    let swaps = read_to_string("/proc/swaps").unwrap_or_default();
+    linux::sysfs_links("/sys/block")
+        .into_iter()
+        .filter(|name| name.starts_with("zram"))
+        .filter_map(|name| {
+            let read = |file: &str| read_to_string(format!("/sys/block/{}/{}", name, file)).ok();
+            let disk_size: u64 = read("disksize")?.trim().parse().ok()?;
+            if disk_size == 0 {
+                return None;
+            }
+            let usage = read("mm_stat").as_deref().and_then(parse_mm_stat);
+            // `lzo [lz4] zstd` marks the active algorithm
+            let algorithm = read("comp_algorithm")
+                .unwrap_or_default()
+                .split_whitespace()
+                .find_map(|a| a.strip_prefix('[').and_then(|a| a.strip_suffix(']')).map(String::from))
+                .unwrap_or_default();
+            let device = format!("/dev/{}", name);
+            Some(Zram {
+                swap: swaps
+                    .lines()
+                    .any(|line| line.split_whitespace().next() == Some(&device)),
+                name,
+                disk_size,
+                usage,
+                algorithm,
+            })
+        })
+        .collect()
+}
+
+/// tmpfs and ramfs mounts of at least [`MIN_TMPFS_SIZE`], largest use first.
+pub fn tmpfs_mounts() -> Vec<Tmpfs> {
+    let mut seen = HashSet::new();
+    let mut mounts: Vec<Tmpfs> = read_to_string("/proc/mounts")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [_, mountpoint, fstype, ..] = fields.as_slice() else {
+                return None;
+            };
+            if !matches!(*fstype, "tmpfs" | "ramfs") {
+                return None;
+            }
+            let mountpoint = swap::decode_octal_escapes(mountpoint);
+            if !seen.insert(mountpoint.clone()) {
+                return None;
+            }
+            let stat = SystemStatvfs.statvfs(&mountpoint).ok()?;
+            Some(Tmpfs {
+                mountpoint,
+                size: stat.blocks.saturating_mul(stat.block_size),
+                used: stat
+                    .blocks
+                    .saturating_sub(stat.blocks_free)
+                    .saturating_mul(stat.block_size),
+            })
+        })
+        .filter(|mount| mount.size >= MIN_TMPFS_SIZE)
+        .collect();
+    mounts.sort_by(|a, b| b.used.cmp(&a.used).then_with(|| a.mountpoint.cmp(&b.mountpoint)));
+    mounts
+}
+
+/// Prints the "Memory-backed" section: zram devices with their compression
+/// ratio and large tmpfs mounts, all of which take RAM rather than disk.
+pub fn print(units: &Units) {
+    let zram = zram_devices();
+    let tmpfs = tmpfs_mounts();
+    if zram.is_empty() && tmpfs.is_empty() {
+        return;
+    }
+    println!("\n{}", "Memory-backed:".bold().blue());
+
+    for device in &zram {
+        let stored = match &device.usage {
+            Some(usage) => {
+                let ratio = match usage.compressed {
+                    0 => String::new(),
+                    compressed => format!(" ({:.1}x)", usage.original as f64 / compressed as f64),
+                };
+                format!(
+                    "{} stored in {}{}, {} of RAM",
+                    units.used.format(usage.original),
+                    units.used.format(usage.compressed),
+                    ratio,
+                    units.used.format(usage.memory)
+                )
+            }
+            None => "usage unreadable".to_string(),
+        };
+        println!(
+            "  {} {} {}, {}, disk size {}",
+            device.name.bold(),
+            if device.swap { "swap" } else { "block device" },
+            device.algorithm.dimmed(),
+            stored,
+            units.size.format(device.disk_size)
+        );
+    }
+    for mount in &tmpfs {
+        println!(
+            "  {} {} {}",
+            "tmpfs".bold(),
+            crate::text::isolate(&mount.mountpoint),
+            units.used_of(mount.used, mount.size)
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{read_dir, read_to_string},
+};
+
+use crate::{
+    Drive, Mount,
+    platform::linux::{self, sysfs_links},
+};
+
+/// One path of a multipath device and its state: for dm-multipath the SCSI
+/// state from `/sys/block/<path>/device/state` (`running`, `offline`, ...),
+/// for NVMe native multipath the ANA state (`optimized`, `non-optimized`,
+/// `inaccessible`, ...).
+pub struct PathState {
+    pub name: String,
+    pub state: String,
+    /// An NVMe path, whose state is an ANA state
+    pub ana: bool,
+}
+
+impl PathState {
+    /// I/O can go down this path.
+    pub fn up(&self) -> bool {
+        matches!(self.state.as_str(), "running" | "optimized" | "non-optimized")
+    }
+}
+
+fn sysfs(device: &str, attribute: &str) -> Option<String> {
+    read_to_string(format!("/sys/block/{}/{}", device, attribute))
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+/// Finds dm-multipath maps (dm uuid prefix `mpath-`) and builds a drive for
+/// each one, with its kpartx partitions as children. Returns the drives and
+/// the underlying path devices, which should not be listed on their own.
+pub fn discover(mountpoints: &HashMap<String, Mount>) -> (Vec<Drive>, HashSet<String>) {
+    let mut drives = Vec::new();
+    let mut paths = HashSet::new();
+
+    let dm_devices: Vec<String> = read_dir("/sys/block")
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().to_str().map(String::from))
+        .filter(|name| name.starts_with("dm-"))
+        .collect();
+
+    for dm in &dm_devices {
+        let is_multipath = sysfs(dm, "dm/uuid").is_some_and(|uuid| uuid.starts_with("mpath-"));
+        if !is_multipath {
+            continue;
+        }
+
+        let slaves = sysfs_links(&format!("/sys/block/{}/slaves", dm));
+        paths.extend(slaves.iter().cloned());
+
+        let states = slaves
+            .into_iter()
+            .map(|name| PathState {
+                state: sysfs(&name, "device/state").unwrap_or_else(|| "unknown".to_string()),
+                name,
+                ana: false,
+            })
+            .collect();
+
+        // kpartx partitions are dm devices stacked on the map, so they are
+        // found as holders of the whole device
+        let mut drive = linux::drive(dm, mountpoints);
+        drive.alias = sysfs(dm, "dm/name");
+        drive.multipath = Some(states);
+        drives.push(drive);
+    }
+
+    (drives, paths)
+}
+
+/// The controller paths of an NVMe namespace under native multipath, the
+/// hidden `nvme<subsys>c<ctrl>n<ns>` devices linked from
+/// `/sys/block/<head>/multipath`, with their ANA states. `None` for other
+/// drives.
+pub fn nvme_paths(head: &str) -> Option<Vec<PathState>> {
+    let paths = sysfs_links(&format!("/sys/block/{}/multipath", head));
+    if paths.is_empty() {
+        return None;
+    }
+    let states = paths
+        .into_iter()
+        .map(|name| PathState {
+            state: read_to_string(format!("/sys/class/block/{}/ana_state", name))
+                .map_or_else(|_| "unknown".to_string(), |state| state.trim().to_string()),
+            name,
+            ana: true,
+        })
+        .collect();
+    Some(states)
+}
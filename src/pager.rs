@@ -0,0 +1,93 @@
+//! `--paging`: the charts through `$PAGER`, the way git and bat do, for
+//! storage servers with more drives than the terminal has rows. The charts
+//! are rendered into a pipe first to see whether they fit.
+
+#[cfg(unix)]
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+    process::{Command, Stdio},
+};
+
+#[cfg(unix)]
+use terminal_size::{Height, Width, terminal_size};
+
+use crate::cli::Paging;
+#[cfg(unix)]
+use crate::{text, watch};
+
+/// Terminal rows `output` takes up, counting the lines that wrap.
+#[cfg(unix)]
+fn rows(output: &str, columns: usize) -> usize {
+    output
+        .lines()
+        .map(|line| text::visible_width(line).div_ceil(columns.max(1)).max(1))
+        .sum()
+}
+
+/// Feeds `output` to the pager and waits for it to quit. Without `LESS` in
+/// the environment less is told to pass the colors through and to leave
+/// the charts on screen after quitting.
+#[cfg(unix)]
+fn pipe(output: &str) -> io::Result<()> {
+    let pager = env::var("PAGER").ok().filter(|pager| !pager.trim().is_empty());
+    let pager = pager.as_deref().unwrap_or("less");
+    let mut words = pager.split_whitespace();
+    let mut command = Command::new(words.next().unwrap_or("less"));
+    command.args(words).stdin(Stdio::piped());
+    if env::var_os("LESS").is_none() {
+        command.env("LESS", "RX");
+    }
+    let mut child = command.spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Quitting the pager early closes the pipe, which is not an error
+        match stdin.write_all(output.as_bytes()) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e),
+            _ => {}
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
+
+/// Runs `render`, which prints to stdout, and shows what it printed
+/// through the pager if `paging` asks for it. Only a terminal is paged;
+/// pipes and files get the output as it is.
+#[cfg(unix)]
+pub fn page<T>(paging: Paging, render: impl FnOnce() -> T) -> T {
+    let (columns, height) = match terminal_size() {
+        Some((Width(columns), Height(height))) if paging != Paging::Never && io::stdout().is_terminal() => {
+            (columns as usize, height as usize)
+        }
+        _ => return render(),
+    };
+    // colored decides on first use whether stdout is a terminal, so it has
+    // to before stdout becomes the pipe
+    colored::control::SHOULD_COLORIZE.should_colorize();
+    let (value, output) = watch::capture_stdout(render);
+
+    // The last row stays free for the shell prompt
+    if paging == Paging::Auto && rows(&output, columns) < height {
+        print!("{}", output);
+    } else if let Err(e) = pipe(&output) {
+        warn!("pblk: cannot run the pager: {}", e);
+        print!("{}", output);
+    }
+    value
+}
+
+/// The console cannot be redirected in-process, so Windows is not paged.
+#[cfg(windows)]
+pub fn page<T>(_: Paging, render: impl FnOnce() -> T) -> T {
+    render()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrapped_and_colored_lines_count_by_what_shows() {
+        assert_eq!(rows("\x1b[31mabcd\x1b[0m\n\nabcdefghi\n", 4), 1 + 1 + 3);
+    }
+}
@@ -0,0 +1,79 @@
+use std::{
+    collections::HashMap,
+    fs::{canonicalize, read_to_string},
+};
+
+use crate::{
+    Mount, platform,
+    severity::{Severity, Thresholds},
+    source::{StatvfsProvider, SystemStatvfs},
+};
+
+/// Exit status when the target cannot be found or queried, after the
+/// monitoring plugin convention of 0 ok, 1 warning, 2 critical, 3 unknown.
+const UNKNOWN: i32 = 3;
+
+/// The device and mount holding `target`, which is a device or a path on
+/// the filesystem.
+fn resolve<'a>(target: &str, table: &'a HashMap<String, Mount>) -> Option<(&'a str, &'a Mount)> {
+    for device in [target.to_string(), format!("/dev/{}", target)] {
+        if let Some((device, mount)) = table.get_key_value(&device) {
+            return Some((device, mount));
+        }
+    }
+    // The deepest mountpoint holding the path, bind mounts included
+    let path = canonicalize(target).ok()?;
+    table
+        .iter()
+        .flat_map(|(device, mount)| mount.paths().map(move |mountpoint| (device, mount, mountpoint)))
+        .filter(|(_, _, mountpoint)| path.starts_with(mountpoint))
+        .max_by_key(|(_, _, mountpoint)| mountpoint.len())
+        .map(|(device, mount, _)| (device.as_str(), mount))
+}
+
+/// Size of the block device in bytes, which the charts measure usage
+/// against; `None` off Linux or for filesystems without a device.
+fn device_bytes(device: &str) -> Option<u64> {
+    let name = device.rsplit('/').next()?;
+    let sectors: u64 = read_to_string(format!("/sys/class/block/{}/size", name))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    (sectors > 0).then_some(sectors * 512)
+}
+
+/// Prints the usage percentage of the filesystem holding `target`, the same
+/// number as its `use%` column, and returns the exit status for its severity.
+/// With `quiet` nothing is printed, not even why the target is unknown.
+pub fn run(target: &str, thresholds: &Thresholds, quiet: bool) -> i32 {
+    let table = match platform::native().mounts() {
+        Ok(table) => table,
+        Err(e) => {
+            warn!("pblk: cannot read the mount table: {}", e);
+            return UNKNOWN;
+        }
+    };
+    let Some((device, mount)) = resolve(target, &table) else {
+        warn!("pblk: {} is not a mounted filesystem or a path on one", target);
+        return UNKNOWN;
+    };
+    let Some(stat) = SystemStatvfs.statvfs(&mount.path).ok().filter(|stat| stat.blocks > 0) else {
+        warn!("pblk: cannot query usage of {}", mount.path);
+        return UNKNOWN;
+    };
+
+    let used = stat
+        .blocks
+        .saturating_sub(stat.blocks_free)
+        .saturating_mul(stat.block_size);
+    let total = device_bytes(device).unwrap_or(stat.blocks.saturating_mul(stat.block_size));
+    if !quiet {
+        println!("{:.0}", used as f64 / total as f64 * 100.0);
+    }
+    match thresholds.classify(Some(&mount.path), Some(&mount.fstype), used, total) {
+        Severity::Ok => 0,
+        Severity::Warning => 1,
+        Severity::Critical => 2,
+    }
+}
@@ -0,0 +1,198 @@
+//! `--pick`: the drives and partitions as a list to choose from with the
+//! arrow keys, for `dd of=$(pblk --pick --removable-only)`. The list is
+//! drawn on and read from the terminal itself, so only the chosen device
+//! node reaches stdout.
+
+use std::io;
+#[cfg(unix)]
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    os::fd::AsRawFd,
+};
+
+#[cfg(unix)]
+use nix::sys::termios::{LocalFlags, SetArg, SpecialCharacterIndices, tcgetattr, tcsetattr};
+#[cfg(unix)]
+use terminal_size::{Height, Width, terminal_size_using_fd};
+
+use crate::{Drive, text, units::Units};
+
+/// One line of the list and the device node it stands for.
+#[cfg_attr(windows, allow(dead_code))]
+struct Choice {
+    path: String,
+    line: String,
+}
+
+/// A line per drive, `sda  500.1 GB  Samsung SSD 860`, followed by one
+/// per partition with its filesystem, size and mountpoint.
+#[cfg_attr(windows, allow(dead_code))]
+fn choices(drives: &[Drive], units: &Units) -> Vec<Choice> {
+    let width = drives
+        .iter()
+        .flat_map(|drive| {
+            drive
+                .partitions
+                .iter()
+                .map(|p| 2 * (p.depth + 1) + text::width(p.dev_name()))
+        })
+        .max()
+        .unwrap_or(0);
+    let mut choices = Vec::new();
+    for drive in drives {
+        let mut line = format!("{}  {}", drive.display_name(), units.size.format(drive.size * 512));
+        if let Some(model) = &drive.model {
+            line.push_str(&format!("  {}", text::isolate(model)));
+        }
+        choices.push(Choice {
+            path: format!("/dev/{}", drive.name),
+            line,
+        });
+        for partition in &drive.partitions {
+            let name = format!("{}{}", "  ".repeat(partition.depth + 1), partition.dev_name());
+            let line = format!(
+                "{}  {:8}  {:>9}  {}",
+                text::pad_right(&name, width),
+                partition.fstype.as_deref().unwrap_or("-"),
+                units.size.format(partition.size * 512),
+                text::isolate(partition.display_mountpoint().unwrap_or("")),
+            );
+            choices.push(Choice {
+                path: format!("/dev/{}", partition.dev_name()),
+                line: line.trim_end().to_string(),
+            });
+        }
+    }
+    choices
+}
+
+/// `line` cut to `columns`, since a wrapped line would throw off redrawing.
+#[cfg(unix)]
+fn clip(line: &str, columns: usize) -> String {
+    let mut clipped = String::new();
+    for c in line.chars() {
+        if text::width(&clipped) + text::width(c.encode_utf8(&mut [0; 4])) > columns {
+            break;
+        }
+        clipped.push(c);
+    }
+    clipped
+}
+
+#[cfg(unix)]
+enum Key {
+    Up,
+    Down,
+    First,
+    Last,
+    Pick,
+    Cancel,
+    Other,
+}
+
+#[cfg(unix)]
+fn key(bytes: &[u8]) -> Key {
+    match bytes {
+        b"\x1b[A" | b"\x1bOA" | b"k" => Key::Up,
+        b"\x1b[B" | b"\x1bOB" | b"j" => Key::Down,
+        b"\x1b[H" | b"\x1bOH" | b"g" => Key::First,
+        b"\x1b[F" | b"\x1bOF" | b"G" => Key::Last,
+        b"\r" | b"\n" => Key::Pick,
+        // Esc, q, Ctrl+C and Ctrl+D
+        b"\x1b" | b"q" | b"\x03" | b"\x04" => Key::Cancel,
+        _ => Key::Other,
+    }
+}
+
+/// Draws the list on `tty` and moves the highlight until a choice is made,
+/// then erases the list again. `None` if the user cancelled.
+#[cfg(unix)]
+fn select(tty: &mut File, choices: &[Choice]) -> io::Result<Option<usize>> {
+    let (columns, rows) = terminal_size_using_fd(tty.as_raw_fd()).map_or((80, 24), |(Width(w), Height(h))| (w, h));
+    // Two columns go to the highlight marker and a row to the key help
+    let columns = (columns as usize).saturating_sub(2).max(1);
+    let window = choices.len().min((rows as usize).saturating_sub(2).max(1));
+    let (mut selected, mut top) = (0usize, 0);
+    tty.write_all(b"\x1b[?25l")?;
+    let picked = loop {
+        top = top.min(selected).max((selected + 1).saturating_sub(window));
+        let mut frame = String::new();
+        for (i, choice) in choices.iter().enumerate().skip(top).take(window) {
+            match i == selected {
+                true => frame.push_str(&format!("\r\x1b[K\x1b[7m> {}\x1b[0m\n", clip(&choice.line, columns))),
+                false => frame.push_str(&format!("\r\x1b[K  {}\n", clip(&choice.line, columns))),
+            }
+        }
+        frame.push_str(&format!(
+            "\r\x1b[K\x1b[2m{}\x1b[0m",
+            clip("up/down to move, enter to pick, q to cancel", columns)
+        ));
+        tty.write_all(frame.as_bytes())?;
+        tty.flush()?;
+
+        let mut bytes = [0; 8];
+        let read = tty.read(&mut bytes)?;
+        write!(tty, "\r\x1b[{}A", window)?;
+        match key(&bytes[..read]) {
+            Key::Up => selected = selected.saturating_sub(1),
+            Key::Down => selected = (selected + 1).min(choices.len() - 1),
+            Key::First => selected = 0,
+            Key::Last => selected = choices.len() - 1,
+            Key::Pick => break Some(selected),
+            Key::Cancel => break None,
+            Key::Other => {}
+        }
+    };
+    tty.write_all(b"\r\x1b[J\x1b[?25h")?;
+    Ok(picked)
+}
+
+/// Lets the user pick one of `drives` or their partitions and returns its
+/// device node, `None` if they cancelled or there was nothing to pick.
+#[cfg(unix)]
+pub fn run(drives: &[Drive], units: &Units) -> io::Result<Option<String>> {
+    let choices = choices(drives, units);
+    if choices.is_empty() {
+        return Ok(None);
+    }
+    let mut tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    let saved = tcgetattr(&tty)?;
+    let mut keys = saved.clone();
+    keys.local_flags
+        .remove(LocalFlags::ICANON | LocalFlags::ECHO | LocalFlags::ISIG);
+    keys.control_chars[SpecialCharacterIndices::VMIN as usize] = 1;
+    keys.control_chars[SpecialCharacterIndices::VTIME as usize] = 0;
+    tcsetattr(&tty, SetArg::TCSANOW, &keys)?;
+    let picked = select(&mut tty, &choices);
+    let _ = tcsetattr(&tty, SetArg::TCSANOW, &saved);
+    Ok(picked?.map(|index| choices[index].path.clone()))
+}
+
+/// The console has no key-by-key mode that works like termios.
+#[cfg(windows)]
+pub fn run(_: &[Drive], _: &Units) -> io::Result<Option<String>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "--pick needs a Unix terminal",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Mount, Partition};
+
+    #[test]
+    fn partitions_are_picked_by_their_device_node() {
+        let mount = Mount::new("/media/stick", "vfat", &[]);
+        let partition = Partition::new("sdc/sdc1".to_string(), 1 << 21, Some(&mount));
+        let choices = choices(&[Drive::new("sdc", 1 << 22, vec![partition])], &Units::default());
+        assert_eq!(
+            choices.iter().map(|c| c.path.as_str()).collect::<Vec<_>>(),
+            ["/dev/sdc", "/dev/sdc1"]
+        );
+        assert!(choices[1].line.starts_with("  sdc1  vfat "), "{}", choices[1].line);
+        assert!(choices[1].line.ends_with(" /media/stick"));
+    }
+}
@@ -0,0 +1,97 @@
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+/// Holds the pidfile lock for as long as it is alive. The kernel drops the
+/// lock when the process exits, so a pidfile left behind by a crash never
+/// blocks the next start.
+pub struct Guard {
+    _file: File,
+}
+
+/// Takes an exclusive lock on `file` without waiting; `Ok(false)` when
+/// another process holds it.
+#[cfg(unix)]
+fn try_lock(file: &File) -> io::Result<bool> {
+    use nix::{
+        errno::Errno,
+        fcntl::{FlockArg, flock},
+    };
+    use std::os::fd::AsRawFd;
+
+    match flock(file.as_raw_fd(), FlockArg::LockExclusiveNonblock) {
+        Ok(()) => Ok(true),
+        Err(Errno::EWOULDBLOCK) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(windows)]
+fn try_lock(file: &File) -> io::Result<bool> {
+    match file.try_lock() {
+        Ok(()) => Ok(true),
+        Err(fs::TryLockError::WouldBlock) => Ok(false),
+        Err(fs::TryLockError::Error(e)) => Err(e),
+    }
+}
+
+/// Default pidfile of the exporter daemon.
+pub fn default_path() -> PathBuf {
+    env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .unwrap_or_else(env::temp_dir)
+        .join("prettyblk-exporter.pid")
+}
+
+/// Takes the advisory lock on `path` and records this process in it, so a
+/// second daemon refuses to start instead of writing the same history and
+/// logs. `description` (e.g. the listen address) is stored next to the pid
+/// to put a name on the running instance. With `force`, a held lock is
+/// reported and ignored.
+pub fn acquire(path: &Path, description: &str, force: bool) -> Result<Option<Guard>, String> {
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .map_err(|e| format!("cannot open pidfile {}: {}", path.display(), e))?;
+
+    match try_lock(&file) {
+        Ok(true) => {}
+        Ok(false) => {
+            let mut owner = String::new();
+            let _ = file.read_to_string(&mut owner);
+            let mut lines = owner.lines();
+            let pid = lines.next().unwrap_or("unknown");
+            let what = lines.next().unwrap_or("unknown");
+            let message = format!(
+                "another instance is already running (pid {}, {}; pidfile {})",
+                pid,
+                what,
+                path.display()
+            );
+            if force {
+                warn!("pblk: {}; continuing because of --force", message);
+                return Ok(None);
+            }
+            return Err(format!("{}; use --force to start anyway", message));
+        }
+        Err(e) => return Err(format!("cannot lock pidfile {}: {}", path.display(), e)),
+    }
+
+    let contents = format!("{}\n{}\n", process::id(), description);
+    file.set_len(0)
+        .and_then(|_| file.seek(SeekFrom::Start(0)))
+        .and_then(|_| file.write_all(contents.as_bytes()))
+        .map_err(|e| format!("cannot write pidfile {}: {}", path.display(), e))?;
+    Ok(Some(Guard { _file: file }))
+}
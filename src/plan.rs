@@ -0,0 +1,149 @@
+use std::process::Command;
+
+use colored::*;
+
+use crate::{Drive, severity::Thresholds, units::Units};
+
+/// Current capacity of the pool, VG or filesystem a disk would be added to.
+struct Target {
+    kind: &'static str,
+    mountpoint: Option<String>,
+    capacity: u64,
+    used: u64,
+    /// How much usable space one byte of added raw capacity yields
+    efficiency: f64,
+}
+
+/// Usable fraction of raw space for a btrfs data profile spread over
+/// `devices` devices.
+fn profile_efficiency(profile: &str, devices: usize) -> f64 {
+    let devices = devices as f64;
+    match profile {
+        "RAID1" | "RAID10" | "DUP" => 0.5,
+        "RAID1C3" => 1.0 / 3.0,
+        "RAID1C4" => 0.25,
+        "RAID5" => (devices - 1.0) / devices,
+        "RAID6" => (devices - 2.0) / devices,
+        _ => 1.0,
+    }
+}
+
+fn btrfs_target(drives: &[Drive], mountpoint: &str) -> Option<Target> {
+    let members: Vec<_> = drives
+        .iter()
+        .flat_map(|d| &d.partitions)
+        .filter_map(|p| p.pool.as_ref())
+        .filter(|pool| pool.mountpoint == mountpoint)
+        .collect();
+    let primary = members.iter().find(|m| m.primary)?;
+
+    // The new device takes part in the profile, so RAID5/6 get a bit better
+    let efficiency = profile_efficiency(primary.data_profile, primary.num_devices + 1);
+    let current = profile_efficiency(primary.data_profile, primary.num_devices);
+    let unallocated: u64 = members.iter().map(|m| m.unallocated).sum();
+    let free = primary.data_total.saturating_sub(primary.data_used) + (unallocated as f64 * current) as u64;
+    Some(Target {
+        kind: "btrfs filesystem",
+        mountpoint: Some(mountpoint.to_string()),
+        capacity: primary.data_used + free,
+        used: primary.data_used,
+        efficiency,
+    })
+}
+
+#[cfg(feature = "zfs")]
+fn zfs_target(name: &str) -> Option<Target> {
+    let pool = crate::zfs::read_pools().into_iter().find(|pool| pool.name == name)?;
+    Some(Target {
+        kind: "ZFS pool (as a new top-level vdev)",
+        mountpoint: None,
+        capacity: pool.size,
+        used: pool.allocated,
+        efficiency: 1.0,
+    })
+}
+
+#[cfg(not(feature = "zfs"))]
+fn zfs_target(_name: &str) -> Option<Target> {
+    None
+}
+
+fn lvm_target(name: &str) -> Option<Target> {
+    let output = Command::new("vgs")
+        .args([
+            "--noheadings",
+            "--units",
+            "b",
+            "--nosuffix",
+            "-o",
+            "vg_size,vg_free",
+            name,
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    let mut fields = stdout.split_whitespace().map(|f| f.parse::<u64>());
+    let size = fields.next()?.ok()?;
+    let free = fields.next()?.ok()?;
+    Some(Target {
+        kind: "LVM volume group (allocated extents)",
+        mountpoint: None,
+        capacity: size,
+        used: size - free,
+        efficiency: 1.0,
+    })
+}
+
+fn percent(used: u64, capacity: u64, target: &Target, thresholds: &Thresholds) -> ColoredString {
+    let ratio = used as f64 / capacity.max(1) as f64;
+    let severity = thresholds.classify_ratio(target.mountpoint.as_deref(), None, ratio);
+    format!("{:.1}%", ratio * 100.0).color(severity.color(Color::Green))
+}
+
+/// Prints the capacity and usage of `name` (a ZFS pool, btrfs mountpoint or
+/// LVM volume group) before and after adding `add` raw bytes. Returns false
+/// if the target was not found.
+pub fn run(drives: &[Drive], add: u64, name: &str, units: &Units, thresholds: &Thresholds) -> bool {
+    let Some(target) = zfs_target(name)
+        .or_else(|| btrfs_target(drives, name))
+        .or_else(|| lvm_target(name))
+    else {
+        eprintln!(
+            "pblk: '{}' is not a ZFS pool, btrfs mountpoint or LVM volume group",
+            name
+        );
+        return false;
+    };
+
+    let gained = (add as f64 * target.efficiency) as u64;
+    let capacity = target.capacity + gained;
+
+    println!(
+        "{} add {} to {} ({})",
+        "Plan:".bold().blue(),
+        units.size.format(add),
+        name.bold(),
+        target.kind
+    );
+    println!(
+        "  capacity  {} -> {}  (+{})",
+        units.size.format(target.capacity),
+        units.size.format(capacity),
+        units.size.format(gained)
+    );
+    println!(
+        "  used      {}  {} -> {}",
+        units.used.format(target.used),
+        percent(target.used, target.capacity, &target, thresholds),
+        percent(target.used, capacity, &target, thresholds)
+    );
+    println!(
+        "  free      {} -> {}",
+        units.used.format(target.capacity.saturating_sub(target.used)),
+        units.used.format(capacity.saturating_sub(target.used))
+    );
+    true
+}
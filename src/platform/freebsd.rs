@@ -0,0 +1,92 @@
+//! Drives from the GEOM configuration (`kern.geom.conftxt`), mounts from
+//! `mount -p`.
+
+use std::{collections::HashMap, io, process::Command};
+
+use crate::{Drive, Mount, Partition, PrettyblkError};
+
+use super::Platform;
+
+pub struct FreeBsd;
+
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+impl Platform for FreeBsd {
+    fn drives(&self, mountpoints: &HashMap<String, Mount>) -> Vec<Drive> {
+        let Some(conf) = run("sysctl", &["-n", "kern.geom.conftxt"]) else {
+            return Vec::new();
+        };
+
+        // Lines look like `<depth> <class> <name> <mediasize> <sectorsize> [key value]...`;
+        // PART providers follow the DISK they are carved from
+        let mut drives: Vec<Drive> = Vec::new();
+        for line in conf.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [_, class, name, size, ..] = fields.as_slice() else {
+                continue;
+            };
+            let (sectors, size_error) = match size.parse::<u64>() {
+                Ok(bytes) => (bytes / 512, None),
+                Err(e) => (
+                    0,
+                    Some(PrettyblkError::Platform {
+                        what: format!("the size of {} from kern.geom.conftxt", name),
+                        source: io::Error::new(io::ErrorKind::InvalidData, e),
+                    }),
+                ),
+            };
+            match *class {
+                "DISK" => {
+                    let mut drive = Drive::new(name, sectors, Vec::new());
+                    drive.size_error = size_error;
+                    drives.push(drive);
+                }
+                "PART" => {
+                    let Some(drive) = drives.iter_mut().rev().find(|d| name.starts_with(&d.name)) else {
+                        continue;
+                    };
+                    let scheme = fields.windows(2).find(|pair| pair[0] == "xs").map(|pair| pair[1]);
+                    drive.table = match scheme {
+                        Some("GPT") => Some("gpt"),
+                        Some("MBR") => Some("dos"),
+                        _ => drive.table,
+                    };
+                    let mount = super::mount_of(name, mountpoints);
+                    let mut partition = Partition::new(name.to_string(), sectors, mount);
+                    partition.size_error = size_error;
+                    drive.partitions.push(partition);
+                }
+                _ => {}
+            }
+        }
+        drives
+    }
+
+    fn mounts(&self) -> io::Result<HashMap<String, Mount>> {
+        // `mount -p` prints fstab lines: device, mountpoint, type, options
+        let entries = run("mount", &["-p"])
+            .ok_or_else(|| io::Error::other("mount failed"))?
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let [source, path, fstype, options, ..] = fields.as_slice() else {
+                    return None;
+                };
+                let mount = Mount {
+                    path: path.to_string(),
+                    fstype: fstype.to_string(),
+                    options: options.split(',').map(String::from).collect(),
+                    also: Vec::new(),
+                };
+                Some((source.to_string(), mount))
+            })
+            .collect::<Vec<_>>();
+        Ok(super::mount_table(entries))
+    }
+}
@@ -0,0 +1,437 @@
+//! Block devices from sysfs, mounts from procfs.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{File, canonicalize, read_dir, read_link, read_to_string},
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{Drive, Mount, Partition, PrettyblkError, bay, image, multipath, privilege, remote, udev, winfs};
+
+use super::Platform;
+
+const PSEUDO_DEVICES: [&str; 8] = ["loop", "ram", "zram", "dm", "md", "fd", "vd", "xvd"];
+
+pub struct Linux;
+
+impl Platform for Linux {
+    fn drives(&self, mountpoints: &HashMap<String, Mount>) -> Vec<Drive> {
+        let (multipath_drives, multipath_paths) = multipath::discover(mountpoints);
+
+        let devices = block_devices();
+        let mut drives: Vec<Drive> = devices
+            .iter()
+            .filter(|device| device.partition.is_none())
+            .map(|device| device.name.as_str())
+            .filter(|name| !is_pseudo(name, Path::new("/sys/block")))
+            .filter(|name| !multipath_paths.contains(*name))
+            // NVMe native multipath paths, listed under the namespace they serve
+            .filter(|name| !hidden(name))
+            .map(|name| drive_of(name, &devices, mountpoints))
+            .collect();
+        drives.extend(multipath_drives);
+
+        // A volume group spanning several disks is listed under the first one only
+        let mut seen = HashSet::new();
+        for drive in &mut drives {
+            drive.partitions.retain(|p| p.depth == 0 || seen.insert(p.name.clone()));
+        }
+        drives
+    }
+
+    fn mounts(&self) -> io::Result<HashMap<String, Mount>> {
+        let mut entries = Vec::new();
+        for line in read_to_string("/proc/mounts")?.lines() {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() >= 3 {
+                // Resolve /dev/mapper/* and /dev/disk/by-* links to kernel names
+                let source = match parts[0].starts_with("/dev/") {
+                    true => canonicalize(parts[0])
+                        .map(|p| p.display().to_string())
+                        .unwrap_or_else(|_| parts[0].to_string()),
+                    false => parts[0].to_string(),
+                };
+                entries.push((
+                    source,
+                    Mount {
+                        path: parts[1].to_string(),
+                        fstype: parts[2].to_string(),
+                        options: parts
+                            .get(3)
+                            .map(|o| o.split(',').map(String::from).collect())
+                            .unwrap_or_default(),
+                        also: Vec::new(),
+                    },
+                ));
+            }
+        }
+        Ok(super::mount_table(entries))
+    }
+}
+
+/// Vendor and model substrings of RAID controllers that present their
+/// arrays as a single SCSI disk, with the family shown to the user.
+const RAID_MODELS: [(&str, &str); 8] = [
+    ("PERC", "Dell PERC"),
+    ("MegaRAID", "Broadcom MegaRAID"),
+    ("MR9", "Broadcom MegaRAID"),
+    ("LOGICAL VOLUME", "HPE Smart Array"),
+    ("Smart Array", "HPE Smart Array"),
+    ("ServeRAID", "IBM ServeRAID"),
+    ("Adaptec", "Adaptec"),
+    ("ASR8", "Adaptec"),
+];
+
+/// A block device as `/sys/class/block` lists it.
+struct BlockDevice {
+    name: String,
+    /// Number in its drive's partition table, for partitions
+    partition: Option<u32>,
+    /// The disk a partition belongs to
+    parent: Option<String>,
+}
+
+/// Disks and partitions from `/sys/class/block`, which lists every block
+/// device the kernel knows, sorted by name. `DEVTYPE` in the uevent file
+/// tells the two apart, or the `partition` attribute where it is missing;
+/// a partition's disk is the directory above it in the device path.
+fn block_devices() -> Vec<BlockDevice> {
+    sysfs_links("/sys/class/block")
+        .into_iter()
+        .map(|name| {
+            let dir = format!("/sys/class/block/{}", name);
+            let uevent = read_to_string(format!("{}/uevent", dir)).unwrap_or_default();
+            let number = read_to_string(format!("{}/partition", dir)).ok();
+            let is_partition = match uevent.lines().find_map(|line| line.strip_prefix("DEVTYPE=")) {
+                Some(devtype) => devtype == "partition",
+                None => number.is_some(),
+            };
+            if !is_partition {
+                return BlockDevice {
+                    name,
+                    partition: None,
+                    parent: None,
+                };
+            }
+            let parent = canonicalize(&dir)
+                .ok()
+                .and_then(|path| Some(path.parent()?.file_name()?.to_str()?.to_string()));
+            BlockDevice {
+                name,
+                partition: Some(number.and_then(|n| n.trim().parse().ok()).unwrap_or(0)),
+                parent,
+            }
+        })
+        .collect()
+}
+
+/// Devices that are not drives of their own, and NBD devices the kernel
+/// keeps around unconnected, which have no client `pid`.
+fn is_pseudo(name: &str, sys_block: &Path) -> bool {
+    if name.starts_with("nbd") {
+        return !sys_block.join(name).join("pid").exists();
+    }
+    PSEUDO_DEVICES.iter().any(|device| name.starts_with(device))
+}
+
+/// The drive `/sys/block/<name>` with its partitions and stacked devices.
+pub fn drive(name: &str, mountpoints: &HashMap<String, Mount>) -> Drive {
+    drive_of(name, &block_devices(), mountpoints)
+}
+
+/// The drive `name`, its partitions found among `devices`.
+fn drive_of(name: &str, devices: &[BlockDevice], mountpoints: &HashMap<String, Mount>) -> Drive {
+    let (size, size_error) = checked_size(name);
+    let mut drive = Drive::new(name, size, get_partitions(name, devices, mountpoints));
+    drive.size_error = size_error;
+    drive.read_only = read_only(name);
+    drive.raid_controller = raid_controller(name);
+    drive.multipath = multipath::nvme_paths(name);
+    drive.removable = flag(name, "removable");
+    drive.model = read_to_string(format!("/sys/block/{}/device/model", name))
+        .ok()
+        .map(|model| model.trim().to_string())
+        .filter(|model| !model.is_empty());
+    drive.rotational = read_to_string(format!("/sys/block/{}/queue/rotational", name))
+        .ok()
+        .map(|rotational| rotational.trim() == "1");
+    // The device path runs through the USB host controller and hub ports
+    drive.usb = canonicalize(format!("/sys/block/{}", name)).is_ok_and(|path| {
+        path.components()
+            .any(|c| c.as_os_str().to_string_lossy().starts_with("usb"))
+    });
+    // Card readers and optical drives stay registered with a size of zero
+    // while empty; `events` lists the media events they can report
+    let events = read_to_string(format!("/sys/block/{}/events", name)).unwrap_or_default();
+    drive.no_media = drive.size == 0 && (drive.removable || !events.trim().is_empty());
+    drive.remote = remote::target(name);
+    drive.bay = bay::find(name);
+    drive.unused = drive.partitions.is_empty() && drive.size > 0 && !drive.no_media && blank(name) == Some(true);
+    drive
+}
+
+/// Whether the disk holds no signature at all, going by udev's probe or,
+/// without udev, by reading its first sectors. `None` when neither can tell.
+fn blank(name: &str) -> Option<bool> {
+    let props = udev::properties(&format!("/sys/block/{}", name));
+    if !props.is_empty() {
+        return Some(!props.contains_key("ID_FS_TYPE") && !props.contains_key("ID_PART_TABLE_TYPE"));
+    }
+    let mut file = open_device(name, "whether the disk is unused")?;
+    Some(!image::has_signature(&mut file))
+}
+
+/// Opens the device node of `name`, a drive or a `drive/partition` sysfs
+/// path, for raw reads, noting `what` as left out if that is refused.
+fn open_device(name: &str, what: &str) -> Option<File> {
+    let device = name.rsplit('/').next().unwrap_or(name);
+    File::open(format!("/dev/{}", device))
+        .inspect_err(|e| {
+            if privilege::refused(e) {
+                privilege::deny_on(what, device);
+            }
+        })
+        .ok()
+}
+
+/// The controller family if the drive is a hardware RAID logical volume,
+/// recognized by the SCSI vendor and model strings the controller reports.
+fn raid_controller(name: &str) -> Option<&'static str> {
+    let attribute = |file| read_to_string(format!("/sys/block/{}/device/{}", name, file)).unwrap_or_default();
+    let identity = format!("{} {}", attribute("vendor").trim(), attribute("model").trim());
+    RAID_MODELS
+        .iter()
+        .find(|(pattern, _)| identity.contains(pattern))
+        .map(|(_, family)| *family)
+}
+
+/// The partition or stacked device at `/sys/block/<name>`.
+fn partition(name: String, mountpoints: &HashMap<String, Mount>) -> Partition {
+    let mount = super::mount_of(&name, mountpoints);
+    let (size, size_error) = checked_size(&name);
+    let mut partition = Partition::new(name.clone(), size, mount);
+    partition.size_error = size_error;
+    partition.alias = read_to_string(format!("/sys/block/{}/dm/name", name))
+        .ok()
+        .map(|alias| alias.trim().to_string());
+    partition.read_only = read_only(&name);
+    partition.uuid = uuid(&name);
+    partition.holders = sysfs_links(&format!("/sys/block/{}/holders", name))
+        .iter()
+        .map(|holder| describe_holder(holder))
+        .collect();
+    partition.slaves = sysfs_links(&format!("/sys/block/{}/slaves", name));
+    if mount.is_none() {
+        let fstype = unmounted_filesystem(&name);
+        partition.windows = fstype
+            .as_deref()
+            .and_then(|fstype| winfs::Windows::of(fstype, &partition.holders));
+        partition.image_fs = fstype.filter(|fstype| fstype == "squashfs" || fstype == "erofs");
+    }
+    // Only partitions have a `start`; stacked devices are not on the disk directly
+    partition.start = read_to_string(format!("/sys/block/{}/start", name))
+        .ok()
+        .and_then(|start| start.trim().parse().ok());
+    partition
+}
+
+/// A device built on another one, by its device-mapper name where it has
+/// one, with what it is: `md0 (raid1)`, `luks-3f2a (crypt)`, `vg0-root (LVM)`.
+fn describe_holder(name: &str) -> String {
+    let attribute = |file: &str| {
+        read_to_string(format!("/sys/block/{}/{}", name, file))
+            .ok()
+            .map(|value| value.trim().to_string())
+    };
+    let kind = match name {
+        _ if name.starts_with("md") => attribute("md/level"),
+        _ if name.starts_with("bcache") => Some("bcache".to_string()),
+        _ => attribute("dm/uuid").and_then(|uuid| {
+            let kind = match uuid.split('-').next()? {
+                _ if uuid.starts_with("CRYPT-VERITY-") => "verity",
+                "CRYPT" => "crypt",
+                "LVM" => "LVM",
+                "mpath" => "multipath",
+                _ => return None,
+            };
+            Some(kind.to_string())
+        }),
+    };
+    let label = attribute("dm/name").unwrap_or_else(|| name.to_string());
+    match kind {
+        Some(kind) => format!("{} ({})", label, kind),
+        None => label,
+    }
+}
+
+/// The filesystem on an unmounted device, for the read-only images embedded
+/// systems ship their system partitions as and for partitions Windows
+/// formatted. Asks udev, or without udev reads the superblock.
+fn unmounted_filesystem(name: &str) -> Option<String> {
+    let mut props = udev::properties(&format!("/sys/block/{}", name));
+    match props.is_empty() {
+        true => image::filesystem(&mut open_device(name, "unmounted filesystem types")?),
+        false => props.remove("ID_FS_TYPE"),
+    }
+}
+
+/// The partition's own UUID from udev, falling back to the filesystem UUID
+/// for devices outside a partition table and to `uevent` without udev.
+fn uuid(name: &str) -> Option<String> {
+    let sysfs_dir = format!("/sys/block/{}", name);
+    let mut props = udev::properties(&sysfs_dir);
+    props
+        .remove("ID_PART_ENTRY_UUID")
+        .or_else(|| props.remove("ID_FS_UUID"))
+        .or_else(|| {
+            let uevent = read_to_string(format!("{}/uevent", sysfs_dir)).ok()?;
+            let uuid = uevent.lines().find_map(|line| line.strip_prefix("PARTUUID="))?;
+            Some(uuid.to_string())
+        })
+}
+
+/// Names of the entries in a sysfs directory such as `holders`.
+pub fn sysfs_links(path: &str) -> Vec<String> {
+    let mut names: Vec<String> = read_dir(path)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().to_str().map(String::from))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Output of `dmsetup <command> --target <target>`, one line per map
+/// using that target. dmsetup needs root, so this is `None` for others.
+pub fn dmsetup(command: &str, target: &str) -> Option<String> {
+    let output = Command::new("dmsetup")
+        .args([command, "--target", target])
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The device-mapper name of each `dm-N`, e.g. `vg0-root` for `dm-0`.
+pub fn dm_maps() -> Vec<(String, String)> {
+    sysfs_links("/sys/block")
+        .into_iter()
+        .filter(|name| name.starts_with("dm-"))
+        .filter_map(|name| {
+            let map = read_to_string(Path::new("/sys/block").join(&name).join("dm/name")).ok()?;
+            Some((name, map.trim().to_string()))
+        })
+        .collect()
+}
+
+/// The kernel name of the block device `major:minor`, as dmsetup tables
+/// name the devices a map is built on.
+pub fn block_name(device: &str) -> Option<String> {
+    let link = read_link(format!("/sys/dev/block/{}", device)).ok()?;
+    Some(link.file_name()?.to_str()?.to_string())
+}
+
+/// The `dm-N` kernel name of the device-mapper map called `map`.
+pub fn dm_device(map: &str) -> Option<String> {
+    dm_maps()
+        .into_iter()
+        .find(|(_, name)| name == map)
+        .map(|(device, _)| device)
+}
+
+/// Partitions of a drive in table order, each followed by the devices
+/// stacked on it. Partitions are recognized by their device type rather
+/// than their name, which differs between sda1, nvme0n1p1 and mmcblk0p1.
+/// A drive without partitions lists what is built on the whole
+/// disk, e.g. an LVM physical volume or a multipath map's kpartx partitions.
+fn get_partitions(_name: &str, devices: &[BlockDevice], mountpoints: &HashMap<String, Mount>) -> Vec<Partition> {
+    let mut numbered: Vec<(u32, &str)> = devices
+        .iter()
+        .filter(|device| device.parent.as_deref() == Some(_name))
+        .filter_map(|device| Some((device.partition?, device.name.as_str())))
+        .collect();
+    numbered.sort();
+
+    let mut partitions = Vec::new();
+    if numbered.is_empty() {
+        add_holders(_name, 0, mountpoints, &mut partitions);
+    }
+    for (_, entry) in numbered {
+        let path = format!("{}/{}", _name, entry);
+        partitions.push(partition(path.clone(), mountpoints));
+        add_holders(&path, 1, mountpoints, &mut partitions);
+    }
+    partitions
+}
+
+/// Appends the devices holding `path` open (dm, md, bcache), recursively.
+fn add_holders(path: &str, depth: usize, mountpoints: &HashMap<String, Mount>, out: &mut Vec<Partition>) {
+    // Stacks deeper than this are either exotic or a sysfs loop
+    if depth > 8 {
+        return;
+    }
+    for holder in sysfs_links(&format!("/sys/block/{}/holders", path)) {
+        let mut stacked = partition(holder.clone(), mountpoints);
+        stacked.depth = depth;
+        out.push(stacked);
+        add_holders(&holder, depth + 1, mountpoints, out);
+    }
+}
+
+pub fn read_size(name: &str) -> io::Result<u64> {
+    let file = read_to_string(format!("/sys/block/{}/size", name))?;
+    file.trim()
+        .parse()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// The size of `name` in sectors, zero with the reason when it cannot be read.
+fn checked_size(name: &str) -> (u64, Option<PrettyblkError>) {
+    match read_size(name) {
+        Ok(size) => (size, None),
+        Err(source) => {
+            let path = PathBuf::from(format!("/sys/block/{}/size", name));
+            (0, Some(PrettyblkError::Sysfs { path, source }))
+        }
+    }
+}
+
+fn read_only(name: &str) -> bool {
+    flag(name, "ro")
+}
+
+/// Devices the kernel keeps out of /dev, such as the per-controller paths
+/// of an NVMe namespace.
+fn hidden(name: &str) -> bool {
+    read_to_string(format!("/sys/class/block/{}/hidden", name)).is_ok_and(|value| value.trim() == "1")
+}
+
+fn flag(name: &str, attribute: &str) -> bool {
+    read_to_string(format!("/sys/block/{}/{}", name, attribute)).is_ok_and(|value| value.trim() == "1")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+
+    #[test]
+    fn only_connected_nbd_devices_are_drives() {
+        let sys_block = env::temp_dir().join(format!("prettyblk-sys-block-{}", process::id()));
+        fs::create_dir_all(sys_block.join("nbd0")).unwrap();
+        fs::create_dir_all(sys_block.join("nbd1")).unwrap();
+        fs::write(sys_block.join("nbd0/pid"), "4242\n").unwrap();
+        let listed: Vec<&str> = ["nbd0", "nbd1", "loop0", "sr0", "sda"]
+            .into_iter()
+            .filter(|name| !is_pseudo(name, &sys_block))
+            .collect();
+        fs::remove_dir_all(&sys_block).unwrap();
+        assert_eq!(listed, ["nbd0", "sr0", "sda"]);
+    }
+}
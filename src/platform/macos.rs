@@ -0,0 +1,117 @@
+//! Drives from `diskutil list`, mounts from `mount`. These report what
+//! DiskArbitration and getmntinfo know without linking the frameworks.
+
+use std::{collections::HashMap, io, process::Command};
+
+use crate::{Drive, Mount, Partition, PrettyblkError, units};
+
+use super::Platform;
+
+pub struct MacOs;
+
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+/// Parses a `diskutil` size such as `*500.3 GB` into 512-byte sectors.
+fn sectors(identifier: &str, size: &str) -> Result<u64, PrettyblkError> {
+    units::parse_size(&size.trim_start_matches('*').replace(' ', ""))
+        .map(|bytes| bytes / 512)
+        .ok_or_else(|| size_error(identifier, format!("'{}' is not a size", size)))
+}
+
+fn size_error(identifier: &str, message: String) -> PrettyblkError {
+    PrettyblkError::Platform {
+        what: format!("the size of {} from diskutil", identifier),
+        source: io::Error::new(io::ErrorKind::InvalidData, message),
+    }
+}
+
+impl Platform for MacOs {
+    fn drives(&self, mountpoints: &HashMap<String, Mount>) -> Vec<Drive> {
+        let Some(list) = run("diskutil", &["list"]) else {
+            return Vec::new();
+        };
+
+        // Each disk starts with `/dev/disk0 (internal, physical):` followed
+        // by rows `   1:   EFI EFI   314.6 MB   disk0s1`; row 0 is the disk
+        let mut drives: Vec<Drive> = Vec::new();
+        for line in list.lines() {
+            if let Some(header) = line.strip_prefix("/dev/") {
+                let name = header.split_whitespace().next().unwrap_or(header);
+                // Row 0 fills the size in
+                let mut drive = Drive::new(name, 0, Vec::new());
+                drive.size_error = Some(size_error(name, "no row for the whole disk".to_string()));
+                drives.push(drive);
+                continue;
+            }
+            let Some(drive) = drives.last_mut() else {
+                continue;
+            };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let Some(index) = fields.first().and_then(|f| f.strip_suffix(':')) else {
+                continue;
+            };
+            let (Some(identifier), Some(unit), Some(amount)) =
+                (fields.last(), fields.iter().rev().nth(1), fields.iter().rev().nth(2))
+            else {
+                continue;
+            };
+            let (size, size_error) = match sectors(identifier, &format!("{}{}", amount, unit)) {
+                Ok(size) => (size, None),
+                Err(e) => (0, Some(e)),
+            };
+            match index {
+                "0" => {
+                    drive.size = size;
+                    drive.size_error = size_error;
+                    drive.table = match fields.get(1) {
+                        Some(&"GUID_partition_scheme") => Some("gpt"),
+                        Some(&"FDisk_partition_scheme") => Some("dos"),
+                        _ => None,
+                    };
+                }
+                "#" => {}
+                _ => {
+                    let mount = super::mount_of(identifier, mountpoints);
+                    let mut partition = Partition::new(identifier.to_string(), size, mount);
+                    partition.size_error = size_error;
+                    drive.partitions.push(partition);
+                }
+            }
+        }
+        drives
+    }
+
+    fn mounts(&self) -> io::Result<HashMap<String, Mount>> {
+        // `/dev/disk3s1s1 on / (apfs, sealed, local, read-only, journaled)`
+        let entries = run("mount", &[])
+            .ok_or_else(|| io::Error::other("mount failed"))?
+            .lines()
+            .filter_map(|line| {
+                let (source, rest) = line.split_once(" on ")?;
+                let (path, flags) = rest.rsplit_once(" (")?;
+                let mut flags = flags.trim_end_matches(')').split(", ");
+                let fstype = flags.next()?.to_string();
+                let options = flags
+                    .map(|flag| match flag {
+                        "read-only" => "ro".to_string(),
+                        other => other.to_string(),
+                    })
+                    .collect();
+                let mount = Mount {
+                    path: path.to_string(),
+                    fstype,
+                    options,
+                    also: Vec::new(),
+                };
+                Some((source.to_string(), mount))
+            })
+            .collect::<Vec<_>>();
+        Ok(super::mount_table(entries))
+    }
+}
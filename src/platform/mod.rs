@@ -0,0 +1,88 @@
+//! Operating system backends that discover drives, partitions and mounts.
+//! Everything after discovery (usage, rendering, history) is shared.
+
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    io,
+};
+
+use crate::{Drive, Mount};
+
+#[cfg(target_os = "freebsd")]
+mod freebsd;
+// compare and --loops read sysfs directly and report nothing elsewhere
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub mod linux;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(windows)]
+pub mod windows;
+
+pub trait Platform {
+    /// Drives with their partitions and filesystem types, mounted as listed
+    /// in `mountpoints`.
+    fn drives(&self, mountpoints: &HashMap<String, Mount>) -> Vec<Drive>;
+
+    /// Mounted filesystems keyed by their source device path, e.g. `/dev/sda1`
+    /// or `\\.\Harddisk0Partition2`.
+    fn mounts(&self) -> io::Result<HashMap<String, Mount>>;
+}
+
+/// The backend for the operating system prettyblk was built for.
+pub fn native() -> Box<dyn Platform> {
+    #[cfg(target_os = "freebsd")]
+    return Box::new(freebsd::FreeBsd);
+    #[cfg(target_os = "macos")]
+    return Box::new(macos::MacOs);
+    #[cfg(windows)]
+    return Box::new(windows::Windows);
+    #[cfg(not(any(target_os = "freebsd", target_os = "macos", windows)))]
+    return Box::new(linux::Linux);
+}
+
+/// Mounts keyed by source device, from `(source, mount)` pairs in the order
+/// of the system's mount table. A device mounted in several places keeps
+/// the first as its primary path, since bind mounts and further subvolumes
+/// come after it, and the others in `also`.
+fn mount_table(entries: impl IntoIterator<Item = (String, Mount)>) -> HashMap<String, Mount> {
+    let mut table: HashMap<String, Mount> = HashMap::new();
+    for (source, mount) in entries {
+        match table.entry(source) {
+            Entry::Occupied(mut primary) => {
+                let primary = primary.get_mut();
+                if primary.paths().all(|path| path != mount.path) {
+                    primary.also.push(mount.path);
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(mount);
+            }
+        }
+    }
+    table
+}
+
+/// The mount of the device called `name` (or `drive/name`), if any.
+fn mount_of<'a>(name: &str, mountpoints: &'a HashMap<String, Mount>) -> Option<&'a Mount> {
+    let dev_name = name.rsplit('/').next().unwrap_or(name);
+    mountpoints.get(&format!("/dev/{}", dev_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_mounts_of_a_device_are_listed_after_the_first() {
+        let entries = [
+            ("/dev/sda2", Mount::new("/", "btrfs", &["subvol=/@"])),
+            ("/dev/sda2", Mount::new("/home", "btrfs", &["subvol=/@home"])),
+            ("/dev/sda2", Mount::new("/home", "btrfs", &["subvol=/@home"])),
+            ("/dev/sda1", Mount::new("/boot", "ext4", &[])),
+        ];
+        let table = mount_table(entries.map(|(source, mount)| (source.to_string(), mount)));
+        assert_eq!(table["/dev/sda2"].paths().collect::<Vec<_>>(), ["/", "/home"]);
+        assert_eq!(table["/dev/sda2"].options, ["subvol=/@"]);
+        assert_eq!(table["/dev/sda1"].paths().count(), 1);
+    }
+}
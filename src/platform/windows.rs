@@ -0,0 +1,358 @@
+//! Physical disks from `\\.\PhysicalDriveN` and their partition layouts,
+//! volumes from the volume manager. Partitions are named after their NT
+//! device links, e.g. `Harddisk0Partition2`, and a volume is mounted where
+//! it has a drive letter or a folder mount.
+
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io,
+    mem::{self, offset_of},
+    os::windows::{fs::OpenOptionsExt, io::AsRawHandle},
+    ptr,
+};
+
+use windows_sys::Win32::{
+    Foundation::{CloseHandle, ERROR_INSUFFICIENT_BUFFER, ERROR_MORE_DATA, HANDLE, INVALID_HANDLE_VALUE, MAX_PATH},
+    Security::{GetTokenInformation, TOKEN_ELEVATION, TOKEN_QUERY, TokenElevation},
+    Storage::FileSystem::{
+        BusTypeUsb, FILE_DEVICE_DISK, FILE_SHARE_READ, FILE_SHARE_WRITE, FindFirstVolumeW, FindNextVolumeW,
+        FindVolumeClose, GetDiskFreeSpaceExW, GetVolumeInformationW, GetVolumePathNamesForVolumeNameW,
+    },
+    System::{
+        IO::DeviceIoControl,
+        Ioctl::{
+            DISK_GEOMETRY_EX, DRIVE_LAYOUT_INFORMATION_EX, GET_LENGTH_INFORMATION, IOCTL_DISK_GET_DRIVE_GEOMETRY_EX,
+            IOCTL_DISK_GET_DRIVE_LAYOUT_EX, IOCTL_DISK_GET_LENGTH_INFO, IOCTL_STORAGE_GET_DEVICE_NUMBER,
+            IOCTL_STORAGE_QUERY_PROPERTY, PARTITION_INFORMATION_EX, PARTITION_STYLE_GPT, PARTITION_STYLE_MBR,
+            PropertyStandardQuery, STORAGE_DEVICE_DESCRIPTOR, STORAGE_DEVICE_NUMBER, STORAGE_PROPERTY_QUERY,
+            StorageDeviceProperty,
+        },
+        SystemServices::FILE_READ_ONLY_VOLUME,
+        Threading::{GetCurrentProcess, OpenProcessToken},
+    },
+};
+
+use crate::{Drive, Mount, Partition, source::FsStat};
+
+use super::Platform;
+
+pub struct Windows;
+
+/// Physical drive numbers are not reused densely after hot-unplugging, so
+/// this many are tried rather than stopping at the first gap.
+const MAX_DRIVES: u32 = 64;
+
+fn wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain([0]).collect()
+}
+
+/// The string in `buffer` up to its first NUL.
+fn from_wide(buffer: &[u16]) -> String {
+    let end = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    String::from_utf16_lossy(&buffer[..end])
+}
+
+/// Opens a device such as `\\.\PhysicalDrive0` for queries only, which
+/// needs no administrator rights.
+fn open(path: &str) -> io::Result<File> {
+    OpenOptions::new()
+        .access_mode(0)
+        .share_mode(FILE_SHARE_READ | FILE_SHARE_WRITE)
+        .open(path)
+}
+
+/// Sends the control code `code` to `device` and returns its answer,
+/// retrying with a larger buffer than `size` while the driver asks for one.
+fn ioctl(device: &File, code: u32, input: &[u8], mut size: usize) -> io::Result<Vec<u8>> {
+    loop {
+        let mut output = vec![0u8; size];
+        let mut returned = 0;
+        // SAFETY: both buffers outlive the call, which completes before returning
+        let ok = unsafe {
+            DeviceIoControl(
+                device.as_raw_handle() as HANDLE,
+                code,
+                input.as_ptr().cast(),
+                input.len() as u32,
+                output.as_mut_ptr().cast(),
+                size as u32,
+                &mut returned,
+                ptr::null_mut(),
+            )
+        };
+        if ok != 0 {
+            output.truncate(returned as usize);
+            return Ok(output);
+        }
+        let error = io::Error::last_os_error();
+        let more = [ERROR_INSUFFICIENT_BUFFER, ERROR_MORE_DATA].map(|code| Some(code as i32));
+        if !more.contains(&error.raw_os_error()) || size >= 1 << 20 {
+            return Err(error);
+        }
+        size *= 4;
+    }
+}
+
+/// The `T` at `offset` in an ioctl answer, if it is long enough.
+fn read<T: Copy>(bytes: &[u8], offset: usize) -> Option<T> {
+    let end = offset.checked_add(mem::size_of::<T>())?;
+    // SAFETY: the range is in bounds and T is a plain C struct
+    (end <= bytes.len()).then(|| unsafe { ptr::read_unaligned(bytes[offset..].as_ptr().cast::<T>()) })
+}
+
+/// The ASCII string at `offset` of a storage descriptor, trimmed of the
+/// padding drives report; offset 0 means the field is absent.
+fn descriptor_string(bytes: &[u8], offset: u32) -> Option<String> {
+    let field = bytes.get(offset as usize..).filter(|_| offset != 0)?;
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    let text = String::from_utf8_lossy(&field[..end]).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Model, bus and removability from the storage descriptor.
+fn describe(device: &File, drive: &mut Drive) {
+    // SAFETY: an all-zero query is a valid C struct
+    let mut query: STORAGE_PROPERTY_QUERY = unsafe { mem::zeroed() };
+    query.PropertyId = StorageDeviceProperty;
+    query.QueryType = PropertyStandardQuery;
+    // SAFETY: the query is a plain C struct, viewed as its bytes
+    let input = unsafe {
+        std::slice::from_raw_parts(
+            (&query as *const STORAGE_PROPERTY_QUERY).cast::<u8>(),
+            mem::size_of_val(&query),
+        )
+    };
+    let Ok(bytes) = ioctl(device, IOCTL_STORAGE_QUERY_PROPERTY, input, 1024) else {
+        return;
+    };
+    let Some(descriptor) = read::<STORAGE_DEVICE_DESCRIPTOR>(&bytes, 0) else {
+        return;
+    };
+    let model: Vec<String> = [descriptor.VendorIdOffset, descriptor.ProductIdOffset]
+        .into_iter()
+        .filter_map(|offset| descriptor_string(&bytes, offset))
+        .collect();
+    drive.model = (!model.is_empty()).then(|| model.join(" "));
+    drive.usb = descriptor.BusType == BusTypeUsb;
+    drive.removable = descriptor.RemovableMedia != 0;
+}
+
+/// One physical drive and the partitions of its layout.
+fn drive(number: u32, mountpoints: &HashMap<String, Mount>) -> Option<Drive> {
+    let name = format!("PhysicalDrive{}", number);
+    let device = open(&format!(r"\\.\{}", name)).ok()?;
+    let geometry = ioctl(
+        &device,
+        IOCTL_DISK_GET_DRIVE_GEOMETRY_EX,
+        &[],
+        mem::size_of::<DISK_GEOMETRY_EX>(),
+    )
+    .ok()?;
+    let geometry: DISK_GEOMETRY_EX = read(&geometry, 0)?;
+    let mut drive = Drive::new(&name, geometry.DiskSize as u64 / 512, Vec::new());
+    describe(&device, &mut drive);
+
+    let entry_size = mem::size_of::<PARTITION_INFORMATION_EX>();
+    let size = mem::size_of::<DRIVE_LAYOUT_INFORMATION_EX>() + 15 * entry_size;
+    let Ok(layout) = ioctl(&device, IOCTL_DISK_GET_DRIVE_LAYOUT_EX, &[], size) else {
+        return Some(drive);
+    };
+    let Some(header) = read::<DRIVE_LAYOUT_INFORMATION_EX>(&layout, 0) else {
+        return Some(drive);
+    };
+    drive.table = match header.PartitionStyle as i32 {
+        PARTITION_STYLE_GPT => Some("gpt"),
+        PARTITION_STYLE_MBR => Some("dos"),
+        _ => None,
+    };
+    let first = offset_of!(DRIVE_LAYOUT_INFORMATION_EX, PartitionEntry);
+    for index in 0..header.PartitionCount as usize {
+        let Some(entry) = read::<PARTITION_INFORMATION_EX>(&layout, first + index * entry_size) else {
+            break;
+        };
+        // MBR layouts list their four primary slots even when empty, and
+        // extended partitions as containers without a number
+        if entry.PartitionNumber == 0 || entry.PartitionLength == 0 {
+            continue;
+        }
+        let partition_name = format!("Harddisk{}Partition{}", number, entry.PartitionNumber);
+        let mount = mountpoints.get(&format!(r"\\.\{}", partition_name));
+        let mut partition = Partition::new(
+            format!("{}/{}", name, partition_name),
+            entry.PartitionLength as u64 / 512,
+            mount,
+        );
+        partition.start = Some(entry.StartingOffset as u64 / 512);
+        drive.partitions.push(partition);
+    }
+    Some(drive)
+}
+
+/// The GUID paths of all volumes, e.g. `\\?\Volume{6f1c...}\`.
+fn volumes() -> Vec<String> {
+    let mut name = [0u16; MAX_PATH as usize];
+    // SAFETY: the buffer length is passed along with it
+    let find = unsafe { FindFirstVolumeW(name.as_mut_ptr(), name.len() as u32) };
+    if find == INVALID_HANDLE_VALUE {
+        return Vec::new();
+    }
+    let mut volumes = Vec::new();
+    loop {
+        volumes.push(from_wide(&name));
+        // SAFETY: as above, with the handle FindFirstVolumeW returned
+        if unsafe { FindNextVolumeW(find, name.as_mut_ptr(), name.len() as u32) } == 0 {
+            break;
+        }
+    }
+    // SAFETY: closes the search handle once
+    unsafe { FindVolumeClose(find) };
+    volumes
+}
+
+/// Where the volume is mounted: drive letters first, then folders.
+fn path_names(volume: &[u16]) -> Vec<String> {
+    let mut buffer = vec![0u16; MAX_PATH as usize];
+    loop {
+        let mut needed = 0;
+        // SAFETY: the buffer length is passed along with it
+        let ok = unsafe {
+            GetVolumePathNamesForVolumeNameW(volume.as_ptr(), buffer.as_mut_ptr(), buffer.len() as u32, &mut needed)
+        };
+        if ok != 0 {
+            break;
+        }
+        if io::Error::last_os_error().raw_os_error() != Some(ERROR_MORE_DATA as i32) {
+            return Vec::new();
+        }
+        buffer = vec![0; needed as usize];
+    }
+    // A list of NUL-terminated strings ended by an empty one
+    let mut paths: Vec<String> = buffer
+        .split(|&c| c == 0)
+        .take_while(|path| !path.is_empty())
+        .map(String::from_utf16_lossy)
+        .collect();
+    paths.sort_by_key(|path| path.len() > 3);
+    paths
+}
+
+/// The mount of one volume, keyed by the partition it lives on, e.g.
+/// `\\.\Harddisk0Partition2`. Volumes spanning disks, optical media and
+/// volumes without a drive letter or folder are left out.
+fn mount(volume: &str) -> Option<(String, Mount)> {
+    let name = wide(volume);
+    // A volume can be a drive letter and folder mounts at once
+    let mut paths = path_names(&name).into_iter();
+    let path = paths.next()?;
+    let mut fstype = [0u16; MAX_PATH as usize];
+    let mut flags = 0;
+    // SAFETY: the file system name buffer length is passed along with it
+    let ok = unsafe {
+        GetVolumeInformationW(
+            name.as_ptr(),
+            ptr::null_mut(),
+            0,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            &mut flags,
+            fstype.as_mut_ptr(),
+            fstype.len() as u32,
+        )
+    };
+    if ok == 0 {
+        return None;
+    }
+
+    // Opening the volume itself takes its path without the trailing backslash
+    let device = open(volume.trim_end_matches('\\')).ok()?;
+    let number = ioctl(
+        &device,
+        IOCTL_STORAGE_GET_DEVICE_NUMBER,
+        &[],
+        mem::size_of::<STORAGE_DEVICE_NUMBER>(),
+    )
+    .ok()?;
+    let number: STORAGE_DEVICE_NUMBER = read(&number, 0)?;
+    if number.DeviceType != FILE_DEVICE_DISK {
+        return None;
+    }
+    let source = format!(
+        r"\\.\Harddisk{}Partition{}",
+        number.DeviceNumber, number.PartitionNumber
+    );
+    let options = match flags & FILE_READ_ONLY_VOLUME {
+        0 => ["rw"],
+        _ => ["ro"],
+    };
+    let mut mount = Mount::new(&path, &from_wide(&fstype).to_lowercase(), &options);
+    mount.also = paths.collect();
+    Some((source, mount))
+}
+
+impl Platform for Windows {
+    fn drives(&self, mountpoints: &HashMap<String, Mount>) -> Vec<Drive> {
+        (0..MAX_DRIVES)
+            .filter_map(|number| drive(number, mountpoints))
+            .collect()
+    }
+
+    fn mounts(&self) -> io::Result<HashMap<String, Mount>> {
+        Ok(volumes().iter().filter_map(|volume| mount(volume)).collect())
+    }
+}
+
+/// The statvfs equivalent for a mounted volume, in bytes. Windows keeps no
+/// inode counts, so those stay zero.
+pub fn statvfs(mountpoint: &str) -> io::Result<FsStat> {
+    let (mut total, mut free) = (0, 0);
+    // SAFETY: the path is NUL-terminated and both outputs are valid
+    let ok = unsafe { GetDiskFreeSpaceExW(wide(mountpoint).as_ptr(), ptr::null_mut(), &mut total, &mut free) };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(FsStat {
+        blocks: total,
+        blocks_free: free,
+        block_size: 1,
+        ..FsStat::default()
+    })
+}
+
+/// Size in bytes of an open disk or partition, which seeking to the end
+/// does not tell on Windows.
+pub fn length(device: &File) -> io::Result<u64> {
+    let answer = ioctl(
+        device,
+        IOCTL_DISK_GET_LENGTH_INFO,
+        &[],
+        mem::size_of::<GET_LENGTH_INFORMATION>(),
+    )?;
+    read::<GET_LENGTH_INFORMATION>(&answer, 0)
+        .map(|info| info.Length as u64)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "short answer to IOCTL_DISK_GET_LENGTH_INFO"))
+}
+
+/// Whether the process runs elevated, the Windows counterpart of root.
+pub fn elevated() -> bool {
+    let mut token = ptr::null_mut();
+    // SAFETY: the pseudo handle of the current process needs no closing
+    if unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) } == 0 {
+        return false;
+    }
+    let mut elevation = TOKEN_ELEVATION { TokenIsElevated: 0 };
+    let mut returned = 0;
+    // SAFETY: the output is a TOKEN_ELEVATION of the size passed
+    let ok = unsafe {
+        GetTokenInformation(
+            token,
+            TokenElevation,
+            (&mut elevation as *mut TOKEN_ELEVATION).cast(),
+            mem::size_of::<TOKEN_ELEVATION>() as u32,
+            &mut returned,
+        )
+    };
+    // SAFETY: the token was opened above and is closed once
+    unsafe { CloseHandle(token) };
+    ok != 0 && elevation.TokenIsElevated != 0
+}
@@ -0,0 +1,87 @@
+use crate::glyph;
+
+const BRAILLE_BASE: u32 = 0x2800;
+
+// Bit for each dot of a braille cell, indexed by [row][column]
+const DOT_BITS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Plots `points` as a line chart of `width` x `height` braille cells, or
+/// asterisks where only ASCII can be shown.
+/// X values are mapped onto `x_range` and Y values onto `y_range`; rows are
+/// returned top to bottom.
+pub fn braille_chart(
+    points: &[(f64, f64)],
+    x_range: (f64, f64),
+    y_range: (f64, f64),
+    width: usize,
+    height: usize,
+) -> Vec<String> {
+    let dots_x = width * 2;
+    let dots_y = height * 4;
+    let mut cells = vec![vec![0u8; width]; height];
+
+    if dots_x == 0 || dots_y == 0 {
+        return Vec::new();
+    }
+
+    let scale = |value: f64, (min, max): (f64, f64), dots: usize| {
+        let span = if max > min { max - min } else { 1.0 };
+        let ratio = ((value - min) / span).clamp(0.0, 1.0);
+        (ratio * (dots - 1) as f64).round() as i64
+    };
+
+    let mut set = |x: i64, y: i64| {
+        // y grows upwards on the chart but downwards in the cell grid
+        let row = dots_y as i64 - 1 - y;
+        let (cx, cy) = (x as usize / 2, row as usize / 4);
+        cells[cy][cx] |= DOT_BITS[row as usize % 4][x as usize % 2];
+    };
+
+    let scaled: Vec<(i64, i64)> = points
+        .iter()
+        .map(|&(x, y)| (scale(x, x_range, dots_x), scale(y, y_range, dots_y)))
+        .collect();
+
+    for (i, &(x, y)) in scaled.iter().enumerate() {
+        match scaled.get(i + 1) {
+            Some(&(nx, ny)) => draw_line((x, y), (nx, ny), &mut set),
+            None => set(x, y),
+        }
+    }
+
+    let braille = glyph::glyphs().braille;
+    cells
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&bits| match (braille, bits) {
+                    (true, _) => char::from_u32(BRAILLE_BASE + bits as u32).unwrap_or(' '),
+                    (false, 0) => ' ',
+                    (false, _) => '*',
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn draw_line((x0, y0): (i64, i64), (x1, y1): (i64, i64), set: &mut impl FnMut(i64, i64)) {
+    let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+    let (sx, sy) = ((x1 - x0).signum(), (y1 - y0).signum());
+    let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+
+    loop {
+        set(x, y);
+        if x == x1 && y == y1 {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
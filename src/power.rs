@@ -0,0 +1,29 @@
+use std::{fs::read_dir, fs::read_to_string, path::Path};
+
+fn attribute(supply: &Path, name: &str) -> String {
+    read_to_string(supply.join(name))
+        .map(|value| value.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Whether the machine runs on battery: a system battery is discharging and
+/// no mains or USB charger is online, per `/sys/class/power_supply`.
+/// Desktops, and platforms without that directory, are never on battery.
+pub fn on_battery() -> bool {
+    let Ok(entries) = read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+    let (mut charger, mut discharging) = (false, false);
+    for entry in entries.filter_map(Result::ok) {
+        let supply = entry.path();
+        match attribute(&supply, "type").as_str() {
+            "Mains" | "USB" => charger |= attribute(&supply, "online") == "1",
+            // Wireless mice and headsets report their batteries with scope "Device"
+            "Battery" if attribute(&supply, "scope") != "Device" => {
+                discharging |= attribute(&supply, "status") == "Discharging";
+            }
+            _ => {}
+        }
+    }
+    discharging && !charger
+}
@@ -0,0 +1,107 @@
+//! Remembers what a scan could not read for lack of privileges, so a run as
+//! an ordinary user explains once what a privileged run would add instead of
+//! quietly showing less.
+
+use std::{io, sync::Mutex};
+
+#[cfg(unix)]
+use nix::{errno::Errno, unistd::geteuid};
+
+/// What was left out, each with the devices or mountpoints it was left out
+/// for, in the order first refused.
+static DENIED: Mutex<Vec<(String, Vec<String>)>> = Mutex::new(Vec::new());
+
+/// Records that `what`, e.g. `dm-cache statistics`, was left out because
+/// the kernel refused access.
+pub fn deny(what: impl Into<String>) {
+    record(what.into(), None);
+}
+
+/// Records that `what`, e.g. `partition types`, was left out for `on`, a
+/// device or mountpoint.
+pub fn deny_on(what: &str, on: &str) {
+    record(what.to_string(), Some(on.to_string()));
+}
+
+fn record(what: String, on: Option<String>) {
+    let mut denied = DENIED.lock().unwrap_or_else(|e| e.into_inner());
+    let index = match denied.iter().position(|(denied, _)| *denied == what) {
+        Some(index) => index,
+        None => {
+            denied.push((what, Vec::new()));
+            denied.len() - 1
+        }
+    };
+    let places = &mut denied[index].1;
+    if let Some(on) = on.filter(|on| !places.contains(on)) {
+        places.push(on);
+    }
+}
+
+/// `partition types (sda, sdb), dm-cache statistics`
+fn describe(denied: &[(String, Vec<String>)]) -> String {
+    let items: Vec<String> = denied
+        .iter()
+        .map(|(what, places)| match places.is_empty() {
+            true => what.clone(),
+            false => format!("{} ({})", what, places.join(", ")),
+        })
+        .collect();
+    items.join(", ")
+}
+
+/// Whether `error` means permission was refused rather than that the thing
+/// does not exist.
+pub fn refused(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::PermissionDenied
+}
+
+/// Running as root, or elevated on Windows.
+pub fn is_root() -> bool {
+    #[cfg(unix)]
+    return geteuid().is_root();
+    #[cfg(windows)]
+    return crate::platform::windows::elevated();
+}
+
+/// The errno form of [`refused`], for nix calls.
+#[cfg(unix)]
+pub fn refused_errno(errno: Errno) -> bool {
+    matches!(errno, Errno::EACCES | Errno::EPERM)
+}
+
+/// Prints one line listing everything left out since the last hint, and
+/// where, so it is clear what a privileged run would add.
+pub fn hint() {
+    let denied = std::mem::take(&mut *DENIED.lock().unwrap_or_else(|e| e.into_inner()));
+    if denied.is_empty() {
+        return;
+    }
+    let advice = match is_root() {
+        true => "access was denied even as root, e.g. by a container or security policy",
+        false if cfg!(windows) => "run from an elevated prompt to see them",
+        false => "run with sudo to see them",
+    };
+    warn!(
+        "pblk: not shown without more privileges: {}; {}",
+        describe(&denied),
+        advice
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn denials_are_grouped_by_what_was_left_out() {
+        let denied = [
+            (
+                "partition types".to_string(),
+                vec!["sda".to_string(), "sdb".to_string()],
+            ),
+            ("dm-cache statistics".to_string(), Vec::new()),
+        ];
+        assert_eq!(describe(&denied), "partition types (sda, sdb), dm-cache statistics");
+    }
+}
@@ -0,0 +1,309 @@
+use std::{
+    collections::HashMap,
+    fs::{File, read_to_string},
+    io::{self, Read, Seek, SeekFrom},
+};
+
+use crate::{Drive, privilege, udev};
+
+/// A partition table entry as recorded on disk.
+pub struct Entry {
+    pub type_name: String,
+    pub flags: Vec<&'static str>,
+    /// First sector and length, in 512-byte sectors
+    pub start: u64,
+    pub sectors: u64,
+}
+
+pub struct Table {
+    /// `gpt` or `dos`, matching udev's `ID_PART_TABLE_TYPE`
+    pub kind: &'static str,
+    pub entries: HashMap<u32, Entry>,
+}
+
+const GPT_TYPES: [(&str, &str); 16] = [
+    ("C12A7328-F81F-11D2-BA4B-00A0C93EC93B", "EFI System"),
+    ("21686148-6449-6E6F-744E-656564454649", "BIOS boot"),
+    ("0FC63DAF-8483-4772-8E79-3D69D8477DE4", "Linux filesystem"),
+    ("4F68BCE3-E8CD-4DB1-96E7-FBCAF984B709", "Linux root (x86-64)"),
+    ("B921B045-1DF0-41C3-AF44-4C6F280D3FAE", "Linux root (ARM64)"),
+    ("BC13C2FF-59E6-4262-A352-B275FD6F7172", "Linux extended boot"),
+    ("933AC7E1-2EB4-4F13-B844-0E14E2AEF915", "Linux home"),
+    ("0657FD6D-A4AB-43C4-84E5-0933C84B4F4F", "Linux swap"),
+    ("E6D6D379-F507-44C2-A23C-238F2A3DF928", "Linux LVM"),
+    ("A19D880F-05FC-4D3B-A006-743F0F84911E", "Linux RAID"),
+    ("CA7D7CCB-63ED-4C53-861C-1742536059CC", "Linux LUKS"),
+    ("EBD0A0A2-B9E5-4433-87C0-68B6B72699C7", "Microsoft basic data"),
+    ("E3C9E316-0B5C-4DB8-817D-F92DF00215AE", "Microsoft reserved"),
+    ("DE94BBA4-06D1-4D40-A16A-BFD50179D6AC", "Windows recovery"),
+    ("6A898CC3-1DD2-11B2-99A6-080020736631", "ZFS"),
+    ("48465300-0000-11AA-AA11-00306543ECAC", "Apple HFS+"),
+];
+
+const MBR_TYPES: [(u8, &str); 14] = [
+    (0x01, "FAT12"),
+    (0x05, "Extended"),
+    (0x06, "FAT16"),
+    (0x07, "NTFS/exFAT"),
+    (0x0b, "FAT32"),
+    (0x0c, "FAT32 (LBA)"),
+    (0x0f, "Extended (LBA)"),
+    (0x27, "Windows recovery"),
+    (0x82, "Linux swap"),
+    (0x83, "Linux"),
+    (0x85, "Linux extended"),
+    (0x8e, "Linux LVM"),
+    (0xef, "EFI System"),
+    (0xfd, "Linux RAID"),
+];
+
+const GPT_ATTRIBUTES: [(u32, &str); 5] = [
+    (0, "required"),
+    (2, "legacy_boot"),
+    (60, "read-only"),
+    (62, "hidden"),
+    (63, "no-automount"),
+];
+
+fn le_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap_or_default())
+}
+
+fn le_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().unwrap_or_default())
+}
+
+/// Formats an on-disk GUID; the first three groups are stored little-endian.
+pub fn format_guid(bytes: &[u8]) -> String {
+    format!(
+        "{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{}",
+        le_u32(&bytes[0..4]),
+        u16::from_le_bytes([bytes[4], bytes[5]]),
+        u16::from_le_bytes([bytes[6], bytes[7]]),
+        bytes[8],
+        bytes[9],
+        bytes[10..16].iter().map(|b| format!("{:02X}", b)).collect::<String>()
+    )
+}
+
+pub fn gpt_type_name(guid: &str) -> String {
+    GPT_TYPES
+        .iter()
+        .find(|(g, _)| g.eq_ignore_ascii_case(guid))
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| guid.to_lowercase())
+}
+
+pub fn mbr_type_name(code: u8) -> String {
+    MBR_TYPES
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| format!("0x{:02x}", code))
+}
+
+fn read_at(file: &mut File, offset: u64, len: usize) -> Option<Vec<u8>> {
+    let mut buffer = vec![0u8; len];
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    file.read_exact(&mut buffer).ok()?;
+    Some(buffer)
+}
+
+fn parse_gpt(file: &mut File, sector: u64) -> Option<Table> {
+    let header = read_at(file, sector, 92)?;
+    if &header[0..8] != b"EFI PART" {
+        return None;
+    }
+    let entries_lba = le_u64(&header[72..80]);
+    let count = le_u32(&header[80..84]).min(1024) as usize;
+    let entry_size = le_u32(&header[84..88]) as usize;
+    // Images are untrusted: a size outside what any tool writes would have
+    // the entry array read below take gigabytes
+    if !(128..=4096).contains(&entry_size) || !entry_size.is_multiple_of(8) {
+        return None;
+    }
+
+    let raw = read_at(file, entries_lba.checked_mul(sector)?, count * entry_size)?;
+    let scale = sector / 512;
+    let entries = raw
+        .chunks_exact(entry_size)
+        .enumerate()
+        .filter(|(_, entry)| entry[0..16].iter().any(|&b| b != 0))
+        .map(|(i, entry)| {
+            let attributes = le_u64(&entry[48..56]);
+            let flags = GPT_ATTRIBUTES
+                .iter()
+                .filter(|(bit, _)| attributes & (1 << bit) != 0)
+                .map(|(_, name)| *name)
+                .collect();
+            let (first, last) = (le_u64(&entry[32..40]), le_u64(&entry[40..48]));
+            let parsed = Entry {
+                type_name: gpt_type_name(&format_guid(&entry[0..16])),
+                flags,
+                start: first.checked_mul(scale)?,
+                sectors: last.checked_add(1)?.saturating_sub(first).checked_mul(scale)?,
+            };
+            Some((i as u32 + 1, parsed))
+        })
+        .collect::<Option<_>>()?;
+
+    Some(Table { kind: "gpt", entries })
+}
+
+fn parse_mbr(file: &mut File, mbr: &[u8], sector: u64) -> Table {
+    let mut entries = HashMap::new();
+    let scale = sector / 512;
+    // Logical partitions count their start from their own boot record
+    let record = |slot: &[u8], base: u64| {
+        let extended = matches!(slot[4], 0x05 | 0x0f | 0x85);
+        Entry {
+            type_name: mbr_type_name(slot[4]),
+            flags: if slot[0] == 0x80 { vec!["boot"] } else { Vec::new() },
+            start: (base + le_u32(&slot[8..12]) as u64) * scale,
+            // Like the kernel, extended partitions get 1 KiB so they do not
+            // count the logical partitions inside them a second time
+            sectors: if extended {
+                2
+            } else {
+                le_u32(&slot[12..16]) as u64 * scale
+            },
+        }
+    };
+
+    let mut extended = None;
+    for i in 0..4 {
+        let slot = &mbr[446 + i * 16..462 + i * 16];
+        if slot[4] == 0 {
+            continue;
+        }
+        if matches!(slot[4], 0x05 | 0x0f | 0x85) {
+            extended = Some(le_u32(&slot[8..12]) as u64);
+        }
+        entries.insert(i as u32 + 1, record(slot, 0));
+    }
+
+    // Logical partitions form a chain of extended boot records
+    if let Some(base) = extended {
+        let mut ebr_lba = base;
+        let mut number = 5;
+        while number < 64 {
+            let Some(ebr) = read_at(file, ebr_lba * sector, 512) else {
+                break;
+            };
+            if ebr[510..512] != [0x55, 0xaa] {
+                break;
+            }
+            let logical = &ebr[446..462];
+            if logical[4] != 0 {
+                entries.insert(number, record(logical, ebr_lba));
+                number += 1;
+            }
+            let next = le_u32(&ebr[462 + 8..462 + 12]) as u64;
+            if next == 0 {
+                break;
+            }
+            ebr_lba = base + next;
+        }
+    }
+
+    Table { kind: "dos", entries }
+}
+
+/// Reads the partition table of `/dev/<drive>` directly. Fails when the
+/// device node cannot be opened, which is not the same as having no table.
+fn read_table(drive: &str) -> io::Result<Option<Table>> {
+    let mut file = File::open(format!("/dev/{}", drive))?;
+    let sector = read_to_string(format!("/sys/block/{}/queue/logical_block_size", drive))
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(512);
+    Ok(parse(&mut file, sector))
+}
+
+/// Reads the partition table of a disk image, whose sector size is only
+/// known from where the GPT header turns up.
+pub fn read_image(file: &mut File) -> Option<Table> {
+    parse(file, 512).or_else(|| parse(file, 4096))
+}
+
+fn parse(file: &mut File, sector: u64) -> Option<Table> {
+    let mbr = read_at(file, 0, 512)?;
+    if mbr[510..512] != [0x55, 0xaa] {
+        return None;
+    }
+    let protective = (0..4).any(|i| mbr[446 + i * 16 + 4] == 0xee);
+    if protective {
+        parse_gpt(file, sector)
+    } else {
+        Some(parse_mbr(file, &mbr, sector))
+    }
+}
+
+/// The same information from udev's blkid probe, for when the device node
+/// cannot be opened.
+fn udev_entry(sysfs_dir: &str) -> Option<(String, Entry)> {
+    let props = udev::properties(sysfs_dir);
+    let scheme = props.get("ID_PART_ENTRY_SCHEME")?.clone();
+    let raw_type = props.get("ID_PART_ENTRY_TYPE")?;
+    let type_name = match raw_type.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16)
+            .map(mbr_type_name)
+            .unwrap_or_else(|_| raw_type.clone()),
+        None => gpt_type_name(raw_type),
+    };
+    // Entries without attributes leave the property out
+    let flags_value = match props.get("ID_PART_ENTRY_FLAGS") {
+        Some(flags) => u64::from_str_radix(flags.trim_start_matches("0x"), 16).ok()?,
+        None => 0,
+    };
+    let flags = match scheme.as_str() {
+        "dos" if flags_value & 0x80 != 0 => vec!["boot"],
+        "gpt" => GPT_ATTRIBUTES
+            .iter()
+            .filter(|(bit, _)| flags_value & (1 << bit) != 0)
+            .map(|(_, name)| *name)
+            .collect(),
+        _ => Vec::new(),
+    };
+    let sectors = |key: &str| props.get(key)?.parse().ok();
+    let entry = Entry {
+        type_name,
+        flags,
+        start: sectors("ID_PART_ENTRY_OFFSET")?,
+        sectors: sectors("ID_PART_ENTRY_SIZE")?,
+    };
+    Some((scheme, entry))
+}
+
+pub fn partition_number(drive: &str, partition: &str) -> Option<u32> {
+    read_to_string(format!("/sys/block/{}/{}/partition", drive, partition))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+pub fn annotate(drives: &mut [Drive]) {
+    for drive in drives.iter_mut() {
+        let (mut table, refused) = match read_table(&drive.name) {
+            Ok(table) => (table, false),
+            Err(e) => (None, privilege::refused(&e)),
+        };
+        drive.table = table.as_ref().map(|t| t.kind);
+
+        // Devices stacked on partitions have no table entry of their own
+        for partition in drive.partitions.iter_mut().filter(|p| p.depth == 0) {
+            let entry = partition_number(&drive.name, partition.dev_name())
+                .and_then(|n| table.as_mut()?.entries.remove(&n))
+                .or_else(|| {
+                    let (scheme, entry) = udev_entry(&partition.sysfs_dir())?;
+                    drive.table.get_or_insert(if scheme == "gpt" { "gpt" } else { "dos" });
+                    Some(entry)
+                });
+            if entry.is_none() && refused {
+                privilege::deny_on("partition types", &drive.name);
+            }
+            partition.table_entry = entry;
+        }
+    }
+}
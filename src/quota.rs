@@ -0,0 +1,99 @@
+use std::process::Command;
+
+use crate::{Drive, privilege};
+
+/// Mount options that enable user, group or project quota accounting on
+/// ext4, xfs and the legacy `quota` tools.
+const QUOTA_OPTIONS: [&str; 9] = [
+    "quota",
+    "usrquota",
+    "grpquota",
+    "prjquota",
+    "uquota",
+    "gquota",
+    "pquota",
+    "usrjquota=",
+    "grpjquota=",
+];
+
+/// How many consumers are listed per kind of quota.
+pub const TOP: usize = 5;
+
+/// Space charged to one user, group or project, in bytes.
+pub struct Consumer {
+    pub name: String,
+    pub used: u64,
+}
+
+/// The largest consumers of one kind of quota on a filesystem.
+pub struct Report {
+    /// "users", "groups" or "projects"
+    pub kind: &'static str,
+    pub consumers: Vec<Consumer>,
+}
+
+fn enabled(options: &[String]) -> bool {
+    options.iter().any(|option| {
+        QUOTA_OPTIONS.iter().any(|quota| match quota.ends_with('=') {
+            true => option.starts_with(quota),
+            false => option == quota,
+        })
+    })
+}
+
+/// Parses `repquota` output: a dashed rule followed by one line per id
+/// with the name, the limit flags and the used space in KiB.
+fn parse(output: &str) -> Vec<Consumer> {
+    let mut consumers: Vec<Consumer> = output
+        .lines()
+        .skip_while(|line| !line.starts_with("---"))
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [name, _flags, used, ..] = fields.as_slice() else {
+                return None;
+            };
+            let used: u64 = used.parse().ok()?;
+            Some(Consumer {
+                name: name.trim_start_matches('#').to_string(),
+                used: used * 1024,
+            })
+        })
+        .filter(|consumer| consumer.used > 0)
+        .collect();
+    consumers.sort_by(|a, b| b.used.cmp(&a.used).then_with(|| a.name.cmp(&b.name)));
+    consumers.truncate(TOP);
+    consumers
+}
+
+fn report(kind: &'static str, flag: &str, mountpoint: &str) -> Option<Report> {
+    let output = Command::new("repquota").args([flag, mountpoint]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let consumers = parse(&String::from_utf8_lossy(&output.stdout));
+    (!consumers.is_empty()).then_some(Report { kind, consumers })
+}
+
+/// Looks up the top quota consumers of every mounted filesystem that has
+/// quota accounting enabled. Needs root and the `repquota` tool.
+pub fn annotate(drives: &mut [Drive]) {
+    for partition in drives.iter_mut().flat_map(|d| d.partitions.iter_mut()) {
+        let Some(mountpoint) = &partition.mountpoint else {
+            continue;
+        };
+        if !enabled(&partition.mount_options) {
+            continue;
+        }
+        partition.quota = [("users", "-u"), ("groups", "-g"), ("projects", "-P")]
+            .iter()
+            .filter_map(|(kind, flag)| report(kind, flag, mountpoint))
+            .collect();
+        if partition.quota.is_empty() {
+            match privilege::is_root() {
+                true => warn!("pblk: cannot read quotas of {} with repquota", mountpoint),
+                false => privilege::deny_on("quotas", mountpoint),
+            }
+        }
+    }
+}
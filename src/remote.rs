@@ -0,0 +1,238 @@
+use std::{
+    fmt,
+    fs::{canonicalize, read_dir, read_to_string},
+    path::Path,
+};
+
+/// Where a network block device gets its data from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Target {
+    Iscsi {
+        /// The target's IQN, e.g. `iqn.2003-01.org.example:storage.lun1`
+        name: String,
+        /// `address:port` of the session's connection
+        portal: Option<String>,
+        lun: Option<u64>,
+    },
+    Nbd {
+        /// `host:port`, a unix socket or the image qemu-nbd serves
+        server: String,
+        export: Option<String>,
+    },
+}
+
+impl Target {
+    /// The transport, for the drive header.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Target::Iscsi { .. } => "iSCSI",
+            Target::Nbd { .. } => "NBD",
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Target::Iscsi { name, portal, lun } => {
+                write!(f, "{}", name)?;
+                if let Some(portal) = portal {
+                    write!(f, " at {}", portal)?;
+                }
+                match lun {
+                    Some(lun) => write!(f, ", LUN {}", lun),
+                    None => Ok(()),
+                }
+            }
+            Target::Nbd { server, export } => match export {
+                Some(export) => write!(f, "{}, export {}", server, export),
+                None => write!(f, "{}", server),
+            },
+        }
+    }
+}
+
+fn attribute(path: &Path) -> Option<String> {
+    read_to_string(path)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// The remote target behind `/dev/<name>`, if it is an iSCSI LUN or an NBD
+/// device that is connected.
+pub fn target(name: &str) -> Option<Target> {
+    match name.starts_with("nbd") {
+        true => nbd(name),
+        false => iscsi(name),
+    }
+}
+
+/// SCSI disks of an iSCSI session sit below its `sessionN` device, whose
+/// `iscsi_session` class directory names the target, and whose connection
+/// knows the portal it was logged in through.
+fn iscsi(name: &str) -> Option<Target> {
+    let device = canonicalize(format!("/sys/block/{}/device", name)).ok()?;
+    let session = device.ancestors().find(|path| {
+        path.file_name()
+            .is_some_and(|n| n.to_string_lossy().starts_with("session"))
+    })?;
+    let id = session.file_name()?.to_string_lossy();
+    let target = attribute(&session.join(format!("iscsi_session/{}/targetname", id)))?;
+
+    let portal = read_dir(session).ok()?.filter_map(Result::ok).find_map(|entry| {
+        let connection = entry.file_name().to_string_lossy().into_owned();
+        if !connection.starts_with("connection") {
+            return None;
+        }
+        let class = entry.path().join(format!("iscsi_connection/{}", connection));
+        let address = attribute(&class.join("persistent_address"))?;
+        let port = attribute(&class.join("persistent_port"));
+        Some(match port {
+            // IPv6 portals are written bracketed, like iscsiadm does
+            Some(port) if address.contains(':') => format!("[{}]:{}", address, port),
+            Some(port) => format!("{}:{}", address, port),
+            None => address,
+        })
+    });
+    // The device directory is named host:channel:target:lun
+    let lun = device.file_name()?.to_string_lossy().rsplit(':').next()?.parse().ok();
+    Some(Target::Iscsi {
+        name: target,
+        portal,
+        lun,
+    })
+}
+
+/// The kernel only knows the process serving a connected NBD device, so
+/// the server comes from that client's command line, unless the client
+/// registered a backend identifier with the kernel.
+fn nbd(name: &str) -> Option<Target> {
+    let pid = attribute(Path::new(&format!("/sys/block/{}/pid", name)))?;
+    if let Some(backend) = attribute(Path::new(&format!("/sys/block/{}/backend", name))) {
+        return Some(Target::Nbd {
+            server: backend,
+            export: None,
+        });
+    }
+    let cmdline = read_to_string(format!("/proc/{}/cmdline", pid)).unwrap_or_default();
+    let args: Vec<&str> = cmdline.split('\0').filter(|arg| !arg.is_empty()).collect();
+    Some(client_target(&args).unwrap_or_else(|| Target::Nbd {
+        server: format!("client pid {}", pid),
+        export: None,
+    }))
+}
+
+/// Options of nbd-client and qemu-nbd that take a separate value.
+const VALUE_OPTIONS: &[&str] = &[
+    "-b",
+    "-block-size",
+    "-t",
+    "-timeout",
+    "-C",
+    "-connections",
+    "-I",
+    "-identifier",
+    "-F",
+    "-certfile",
+    "-K",
+    "-keyfile",
+    "-A",
+    "-cacertfile",
+    "-H",
+    "-tlshostname",
+    "-c",
+    "--connect",
+    "-f",
+    "--format",
+    "-o",
+    "--offset",
+    "-P",
+    "--partition",
+    "-k",
+    "--socket",
+    "-x",
+    "--export-name",
+    "-e",
+    "--shared",
+    "-p",
+    "--port",
+    "--bind",
+];
+
+/// Reads the server from an `nbd-client HOST [PORT] [-N NAME] /dev/nbdX`
+/// or `qemu-nbd --connect=/dev/nbdX IMAGE` command line.
+fn client_target(args: &[&str]) -> Option<Target> {
+    let (program, args) = args.split_first()?;
+    let mut positional = Vec::new();
+    let mut export = None;
+    let mut unix = None;
+    let mut args = args.iter();
+    while let Some(&arg) = args.next() {
+        match arg {
+            "-N" | "-name" => export = args.next().map(|name| name.to_string()),
+            "-u" | "-unix" => unix = args.next().map(|path| path.to_string()),
+            _ if VALUE_OPTIONS.contains(&arg) => {
+                args.next();
+            }
+            _ if arg.starts_with('-') => {}
+            _ if arg.starts_with("/dev/nbd") => {}
+            _ => positional.push(arg),
+        }
+    }
+
+    let server = match (Path::new(program).file_name()?.to_str()?, positional.as_slice(), unix) {
+        ("nbd-client", _, Some(socket)) => socket,
+        ("nbd-client", [host, port, ..], None) if port.parse::<u16>().is_ok() => format!("{}:{}", host, port),
+        ("nbd-client", [host, ..], None) => host.to_string(),
+        ("qemu-nbd", [.., image], _) => image.to_string(),
+        _ => return None,
+    };
+    Some(Target::Nbd { server, export })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(args: &[&str]) -> Option<String> {
+        client_target(args).map(|target| target.to_string())
+    }
+
+    #[test]
+    fn nbd_servers_come_from_the_client_command_line() {
+        assert_eq!(
+            server(&["nbd-client", "10.0.0.5", "10809", "/dev/nbd0"]).as_deref(),
+            Some("10.0.0.5:10809")
+        );
+        assert_eq!(
+            server(&[
+                "/usr/sbin/nbd-client",
+                "-b",
+                "4096",
+                "-N",
+                "vm1",
+                "storage",
+                "/dev/nbd1"
+            ])
+            .as_deref(),
+            Some("storage, export vm1")
+        );
+        assert_eq!(
+            server(&[
+                "qemu-nbd",
+                "-f",
+                "qcow2",
+                "--connect=/dev/nbd2",
+                "/var/lib/vm/disk.qcow2"
+            ])
+            .as_deref(),
+            Some("/var/lib/vm/disk.qcow2")
+        );
+        assert_eq!(
+            server(&["nbd-client", "-unix", "/run/nbd.sock", "/dev/nbd3"]).as_deref(),
+            Some("/run/nbd.sock")
+        );
+        assert_eq!(server(&["sleep", "100"]), None);
+    }
+}
@@ -0,0 +1,383 @@
+//! `pblk report`: every drive and its partitions in plain text, the drives
+//! with problems first, or with --email-format a multipart message in text
+//! and HTML that a weekly cron job can pipe into sendmail.
+
+use std::{cmp::Reverse, fmt::Write};
+
+use crate::{
+    Drive,
+    audit::{self, Problem},
+    clock::{self, Clock},
+    export::escape,
+    growth,
+    identity::Identity,
+    lang,
+    severity::{Severity, Thresholds},
+    text,
+    units::Units,
+};
+
+fn severity(problem: &Problem) -> Severity {
+    match problem.check.ends_with(".critical") {
+        true => Severity::Critical,
+        false => Severity::Warning,
+    }
+}
+
+fn label(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical => "CRITICAL",
+        _ => "WARNING",
+    }
+}
+
+/// One problem in words, e.g. `/var on sda2: 96.2% used`.
+fn describe(problem: &Problem, units: &Units) -> String {
+    let what = match problem.check {
+        "usage.warning" | "usage.critical" => format!("{}% used", lang::number(problem.value, 1)),
+        "inodes.warning" | "inodes.critical" => format!("{}% of inodes used", lang::number(problem.value, 1)),
+        "space.system_reserve" => format!("only {} free", units.used.format(problem.value as u64)),
+        "multipath.path_down" => format!("{} multipath paths down", problem.value),
+        "partition.misaligned" => format!("starts at sector {}, not aligned to 1 MiB", problem.value),
+        "mount.unresponsive" => "not responding".to_string(),
+        "filesystem.errors" => match problem.value as u64 {
+            1 => "an error recorded, run fsck before mounting".to_string(),
+            n => format!("{} errors recorded, run fsck before mounting", n),
+        },
+        "filesystem.dirty" => "not cleanly unmounted, check before mounting".to_string(),
+        "smart.critical" => "SMART health check failed, replace the drive".to_string(),
+        check => check.to_string(),
+    };
+    match &problem.mountpoint {
+        Some(mountpoint) => format!("{} on {}: {}", mountpoint, problem.device, what),
+        None => format!("{}: {}", problem.device, what),
+    }
+}
+
+/// The worst severity among the problems of the drive and its partitions.
+fn worst(drive: &Drive, problems: &[&Problem]) -> Severity {
+    problems
+        .iter()
+        .filter(|p| {
+            p.device == drive.name
+                || drive
+                    .partitions
+                    .iter()
+                    .any(|partition| partition.dev_name() == p.device)
+        })
+        .map(|p| severity(p))
+        .fold(
+            Severity::Ok,
+            |worst, severity| if severity > worst { severity } else { worst },
+        )
+}
+
+/// `sda  500.1 GB  Samsung SSD 860`
+fn heading(drive: &Drive, units: &Units) -> String {
+    let mut heading = format!("{}  {}", drive.display_name(), units.size.format(drive.size * 512));
+    if let Some(model) = &drive.model {
+        let _ = write!(heading, "  {}", text::isolate(model));
+    }
+    heading
+}
+
+/// The partition's alias, or its kernel name without the drive's.
+fn name(partition: &crate::Partition) -> &str {
+    partition.alias.as_deref().unwrap_or(partition.dev_name())
+}
+
+/// Filesystem, usage and mountpoint of a partition, `-` where unknown.
+fn columns(partition: &crate::Partition, units: &Units) -> [String; 3] {
+    let usage = match partition.used_bytes() {
+        Some(used) => units.used_of(used, partition.size * 512),
+        None => "unmounted".to_string(),
+    };
+    [
+        partition.fstype.clone().unwrap_or_else(|| "-".to_string()),
+        usage,
+        partition.display_mountpoint().unwrap_or("-").to_string(),
+    ]
+}
+
+fn summary(problems: &[&Problem]) -> String {
+    let critical = problems.iter().filter(|p| severity(p) == Severity::Critical).count();
+    match (problems.len(), critical) {
+        (0, _) => "no problems".to_string(),
+        (1, 1) => "1 critical problem".to_string(),
+        (1, _) => "1 problem".to_string(),
+        (n, 0) => format!("{} problems", n),
+        (n, critical) => format!("{} problems, {} critical", n, critical),
+    }
+}
+
+/// Fill dates at the growth assumed with --assume-growth, e.g.
+/// `/var/log on sdb1: full around 2026-11-28 at 2.1 GB/day`.
+fn projections(drives: &[(Severity, &Drive)], units: &Units, now: u64) -> Vec<String> {
+    drives
+        .iter()
+        .flat_map(|(_, drive)| &drive.partitions)
+        .filter_map(|partition| {
+            let full = now.saturating_add((growth::days_left(partition)? * 86_400.0) as u64);
+            Some(format!(
+                "{} on {}: full around {} at {}/day",
+                partition.mountpoint.as_deref()?,
+                partition.dev_name(),
+                clock::format_date(full),
+                units.used.format(partition.assumed_growth?)
+            ))
+        })
+        .collect()
+}
+
+/// `Disk report for db1: 2 problems`, without the host when left out.
+fn title(identity: Option<&Identity>, problems: &[&Problem]) -> String {
+    match identity {
+        Some(identity) => format!("Disk report for {}: {}", identity.hostname, summary(problems)),
+        None => format!("Disk report: {}", summary(problems)),
+    }
+}
+
+fn plain(
+    identity: Option<&Identity>,
+    drives: &[(Severity, &Drive)],
+    problems: &[&Problem],
+    projected: &[String],
+    units: &Units,
+) -> String {
+    let mut out = format!("{}\n", title(identity, problems));
+    if let Some(identity) = identity {
+        let _ = writeln!(out, "{}", identity.describe());
+    }
+    if !problems.is_empty() {
+        out.push('\n');
+    }
+    for problem in problems {
+        let _ = writeln!(out, "  {:8}  {}", label(severity(problem)), describe(problem, units));
+    }
+    if !projected.is_empty() {
+        out.push_str("\nAssumed growth:\n");
+    }
+    for projection in projected {
+        let _ = writeln!(out, "  {}", projection);
+    }
+    for (severity, drive) in drives {
+        let _ = write!(out, "\n{}", heading(drive, units));
+        if *severity != Severity::Ok {
+            let _ = write!(out, "  [{}]", label(*severity));
+        }
+        out.push('\n');
+        let names: Vec<String> = drive
+            .partitions
+            .iter()
+            .map(|p| format!("{}{}", "  ".repeat(p.depth), name(p)))
+            .collect();
+        let width = names.iter().map(|name| text::width(name)).max().unwrap_or(0);
+        for (partition, name) in drive.partitions.iter().zip(&names) {
+            let [fstype, usage, mountpoint] = columns(partition, units);
+            let _ = writeln!(
+                out,
+                "  {}  {}  {}  {}",
+                text::pad_right(name, width),
+                text::pad_right(&fstype, 8),
+                text::pad_right(&usage, 24),
+                mountpoint
+            );
+        }
+    }
+    out
+}
+
+/// The same report as an HTML page, with inline styles only since mail
+/// clients drop style sheets.
+fn html(
+    identity: Option<&Identity>,
+    drives: &[(Severity, &Drive)],
+    problems: &[&Problem],
+    projected: &[String],
+    units: &Units,
+) -> String {
+    let color = |severity| match severity {
+        Severity::Critical => "#c0392b",
+        _ => "#b7791f",
+    };
+    let title = escape(&title(identity, problems));
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n\
+         <body style=\"font-family: sans-serif\">\n<h2>{}</h2>\n",
+        title, title
+    );
+    if let Some(identity) = identity {
+        let _ = writeln!(out, "<p style=\"color: #777777\">{}</p>", escape(&identity.describe()));
+    }
+    if !problems.is_empty() {
+        out.push_str("<ul>\n");
+        for problem in problems {
+            let severity = severity(problem);
+            let _ = writeln!(
+                out,
+                "<li><b style=\"color: {}\">{}</b> {}</li>",
+                color(severity),
+                label(severity),
+                escape(&describe(problem, units))
+            );
+        }
+        out.push_str("</ul>\n");
+    }
+    if !projected.is_empty() {
+        out.push_str("<p><b>Assumed growth</b></p>\n<ul>\n");
+        for projection in projected {
+            let _ = writeln!(out, "<li>{}</li>", escape(projection));
+        }
+        out.push_str("</ul>\n");
+    }
+    for (severity, drive) in drives {
+        let _ = write!(out, "<h3>{}", escape(&heading(drive, units)));
+        if *severity != Severity::Ok {
+            let _ = write!(
+                out,
+                " <span style=\"color: {}\">{}</span>",
+                color(*severity),
+                label(*severity)
+            );
+        }
+        out.push_str("</h3>\n");
+        if drive.partitions.is_empty() {
+            continue;
+        }
+        out.push_str("<table cellpadding=\"4\">\n");
+        out.push_str("<tr><th align=\"left\">Device</th><th align=\"left\">Filesystem</th>");
+        out.push_str("<th align=\"left\">Used</th><th align=\"left\">Mountpoint</th></tr>\n");
+        for partition in &drive.partitions {
+            let [fstype, usage, mountpoint] = columns(partition, units).map(|column| escape(&column));
+            let _ = writeln!(
+                out,
+                "<tr><td style=\"padding-left: {}em\">{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                partition.depth,
+                escape(name(partition)),
+                fstype,
+                usage,
+                mountpoint
+            );
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// A multipart/alternative message of both renderings. sendmail fills in
+/// the sender, date and message id; the recipient goes on its command line.
+fn email(
+    identity: Option<&Identity>,
+    drives: &[(Severity, &Drive)],
+    problems: &[&Problem],
+    projected: &[String],
+    units: &Units,
+    clock: &dyn Clock,
+) -> String {
+    // `=_` never starts a line of either part, so the boundary cannot clash
+    let boundary = format!("=_pblk_{}", clock.now());
+    let part = |kind: &str, body: String| {
+        format!(
+            "--{}\nContent-Type: text/{}; charset=utf-8\nContent-Transfer-Encoding: 8bit\n\n{}",
+            boundary, kind, body
+        )
+    };
+    format!(
+        "Subject: {}\nMIME-Version: 1.0\n\
+         Content-Type: multipart/alternative; boundary=\"{}\"\n\n{}\n{}\n--{}--\n",
+        title(identity, problems),
+        boundary,
+        part("plain", plain(identity, drives, problems, projected, units)),
+        part("html", html(identity, drives, problems, projected, units)),
+        boundary
+    )
+}
+
+/// Renders the report, leaving out problems acknowledged in the config and,
+/// as the charts do by default, card readers with nothing inserted. The
+/// `identity` of the machine heads it unless left out, and partitions
+/// given an assumed growth get their fill date.
+pub fn render(
+    drives: &[Drive],
+    thresholds: &Thresholds,
+    acknowledged: &[String],
+    units: &Units,
+    as_email: bool,
+    identity: Option<&Identity>,
+    clock: &dyn Clock,
+) -> String {
+    let detected = audit::detect(drives, thresholds);
+    let mut problems: Vec<&Problem> = detected.iter().filter(|p| !p.acknowledged(acknowledged)).collect();
+    problems.sort_by_key(|p| Reverse(severity(p) as u8));
+    let mut ordered: Vec<(Severity, &Drive)> = drives
+        .iter()
+        .filter(|drive| !drive.no_media)
+        .map(|drive| (worst(drive, &problems), drive))
+        .collect();
+    ordered.sort_by_key(|(severity, _)| Reverse(*severity as u8));
+    let projected = projections(&ordered, units, clock.now());
+
+    match as_email {
+        true => email(identity, &ordered, &problems, &projected, units, clock),
+        false => plain(identity, &ordered, &problems, &projected, units),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Mount, Partition, clock::FakeClock};
+
+    #[test]
+    fn problem_drives_come_first_in_both_parts() {
+        let mount = Mount::new("/srv", "ext4", &[]);
+        let mut roomy = Partition::new("sda/sda1".to_string(), 1 << 21, Some(&mount));
+        roomy.used = Some(1 << 20);
+        let mut full = Partition::new("sdb/sdb1".to_string(), 1 << 21, Some(&mount));
+        full.used = Some(1 << 30);
+        let drives = [
+            Drive::new("sda", 1 << 21, vec![roomy]),
+            Drive::new("sdb", 1 << 21, vec![full]),
+        ];
+
+        let clock = FakeClock::at(1_700_000_000);
+        let identity = Identity {
+            hostname: "db1".to_string(),
+            kernel: None,
+            timestamp: 1_700_000_000,
+        };
+        let message = render(
+            &drives,
+            &Thresholds::default(),
+            &[],
+            &Units::default(),
+            true,
+            Some(&identity),
+            &clock,
+        );
+        assert!(message.starts_with("Subject: Disk report for db1: "), "{}", message);
+        assert!(message.contains("\ndb1, collected 2023-11-14 22:13 UTC\n"));
+        assert!(message.lines().next().unwrap().ends_with(": 1 critical problem"));
+        assert_eq!(message.matches("--=_pblk_1700000000").count(), 3);
+        let (text, html) = message.split_once("text/html").unwrap();
+        for part in [text, html] {
+            assert!(part.find("sdb").unwrap() < part.find("sda").unwrap(), "{}", part);
+        }
+        assert!(text.contains("CRITICAL  /srv on sdb1: 100"));
+
+        let acknowledged = ["usage.critical:sdb1".to_string()];
+        assert!(
+            render(
+                &drives,
+                &Thresholds::default(),
+                &acknowledged,
+                &Units::default(),
+                false,
+                None,
+                &clock
+            )
+            .starts_with("Disk report: no problems\n")
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    sync::{Arc, mpsc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    Drive, Inodes, PrettyblkError,
+    source::{FsStat, Progress, StatvfsProvider},
+};
+
+/// Asks `provider` for every distinct mountpoint in parallel and fills in usage.
+/// Mounts that do not answer within `timeout` (e.g. a hung NFS server) are
+/// reported and left without usage instead of stalling the whole run.
+pub fn collect_usage(
+    drives: &mut [Drive],
+    provider: &Arc<dyn StatvfsProvider>,
+    timeout: Duration,
+    report: &mut dyn FnMut(Progress),
+) {
+    let mountpoints: HashSet<String> = drives
+        .iter()
+        .flat_map(|d| &d.partitions)
+        .filter_map(|p| p.mountpoint.clone())
+        .collect();
+    let pending = mountpoints.len();
+    report(Progress::Probing { mounts: pending });
+
+    let (sender, receiver) = mpsc::channel();
+    for mountpoint in mountpoints {
+        let sender = sender.clone();
+        let provider = Arc::clone(provider);
+        // Threads stuck in the kernel are detached and die with the process
+        thread::spawn(move || {
+            let stat = provider.statvfs(&mountpoint);
+            let _ = sender.send((mountpoint, stat));
+        });
+    }
+    drop(sender);
+
+    let deadline = Instant::now() + timeout;
+    let mut results: HashMap<String, io::Result<FsStat>> = HashMap::new();
+    while results.len() < pending {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match receiver.recv_timeout(remaining) {
+            Ok((mountpoint, stat)) => {
+                report(Progress::Probed {
+                    mountpoint: mountpoint.clone(),
+                });
+                results.insert(mountpoint, stat);
+            }
+            Err(_) => break,
+        }
+    }
+
+    for partition in drives.iter_mut().flat_map(|d| d.partitions.iter_mut()) {
+        let Some(mountpoint) = &partition.mountpoint else {
+            continue;
+        };
+        let Some(result) = results.get(mountpoint) else {
+            let message = format!(
+                "statvfs on {} timed out after {:.1}s",
+                mountpoint,
+                timeout.as_secs_f64()
+            );
+            report(Progress::Warning(message.clone()));
+            partition.stalled = true;
+            partition.usage_error = Some(PrettyblkError::Statvfs {
+                mountpoint: mountpoint.clone(),
+                source: io::Error::new(io::ErrorKind::TimedOut, message),
+            });
+            continue;
+        };
+        let stat = match result {
+            Ok(stat) => stat,
+            Err(e) => {
+                // Partitions of one multi-device filesystem share the answer
+                let source = match e.raw_os_error() {
+                    Some(code) => io::Error::from_raw_os_error(code),
+                    None => io::Error::new(e.kind(), e.to_string()),
+                };
+                partition.usage_error = Some(PrettyblkError::Statvfs {
+                    mountpoint: mountpoint.clone(),
+                    source,
+                });
+                continue;
+            }
+        };
+
+        // FUSE and network filesystems can report anything
+        let total = stat.blocks.saturating_mul(stat.block_size);
+        let free = stat.blocks_free.saturating_mul(stat.block_size);
+        partition.used = Some(total.saturating_sub(free));
+        partition.free = Some(free);
+        // Filesystems without a fixed inode table (btrfs, vfat) report zero
+        if stat.files > 0 {
+            partition.inodes = Some(Inodes {
+                used: stat.files.saturating_sub(stat.files_free),
+                total: stat.files,
+            });
+        }
+    }
+}
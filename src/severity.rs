@@ -0,0 +1,211 @@
+use colored::Color;
+
+use crate::Drive;
+
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum Severity {
+    Ok,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    /// Bar color for this severity; healthy bars keep their partition color.
+    pub fn color(self, base: Color) -> Color {
+        match self {
+            Severity::Ok => base,
+            Severity::Warning => Color::Yellow,
+            Severity::Critical => Color::Red,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Ok => "ok",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+/// How percentage and free-space limits of the same level are combined.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Combine {
+    /// Either limit firing is enough
+    Any,
+    /// Every configured limit has to fire, so a 16 TB disk at 95% with
+    /// 800 GB free is not flagged when `warn_free` is lower than that
+    All,
+}
+
+impl Combine {
+    pub fn parse(value: &str) -> Option<Combine> {
+        match value {
+            "any" => Some(Combine::Any),
+            "all" => Some(Combine::All),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Combine::Any => "any",
+            Combine::All => "all",
+        }
+    }
+}
+
+/// Usage limits for a filesystem. A limit that is `None` never fires.
+#[derive(Clone, Copy, Default)]
+pub struct Rule {
+    pub warn: Option<f64>,
+    pub critical: Option<f64>,
+    pub warn_free: Option<u64>,
+    pub critical_free: Option<u64>,
+    pub combine: Option<Combine>,
+}
+
+impl Rule {
+    /// Fills limits left unset in `self` from `base`.
+    fn or(self, base: Rule) -> Rule {
+        Rule {
+            warn: self.warn.or(base.warn),
+            critical: self.critical.or(base.critical),
+            warn_free: self.warn_free.or(base.warn_free),
+            critical_free: self.critical_free.or(base.critical_free),
+            combine: self.combine.or(base.combine),
+        }
+    }
+
+    fn severity(&self, percent: f64, free: u64) -> Severity {
+        let combine = self.combine.unwrap_or(Combine::Any);
+        let level = |percent_limit: Option<f64>, free_limit: Option<u64>| {
+            let by_percent = percent_limit.map(|limit| percent >= limit);
+            let by_free = free_limit.map(|limit| free < limit);
+            match (by_percent, by_free, combine) {
+                (Some(p), Some(f), Combine::Any) => p || f,
+                (Some(p), Some(f), Combine::All) => p && f,
+                (Some(p), None, _) => p,
+                (None, Some(f), _) => f,
+                (None, None, _) => false,
+            }
+        };
+
+        if level(self.critical, self.critical_free) {
+            Severity::Critical
+        } else if level(self.warn, self.warn_free) {
+            Severity::Warning
+        } else {
+            Severity::Ok
+        }
+    }
+
+    fn percent_severity(&self, percent: f64) -> Severity {
+        if self.critical.is_some_and(|limit| percent >= limit) {
+            Severity::Critical
+        } else if self.warn.is_some_and(|limit| percent >= limit) {
+            Severity::Warning
+        } else {
+            Severity::Ok
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Thresholds {
+    pub default: Rule,
+    /// Overrides keyed by exact mountpoint
+    pub mounts: Vec<(String, Rule)>,
+    /// Mountpoint globs that are full by design (ISOs, archives, snaps)
+    pub expected_full: Vec<String>,
+    /// Filesystem types that are always full by design
+    pub expected_full_fstypes: Vec<String>,
+}
+
+impl Default for Thresholds {
+    fn default() -> Thresholds {
+        Thresholds {
+            default: Rule {
+                warn: Some(80.0),
+                critical: Some(90.0),
+                warn_free: None,
+                critical_free: None,
+                combine: Some(Combine::Any),
+            },
+            mounts: Vec::new(),
+            expected_full: Vec::new(),
+            expected_full_fstypes: ["squashfs", "iso9660", "udf", "erofs"]
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Matches `text` against a pattern where `*` stands for any run of characters.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => {
+            let Some(text) = text.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=text.len())
+                .filter(|&i| text.is_char_boundary(i))
+                .any(|i| glob_match(rest, &text[i..]))
+        }
+    }
+}
+
+impl Thresholds {
+    pub fn rule(&self, mountpoint: Option<&str>) -> Rule {
+        self.mounts
+            .iter()
+            .find(|(path, _)| Some(path.as_str()) == mountpoint)
+            .map(|(_, rule)| rule.or(self.default))
+            .unwrap_or(self.default)
+    }
+
+    /// Whether a filesystem is marked as full by design. Such filesystems are
+    /// still displayed but never classified as a problem.
+    pub fn expected_full(&self, mountpoint: Option<&str>, fstype: Option<&str>) -> bool {
+        fstype.is_some_and(|t| self.expected_full_fstypes.iter().any(|e| e == t))
+            || mountpoint.is_some_and(|m| self.expected_full.iter().any(|p| glob_match(p, m)))
+    }
+
+    /// Classifies byte usage by the percentage and free-space limits of the
+    /// rule that applies to `mountpoint`.
+    pub fn classify(&self, mountpoint: Option<&str>, fstype: Option<&str>, used: u64, total: u64) -> Severity {
+        if self.expected_full(mountpoint, fstype) {
+            return Severity::Ok;
+        }
+        let percent = used as f64 / total.max(1) as f64 * 100.0;
+        self.rule(mountpoint).severity(percent, total.saturating_sub(used))
+    }
+
+    /// Classifies a plain ratio (e.g. inodes) by the percentage limits only.
+    pub fn classify_ratio(&self, mountpoint: Option<&str>, fstype: Option<&str>, ratio: f64) -> Severity {
+        if self.expected_full(mountpoint, fstype) {
+            return Severity::Ok;
+        }
+        self.rule(mountpoint).percent_severity(ratio * 100.0)
+    }
+}
+
+/// Filesystems where running out of space breaks package upgrades, kernel
+/// installs and journald, whatever the configured thresholds say.
+const SYSTEM_MOUNTS: [&str; 4] = ["/", "/boot", "/boot/efi", "/efi"];
+
+/// Free space below which systemd-journald and package managers start failing.
+pub const SYSTEM_RESERVE: u64 = 64 * 1024 * 1024;
+
+/// Root and boot filesystems with less than [`SYSTEM_RESERVE`] free, as
+/// (mountpoint, free bytes).
+pub fn system_space_warnings(drives: &[Drive]) -> Vec<(&str, u64)> {
+    drives
+        .iter()
+        .flat_map(|d| &d.partitions)
+        .filter_map(|p| Some((p.mountpoint.as_deref()?, p.free?)))
+        .filter(|(mountpoint, free)| SYSTEM_MOUNTS.contains(mountpoint) && *free < SYSTEM_RESERVE)
+        .collect()
+}
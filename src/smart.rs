@@ -0,0 +1,134 @@
+//! `--smart`: the overall SMART or NVMe health verdict of each drive, its
+//! temperature and power-on hours, from `smartctl --json`. smartctl needs
+//! root to open the disks; as an ordinary user pblk asks
+//! `pblk-smart-helper` instead, a setuid helper that runs nothing but these
+//! read-only queries, found next to pblk or in `PATH`.
+
+use std::{env, io, path::PathBuf, process::Command, time::Duration};
+
+use crate::{Drive, PrettyblkError, child, json, privilege};
+
+/// The queries, the same the helper runs: the health verdict and the
+/// attributes or NVMe health log, leaving disks that have spun down asleep.
+const ARGS: [&str; 4] = ["--json=c", "--health", "--attributes", "--nocheck=standby"];
+
+/// A drive behind a sleepy USB bridge can take this long to answer.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+const HELPER: &str = "pblk-smart-helper";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Health {
+    /// The drive's own verdict; `false` means it expects to fail
+    pub passed: bool,
+    /// Degrees Celsius
+    pub temperature: Option<u64>,
+    pub power_on_hours: Option<u64>,
+}
+
+/// The health in smartctl's JSON output, `None` when it has no verdict,
+/// e.g. for a drive in standby or one smartctl could not open.
+fn parse(output: &str) -> Option<Health> {
+    let value = json::parse(output).ok()?;
+    let passed = match value.get("smart_status")?.get("passed")? {
+        json::Value::Bool(passed) => *passed,
+        _ => return None,
+    };
+    Some(Health {
+        passed,
+        temperature: value
+            .get("temperature")
+            .and_then(|t| t.get("current"))
+            .and_then(json::Value::as_u64),
+        power_on_hours: value
+            .get("power_on_time")
+            .and_then(|t| t.get("hours"))
+            .and_then(json::Value::as_u64),
+    })
+}
+
+/// The helper beside the running binary, or else the first one in `PATH`.
+pub fn helper() -> Option<PathBuf> {
+    let beside = env::current_exe().ok().map(|exe| exe.with_file_name(HELPER));
+    let in_path = env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).map(|dir| dir.join(HELPER)).collect::<Vec<_>>())
+        .unwrap_or_default();
+    beside.into_iter().chain(in_path).find(|path| path.is_file())
+}
+
+/// smartctl's output for `device`, run directly as root and through the
+/// helper otherwise. smartctl exits non-zero for failing drives too, so
+/// the status is not checked.
+fn query(device: &str) -> Result<String, PrettyblkError> {
+    let error = |source| PrettyblkError::Smart {
+        device: device.to_string(),
+        source,
+    };
+    let mut command = match privilege::is_root() {
+        true => {
+            let mut command = Command::new("smartctl");
+            command.args(ARGS).arg(format!("/dev/{}", device));
+            command
+        }
+        false => {
+            let Some(helper) = helper() else {
+                privilege::deny_on("SMART health", device);
+                let denied = io::Error::new(io::ErrorKind::PermissionDenied, "needs root or pblk-smart-helper");
+                return Err(error(denied));
+            };
+            let mut command = Command::new(helper);
+            command.arg(device);
+            command
+        }
+    };
+    let output = child::output(&mut command, TIMEOUT).map_err(error)?;
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    // A helper that was installed without the setuid bit
+    if stdout.contains("Permission denied") {
+        privilege::deny_on("SMART health", device);
+        return Err(error(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "smartctl cannot open the drive",
+        )));
+    }
+    Ok(stdout)
+}
+
+/// Queries every local drive; a dm-multipath map is asked through its
+/// first path.
+pub fn annotate(drives: &mut [Drive]) {
+    for drive in drives.iter_mut().filter(|d| d.origin.is_none() && !d.no_media) {
+        let device = match &drive.multipath {
+            Some(paths) if !paths.iter().any(|p| p.ana) => paths.first().map(|p| p.name.clone()),
+            _ => Some(drive.name.clone()),
+        };
+        let Some(device) = device else {
+            continue;
+        };
+        match query(&device) {
+            Ok(output) => drive.smart = parse(&output),
+            Err(e) => drive.smart_error = Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nvme_health_logs_give_temperature_and_hours() {
+        let output = r#"{"json_format_version":[1,0],"device":{"name":"/dev/nvme0n1","type":"nvme"},
+            "smart_status":{"passed":true,"nvme":{"value":0}},"temperature":{"current":41},
+            "power_on_time":{"hours":8123}}"#;
+        let health = Health {
+            passed: true,
+            temperature: Some(41),
+            power_on_hours: Some(8123),
+        };
+        assert_eq!(parse(output), Some(health));
+
+        let standby = r#"{"smartctl":{"exit_status":2},"power_mode":"STANDBY"}"#;
+        assert_eq!(parse(standby), None);
+    }
+}
@@ -0,0 +1,270 @@
+use std::{collections::HashMap, fmt::Write as _, fs, path::Path};
+
+use colored::*;
+
+use crate::{Drive, clock::Clock, identity::Identity, json, text, units::Units};
+
+const VERSION: u64 = 1;
+
+/// One partition as recorded in a snapshot; sizes in bytes.
+struct Saved {
+    drive: String,
+    mountpoint: Option<String>,
+    size: u64,
+    used: Option<u64>,
+}
+
+struct Snapshot {
+    timestamp: u64,
+    /// Drive sizes by name
+    drives: HashMap<String, u64>,
+    /// Partitions by kernel name
+    partitions: HashMap<String, Saved>,
+}
+
+/// Serializes the current drives and partition usage as JSON, with the
+/// hostname and kernel of `identity` if given.
+pub fn write(drives: &[Drive], identity: Option<&Identity>, clock: &dyn Clock) -> String {
+    let mut out = String::new();
+    let _ = write!(out, "{{\"version\":{},\"timestamp\":{},", VERSION, clock.now());
+    if let Some(identity) = identity {
+        let _ = write!(
+            out,
+            "\"hostname\":{},\"kernel\":{},",
+            json::string(&identity.hostname),
+            json::optional(identity.kernel.as_deref())
+        );
+    }
+    let _ = writeln!(out, "\"drives\":[");
+    for (i, drive) in drives.iter().enumerate() {
+        let separator = if i + 1 < drives.len() { "," } else { "" };
+        let _ = writeln!(
+            out,
+            "  {{\"name\":{},\"size\":{}}}{}",
+            json::string(&drive.name),
+            drive.size * 512,
+            separator
+        );
+    }
+    let _ = writeln!(out, "],\"partitions\":[");
+    let partitions: Vec<_> = drives
+        .iter()
+        .flat_map(|d| d.partitions.iter().map(move |p| (d, p)))
+        .collect();
+    for (i, (drive, partition)) in partitions.iter().enumerate() {
+        let separator = if i + 1 < partitions.len() { "," } else { "" };
+        let _ = writeln!(
+            out,
+            "  {{\"drive\":{},\"name\":{},\"mountpoint\":{},\"size\":{},\"used\":{}}}{}",
+            json::string(&drive.name),
+            json::string(partition.dev_name()),
+            json::optional(partition.display_mountpoint()),
+            partition.size * 512,
+            partition
+                .used_bytes()
+                .map(|u| u.to_string())
+                .unwrap_or_else(|| "null".to_string()),
+            separator
+        );
+    }
+    let _ = writeln!(out, "]}}");
+    out
+}
+
+fn load(path: &Path) -> Result<Snapshot, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let root = json::parse(&content).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let invalid = || format!("{}: not a prettyblk snapshot", path.display());
+
+    if root.get("version").and_then(json::Value::as_u64) != Some(VERSION) {
+        return Err(invalid());
+    }
+    let timestamp = root
+        .get("timestamp")
+        .and_then(json::Value::as_u64)
+        .ok_or_else(invalid)?;
+
+    let mut drives = HashMap::new();
+    for drive in root.get("drives").and_then(json::Value::as_array).ok_or_else(invalid)? {
+        let name = drive.get("name").and_then(json::Value::as_str).ok_or_else(invalid)?;
+        let size = drive.get("size").and_then(json::Value::as_u64).ok_or_else(invalid)?;
+        drives.insert(name.to_string(), size);
+    }
+
+    let mut partitions = HashMap::new();
+    let saved = root
+        .get("partitions")
+        .and_then(json::Value::as_array)
+        .ok_or_else(invalid)?;
+    for partition in saved {
+        let field = |key| partition.get(key).and_then(json::Value::as_str);
+        let name = field("name").ok_or_else(invalid)?;
+        let saved = Saved {
+            drive: field("drive").ok_or_else(invalid)?.to_string(),
+            mountpoint: field("mountpoint").map(String::from),
+            size: partition
+                .get("size")
+                .and_then(json::Value::as_u64)
+                .ok_or_else(invalid)?,
+            used: partition.get("used").and_then(json::Value::as_u64),
+        };
+        partitions.insert(name.to_string(), saved);
+    }
+    Ok(Snapshot {
+        timestamp,
+        drives,
+        partitions,
+    })
+}
+
+/// A duration in its two largest units, e.g. `2d 3h`.
+pub fn ago(seconds: u64) -> String {
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m", seconds / 60),
+        3600..86400 => format!("{}h {}m", seconds / 3600, seconds % 3600 / 60),
+        _ => format!("{}d {}h", seconds / 86400, seconds % 86400 / 3600),
+    }
+}
+
+fn signed(units: &Units, delta: i128) -> ColoredString {
+    let sign = if delta < 0 { "-" } else { "+" };
+    let text = format!("{}{}", sign, units.used.format(delta.unsigned_abs() as u64));
+    match delta {
+        d if d > 0 => text.yellow(),
+        d if d < 0 => text.green(),
+        _ => text.dimmed(),
+    }
+}
+
+/// Prints what changed between the snapshot at `path` and `drives`: added
+/// and removed devices, then partitions ordered by how much their usage moved.
+pub fn diff(path: &Path, drives: &[Drive], units: &Units, clock: &dyn Clock) -> Result<(), String> {
+    let Snapshot {
+        timestamp,
+        drives: saved_drives,
+        partitions: mut saved,
+    } = load(path)?;
+    println!(
+        "{} since snapshot taken {} ago",
+        "Changes".bold().blue(),
+        ago(clock.now().saturating_sub(timestamp))
+    );
+
+    let mut lines: Vec<(i128, [String; 3])> = Vec::new();
+    for drive in drives {
+        if !saved_drives.contains_key(&drive.name) {
+            println!(
+                "  {} {} (new, {})",
+                "+".green(),
+                drive.name.bold(),
+                units.size.format(drive.size * 512)
+            );
+        }
+        for partition in &drive.partitions {
+            let name = partition.dev_name().to_string();
+            let mountpoint = text::isolate(partition.display_mountpoint().unwrap_or("-")).into_owned();
+            let Some(before) = saved.remove(&name) else {
+                lines.push((i128::MAX, [name, mountpoint, "new".green().to_string()]));
+                continue;
+            };
+            let detail = match (before.used, partition.used_bytes()) {
+                (Some(old), Some(new)) if old != new => {
+                    let delta = new as i128 - old as i128;
+                    let text = format!(
+                        "{}  {} -> {}",
+                        signed(units, delta),
+                        units.used.format(old),
+                        units.used.format(new)
+                    );
+                    lines.push((delta.abs(), [name, mountpoint, text]));
+                    continue;
+                }
+                (None, Some(_)) => format!("mounted (was {})", "unmounted".dimmed()),
+                (Some(_), None) => format!(
+                    "{} (was {})",
+                    "unmounted".yellow(),
+                    before.mountpoint.as_deref().unwrap_or("-")
+                ),
+                _ if before.size != partition.size * 512 => format!(
+                    "resized {} -> {}",
+                    units.size.format(before.size),
+                    units.size.format(partition.size * 512)
+                ),
+                _ => continue,
+            };
+            lines.push((0, [name, mountpoint, detail]));
+        }
+    }
+    for (name, before) in saved {
+        let mountpoint = text::isolate(before.mountpoint.as_deref().unwrap_or("-")).into_owned();
+        let detail = format!("{} from {}", "removed".red(), before.drive);
+        lines.push((i128::MAX, [name, mountpoint, detail]));
+    }
+    for name in saved_drives
+        .keys()
+        .filter(|name| !drives.iter().any(|d| &d.name == *name))
+    {
+        println!("  {} {} (removed)", "-".red(), name.bold());
+    }
+
+    if lines.is_empty() {
+        println!("  no changes");
+        return Ok(());
+    }
+    lines.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1[0].cmp(&b.1[0])));
+    let name_width = lines.iter().map(|(_, l)| text::width(&l[0])).max().unwrap_or(0);
+    let mount_width = lines.iter().map(|(_, l)| text::width(&l[1])).max().unwrap_or(0);
+    for (_, [name, mountpoint, detail]) in lines {
+        println!(
+            "  {} {} {}",
+            text::pad_right(&name, name_width),
+            text::pad_right(&mountpoint, mount_width),
+            detail
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+    use crate::{Partition, clock::FakeClock};
+
+    #[test]
+    fn snapshot_round_trips_with_its_timestamp() {
+        let mut partition = Partition::new("sdt/sdt1".to_string(), 4096, None);
+        partition.used = Some(12345);
+        partition.mountpoint = Some("/srv \"x\"".to_string());
+        let drives = [Drive::new("sdt", 0, vec![partition])];
+
+        let clock = FakeClock::at(1_800_000_000);
+        let path = env::temp_dir().join(format!("prettyblk-snapshot-test-{}.json", process::id()));
+        let identity = Identity {
+            hostname: "db1".to_string(),
+            kernel: Some("Linux 6.8.0".to_string()),
+            timestamp: 0,
+        };
+        fs::write(&path, write(&drives, Some(&identity), &clock)).unwrap();
+        let snapshot = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(snapshot.timestamp, 1_800_000_000);
+        let saved = &snapshot.partitions["sdt1"];
+        assert_eq!((saved.size, saved.used), (4096 * 512, Some(12345)));
+        assert_eq!(saved.mountpoint.as_deref(), Some("/srv \"x\""));
+        assert_eq!(saved.drive, "sdt");
+    }
+
+    #[test]
+    fn ages_are_rounded_to_two_units() {
+        let clock = FakeClock::at(1_000_000);
+        let taken = clock.now();
+        clock.advance(90);
+        assert_eq!(ago(clock.now() - taken), "1m");
+        clock.advance(2 * 86_400 + 3 * 3600);
+        assert_eq!(ago(clock.now() - taken), "2d 3h");
+    }
+}
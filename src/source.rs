@@ -0,0 +1,253 @@
+//! Where a scan gets mounts and filesystem usage from. The defaults read the
+//! running system; library users can substitute their own to unit-test an
+//! integration or to describe a container or a host reached through an agent.
+
+use std::{collections::HashMap, io, sync::Arc, time::Duration};
+
+#[cfg(unix)]
+use nix::sys::statvfs::statvfs;
+
+use crate::{Drive, Mount, PrettyblkError, platform, privilege, scan};
+
+/// Mounted filesystems keyed by their source device path, e.g. `/dev/sda1`.
+pub trait MountTable {
+    fn mounts(&self) -> Result<HashMap<String, Mount>, PrettyblkError>;
+}
+
+/// A fixed table, e.g. one parsed from a container's `/proc/self/mounts`.
+impl MountTable for HashMap<String, Mount> {
+    fn mounts(&self) -> Result<HashMap<String, Mount>, PrettyblkError> {
+        Ok(self.clone())
+    }
+}
+
+/// Block and inode counts of one filesystem, as statvfs reports them.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FsStat {
+    pub blocks: u64,
+    pub blocks_free: u64,
+    pub block_size: u64,
+    pub files: u64,
+    pub files_free: u64,
+}
+
+/// Answers statvfs for a mountpoint, or why it cannot be queried.
+/// Each mountpoint is asked on its own thread, and answers that arrive after
+/// the scan timeout are discarded.
+pub trait StatvfsProvider: Send + Sync {
+    fn statvfs(&self, mountpoint: &str) -> io::Result<FsStat>;
+}
+
+impl<F: Fn(&str) -> io::Result<FsStat> + Send + Sync> StatvfsProvider for F {
+    fn statvfs(&self, mountpoint: &str) -> io::Result<FsStat> {
+        self(mountpoint)
+    }
+}
+
+/// The mount table of the running system.
+pub struct SystemMounts;
+
+impl MountTable for SystemMounts {
+    fn mounts(&self) -> Result<HashMap<String, Mount>, PrettyblkError> {
+        platform::native()
+            .mounts()
+            .map_err(|source| PrettyblkError::Mounts { source })
+    }
+}
+
+/// statvfs(3) on the running system, GetDiskFreeSpaceEx on Windows.
+pub struct SystemStatvfs;
+
+impl StatvfsProvider for SystemStatvfs {
+    #[cfg(windows)]
+    fn statvfs(&self, mountpoint: &str) -> io::Result<FsStat> {
+        platform::windows::statvfs(mountpoint).inspect_err(|e| {
+            if privilege::refused(e) {
+                privilege::deny_on("usage", mountpoint);
+            }
+        })
+    }
+
+    #[cfg(unix)]
+    fn statvfs(&self, mountpoint: &str) -> io::Result<FsStat> {
+        let stat = statvfs(mountpoint).inspect_err(|&errno| {
+            if privilege::refused_errno(errno) {
+                privilege::deny_on("usage", mountpoint);
+            }
+        })?;
+        // The field widths vary by platform, e.g. block counts are u32 on macOS
+        #[allow(clippy::unnecessary_cast)]
+        Ok(FsStat {
+            blocks: stat.blocks() as u64,
+            blocks_free: stat.blocks_free() as u64,
+            block_size: stat.block_size() as u64,
+            files: stat.files() as u64,
+            files_free: stat.files_free() as u64,
+        })
+    }
+}
+
+/// Something that happened during [`Sources::scan`], for front-ends that
+/// show a progress indicator while slow mounts are probed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Progress {
+    /// A drive was found with this many partitions and stacked devices
+    Discovered { drive: String, partitions: usize },
+    /// Discovery is done and this many distinct mountpoints will be probed
+    Probing { mounts: usize },
+    /// statvfs answered for a mountpoint, successfully or not
+    Probed { mountpoint: String },
+    /// A problem that leaves part of the result incomplete, e.g. a mount
+    /// that did not answer in time
+    Warning(String),
+}
+
+/// Receives [`Progress`] events on the thread that called [`Sources::scan`].
+pub type ProgressFn = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+/// Everything a scan reads besides the block devices themselves.
+pub struct Sources {
+    pub mounts: Box<dyn MountTable>,
+    pub statvfs: Arc<dyn StatvfsProvider>,
+    /// How long to wait for statvfs before marking a mount as stalled
+    pub timeout: Duration,
+    /// Gets every event; without one, warnings are printed to stderr
+    pub progress: Option<ProgressFn>,
+}
+
+impl Sources {
+    /// The running system's mounts and statvfs.
+    pub fn system() -> Sources {
+        Sources {
+            mounts: Box::new(SystemMounts),
+            statvfs: Arc::new(SystemStatvfs),
+            timeout: Duration::from_secs(2),
+            progress: None,
+        }
+    }
+
+    /// Discovers the drives, attaches mounts from the mount table and fills
+    /// in usage from the statvfs provider. A mount table that cannot be read
+    /// is reported as a warning and every partition listed as unmounted.
+    pub fn scan(&self) -> Vec<Drive> {
+        let mounts = self.mounts.mounts().unwrap_or_else(|e| {
+            self.report(Progress::Warning(e.chain()));
+            HashMap::new()
+        });
+        self.scan_with(&mounts)
+    }
+
+    /// Like [`Sources::scan`], but fails when the mount table cannot be read.
+    pub fn try_scan(&self) -> Result<Vec<Drive>, PrettyblkError> {
+        Ok(self.scan_with(&self.mounts.mounts()?))
+    }
+
+    fn report(&self, event: Progress) {
+        match &self.progress {
+            Some(progress) => progress(&event),
+            None => {
+                if let Progress::Warning(message) = event {
+                    warn!("pblk: {}", message);
+                }
+            }
+        }
+    }
+
+    fn scan_with(&self, mounts: &HashMap<String, Mount>) -> Vec<Drive> {
+        let mut report = |event: Progress| self.report(event);
+        let mut drives = platform::native().drives(mounts);
+        for drive in &drives {
+            report(Progress::Discovered {
+                drive: drive.name.clone(),
+                partitions: drive.partitions.len(),
+            });
+        }
+        scan::collect_usage(&mut drives, &self.statvfs, self.timeout, &mut report);
+        drives
+    }
+}
+
+impl Default for Sources {
+    fn default() -> Sources {
+        Sources::system()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::Partition;
+
+    fn drive(mountpoints: &[&str]) -> Vec<Drive> {
+        let partitions = mountpoints
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                let mount = Mount::new(path, "ext4", &["rw"]);
+                Partition::new(format!("sda/sda{}", i + 1), 2048, Some(&mount))
+            })
+            .collect();
+        vec![Drive::new("sda", 4096, partitions)]
+    }
+
+    #[test]
+    fn usage_comes_from_the_injected_provider() {
+        let mut drives = drive(&["/data"]);
+        let provider: Arc<dyn StatvfsProvider> = Arc::new(|mountpoint: &str| match mountpoint {
+            "/data" => Ok(FsStat {
+                blocks: 256,
+                blocks_free: 64,
+                block_size: 4096,
+                files: 100,
+                files_free: 40,
+            }),
+            _ => Err(io::Error::from(io::ErrorKind::NotFound)),
+        });
+        scan::collect_usage(&mut drives, &provider, Duration::from_secs(1), &mut |_| {});
+
+        let partition = &drives[0].partitions()[0];
+        assert_eq!(partition.used_bytes(), Some(192 * 4096));
+        assert_eq!(partition.free_bytes(), Some(64 * 4096));
+    }
+
+    #[test]
+    fn slow_providers_mark_the_mount_stalled() {
+        let mut drives = drive(&["/fast", "/hung"]);
+        let provider: Arc<dyn StatvfsProvider> = Arc::new(|mountpoint: &str| {
+            if mountpoint == "/hung" {
+                thread::sleep(Duration::from_secs(5));
+            }
+            Ok(FsStat {
+                blocks: 10,
+                block_size: 512,
+                ..FsStat::default()
+            })
+        });
+        let mut events = Vec::new();
+        scan::collect_usage(&mut drives, &provider, Duration::from_millis(200), &mut |e| {
+            events.push(e)
+        });
+
+        let [fast, hung] = drives[0].partitions() else {
+            panic!("expected two partitions");
+        };
+        assert_eq!(fast.used_bytes(), Some(5120));
+        assert_eq!(hung.used_bytes(), None);
+        assert!(hung.stalled);
+        assert!(
+            matches!(hung.usage_error(), Some(PrettyblkError::Statvfs { mountpoint, .. }) if mountpoint == "/hung")
+        );
+        assert_eq!(
+            events,
+            [
+                Progress::Probing { mounts: 2 },
+                Progress::Probed {
+                    mountpoint: "/fast".to_string()
+                },
+                Progress::Warning("statvfs on /hung timed out after 0.2s".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    fs::{self, read_to_string},
+    path::Path,
+};
+
+use crate::Drive;
+
+/// An active swap area as listed in `/proc/swaps`, sizes in bytes.
+pub struct Swap {
+    pub path: String,
+    pub size: u64,
+    pub used: u64,
+}
+
+fn parse_swaps(content: &str) -> Vec<(Swap, bool)> {
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 5 {
+                return None;
+            }
+            let swap = Swap {
+                path: decode_octal_escapes(fields[0]),
+                size: fields[2].parse::<u64>().ok()? * 1024,
+                used: fields[3].parse::<u64>().ok()? * 1024,
+            };
+            Some((swap, fields[1] == "file"))
+        })
+        .collect()
+}
+
+/// Undoes the `\040`-style escaping the kernel applies to paths in procfs.
+pub fn decode_octal_escapes(raw: &str) -> String {
+    let bytes = raw.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes.get(i + 1..i + 4).filter(|_| bytes[i] == b'\\');
+        match escape
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok())
+        {
+            Some(byte) => {
+                out.push(byte);
+                i += 4;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// The `dev_t` of the block device whose sysfs directory is `sysfs_dir`.
+#[cfg(target_os = "linux")]
+pub fn device_number(sysfs_dir: &str) -> Option<u64> {
+    let dev = read_to_string(format!("{}/dev", sysfs_dir)).ok()?;
+    let (major, minor) = dev.trim().split_once(':')?;
+    Some(nix::sys::stat::makedev(major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// Only Linux exposes device numbers through sysfs.
+#[cfg(not(target_os = "linux"))]
+pub fn device_number(_sysfs_dir: &str) -> Option<u64> {
+    None
+}
+
+/// The number of the device holding the file at `path`, comparable with
+/// [`device_number`].
+#[cfg(unix)]
+pub fn file_device(path: &str) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|meta| meta.dev())
+}
+
+/// Windows has no device numbers.
+#[cfg(windows)]
+pub fn file_device(_path: &str) -> Option<u64> {
+    None
+}
+
+/// Attaches active swap partitions and swap files to the partitions that back them.
+pub fn annotate(drives: &mut [Drive]) {
+    let Ok(content) = read_to_string("/proc/swaps") else {
+        return;
+    };
+
+    for (swap, is_file) in parse_swaps(&content) {
+        if is_file {
+            let Some(dev) = file_device(&swap.path) else {
+                continue;
+            };
+            let backing = drives
+                .iter_mut()
+                .flat_map(|d| d.partitions.iter_mut())
+                .find(|p| device_number(&p.sysfs_dir()) == Some(dev));
+            if let Some(partition) = backing {
+                partition.swap_files.push(swap);
+            }
+            continue;
+        }
+
+        let Some(name) = fs::canonicalize(&swap.path)
+            .ok()
+            .and_then(|p| Path::new(&p).file_name().map(|n| n.to_string_lossy().into_owned()))
+        else {
+            continue;
+        };
+        let partition = drives
+            .iter_mut()
+            .flat_map(|d| d.partitions.iter_mut())
+            .find(|p| p.dev_name() == name);
+        if let Some(partition) = partition {
+            partition.fstype = Some("swap".to_string());
+            partition.swap = Some(swap);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::borrow::Cow;
+
+use unicode_width::UnicodeWidthStr;
+
+const FIRST_STRONG_ISOLATE: char = '\u{2068}';
+const POP_DIRECTIONAL_ISOLATE: char = '\u{2069}';
+
+/// Number of terminal columns `text` occupies. CJK characters and most emoji
+/// take two columns, combining marks none.
+pub fn width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+/// Terminal columns of `text` once the color escapes in it are left out.
+pub fn visible_width(text: &str) -> usize {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            plain.push(c);
+            continue;
+        }
+        // Control sequences end with a byte in the `@`..=`~` range
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    width(&plain)
+}
+
+/// Left-aligns `text` in a field of `columns` terminal columns.
+pub fn pad_right(text: &str, columns: usize) -> String {
+    format!("{}{}", text, " ".repeat(columns.saturating_sub(width(text))))
+}
+
+/// Right-aligns `text` in a field of `columns` terminal columns.
+pub fn pad_left(text: &str, columns: usize) -> String {
+    format!("{}{}", " ".repeat(columns.saturating_sub(width(text))), text)
+}
+
+/// Embeddings, overrides, isolates and marks that could leak out of a label
+/// and reorder the rest of the line.
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{200e}' | '\u{200f}' | '\u{061c}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}')
+}
+
+fn is_right_to_left(c: char) -> bool {
+    matches!(c,
+        '\u{0590}'..='\u{08ff}'
+        | '\u{fb1d}'..='\u{fdff}'
+        | '\u{fe70}'..='\u{feff}'
+        | '\u{10800}'..='\u{10fff}'
+        | '\u{1e800}'..='\u{1efff}')
+}
+
+/// Makes a user-controlled label (volume label, mountpoint, device alias)
+/// safe to print next to other columns: stray bidi controls are dropped and
+/// text containing right-to-left script is wrapped in a first-strong isolate
+/// so it cannot reorder the bars and sizes around it. Isolates take no
+/// columns, so widths are unchanged.
+pub fn isolate(text: &str) -> Cow<'_, str> {
+    let has_controls = text.chars().any(is_bidi_control);
+    let has_rtl = text.chars().any(is_right_to_left);
+    if !has_controls && !has_rtl {
+        return Cow::Borrowed(text);
+    }
+    let cleaned: String = text.chars().filter(|&c| !is_bidi_control(c)).collect();
+    match has_rtl {
+        true => Cow::Owned(format!(
+            "{}{}{}",
+            FIRST_STRONG_ISOLATE, cleaned, POP_DIRECTIONAL_ISOLATE
+        )),
+        false => Cow::Owned(cleaned),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: [&str; 6] = ["sda1", "データ", "资料盘", "📦backup", "cafe\u{301}", "mpath😀a"];
+
+    #[test]
+    fn wide_characters_take_two_columns() {
+        assert_eq!(width("sda1"), 4);
+        assert_eq!(width("データ"), 6);
+        assert_eq!(width("📦backup"), 8);
+        assert_eq!(width("cafe\u{301}"), 4);
+    }
+
+    #[test]
+    fn padded_columns_line_up() {
+        let columns = FIXTURES.iter().map(|f| width(f)).max().unwrap();
+        for fixture in FIXTURES {
+            assert_eq!(width(&pad_right(fixture, columns)), columns, "{}", fixture);
+            assert_eq!(width(&pad_left(fixture, columns)), columns, "{}", fixture);
+        }
+    }
+
+    #[test]
+    fn color_escapes_take_no_columns() {
+        assert_eq!(visible_width("\x1b[1;34mDrive:\x1b[0m データ"), 13);
+        assert_eq!(visible_width("\x1b[38;2;152;195;121m██\x1b[0m"), 2);
+    }
+
+    #[test]
+    fn padding_never_truncates() {
+        assert_eq!(pad_right("资料盘", 2), "资料盘");
+        assert_eq!(pad_left("データ", 0), "データ");
+    }
+
+    #[test]
+    fn left_to_right_labels_are_untouched() {
+        assert!(matches!(isolate("/home/データ"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn right_to_left_labels_are_isolated() {
+        assert_eq!(isolate("גיבוי"), "\u{2068}גיבוי\u{2069}");
+        assert_eq!(isolate("/media/نسخة"), "\u{2068}/media/نسخة\u{2069}");
+    }
+
+    #[test]
+    fn embedded_controls_are_removed() {
+        assert_eq!(isolate("evil\u{202e}txt"), "eviltxt");
+        assert_eq!(isolate("\u{2067}שלום\u{2069}x"), "\u{2068}שלוםx\u{2069}");
+    }
+
+    #[test]
+    fn isolation_keeps_columns_aligned() {
+        for label in ["גיבוי", "بيانات", "sda1"] {
+            assert_eq!(width(&isolate(label)), width(label), "{}", label);
+            assert_eq!(width(&pad_right(&isolate(label), 10)), 10, "{}", label);
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::env;
+
+use colored::{Color, Colorize, control::SHOULD_COLORIZE};
+
+use crate::{PALETTE, glyph, severity::Severity};
+
+/// Overall look of the charts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Theme {
+    Default,
+    /// Bright colors, no dimmed text and severities spelled out, for low
+    /// vision and washed-out projectors
+    HighContrast,
+}
+
+/// Partition colors of the high-contrast theme: the bright variants, which
+/// stay readable on black and on a projector's grey.
+const HIGH_CONTRAST_PALETTE: [Color; 5] = [
+    Color::BrightGreen,
+    Color::BrightYellow,
+    Color::BrightCyan,
+    Color::BrightMagenta,
+    Color::BrightWhite,
+];
+
+impl Theme {
+    pub fn parse(value: &str) -> Option<Theme> {
+        match value {
+            "default" => Some(Theme::Default),
+            "high-contrast" => Some(Theme::HighContrast),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Default => "default",
+            Theme::HighContrast => "high-contrast",
+        }
+    }
+
+    /// Partition colors, indexed like [`PALETTE`].
+    pub fn palette(self) -> [Color; 5] {
+        match self {
+            Theme::Default => PALETTE,
+            Theme::HighContrast => HIGH_CONTRAST_PALETTE,
+        }
+    }
+
+    /// Bar color for a severity, see [`Severity::color`].
+    pub fn severity(self, severity: Severity, base: Color) -> Color {
+        match (self, severity) {
+            (Theme::HighContrast, Severity::Warning) => Color::BrightYellow,
+            (Theme::HighContrast, Severity::Critical) => Color::BrightRed,
+            _ => severity.color(base),
+        }
+    }
+
+    /// Secondary text: dimmed, except where dimming would make it unreadable.
+    pub fn faint(self, text: &str) -> String {
+        match self {
+            Theme::Default => text.dimmed().to_string(),
+            Theme::HighContrast => text.to_string(),
+        }
+    }
+
+    /// A label spelling out a severity that colors alone would signal, so it
+    /// survives color blindness and monochrome screens.
+    pub fn marker(self, severity: Severity) -> Option<String> {
+        let label = match (self, severity) {
+            (Theme::Default, _) | (_, Severity::Ok) => return None,
+            (_, Severity::Warning) => " WARNING ".bold().black().on_bright_yellow(),
+            (_, Severity::Critical) => " CRITICAL ".bold().bright_white().on_red(),
+        };
+        Some(label.to_string())
+    }
+}
+
+/// How many colors the terminal can show.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Depth {
+    /// The 8 standard colors and their bright variants
+    Basic,
+    /// The xterm 256-color palette
+    Ansi256,
+    /// 24-bit RGB
+    TrueColor,
+}
+
+impl Depth {
+    pub fn parse(value: &str) -> Option<Depth> {
+        match value {
+            "16" => Some(Depth::Basic),
+            "256" => Some(Depth::Ansi256),
+            "truecolor" => Some(Depth::TrueColor),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Depth::Basic => "16",
+            Depth::Ansi256 => "256",
+            Depth::TrueColor => "truecolor",
+        }
+    }
+
+    /// Guesses the depth from `COLORTERM` and `TERM` the way most terminal
+    /// programs do; there is no reliable way to ask the terminal itself.
+    pub fn detect() -> Depth {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            Depth::TrueColor
+        } else if term.contains("256color") {
+            Depth::Ansi256
+        } else {
+            Depth::Basic
+        }
+    }
+}
+
+/// The RGB shade drawn for a basic color when the terminal can show more,
+/// matching the html and svg export.
+pub fn rgb(color: Color) -> (u8, u8, u8) {
+    match color {
+        Color::Green => (0x98, 0xc3, 0x79),
+        Color::Yellow => (0xe5, 0xc0, 0x7b),
+        Color::Blue => (0x61, 0xaf, 0xef),
+        Color::Magenta => (0xc6, 0x78, 0xdd),
+        Color::Cyan => (0x56, 0xb6, 0xc2),
+        Color::Red => (0xe0, 0x6c, 0x75),
+        Color::BrightGreen => (0x5f, 0xff, 0x5f),
+        Color::BrightYellow => (0xff, 0xff, 0x5f),
+        Color::BrightCyan => (0x5f, 0xff, 0xff),
+        Color::BrightMagenta => (0xff, 0x87, 0xff),
+        Color::BrightRed => (0xff, 0x5f, 0x5f),
+        Color::BrightWhite => (0xff, 0xff, 0xff),
+        Color::TrueColor { r, g, b } => (r, g, b),
+        _ => (0xab, 0xb2, 0xbf),
+    }
+}
+
+/// The nearest entry of the 6×6×6 color cube in the xterm 256-color palette.
+fn ansi256(color: Color) -> u8 {
+    let (r, g, b) = rgb(color);
+    let level = |c: u8| ((c as u16 * 5 + 127) / 255) as u8;
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
+
+/// Draws a usage bar of `width` cells with `filled` of them full, shading
+/// from green through yellow at `warn` percent to red at `critical` percent
+/// so each cell shows how close that much usage is to the limits.
+pub fn gradient_bar(filled: usize, width: usize, warn: f64, critical: f64) -> String {
+    let filled = filled.min(width);
+    let glyphs = glyph::glyphs();
+    let empty = glyphs.free.repeat(width - filled);
+    if !SHOULD_COLORIZE.should_colorize() {
+        return glyphs.used.repeat(filled) + &empty;
+    }
+    let stops = [
+        (0.0, rgb(Color::Green)),
+        (warn, rgb(Color::Yellow)),
+        (critical.max(warn), rgb(Color::Red)),
+    ];
+    let shade = |percent: f64| {
+        let Some(pair) = stops.windows(2).find(|pair| percent < pair[1].0) else {
+            return stops[2].1;
+        };
+        let ((from, a), (to, b)) = (pair[0], pair[1]);
+        let t = (percent - from) / (to - from);
+        let mix = |x: u8, y: u8| (x as f64 + (y as f64 - x as f64) * t).round() as u8;
+        (mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+    };
+
+    let mut out = String::new();
+    for cell in 0..filled {
+        let (r, g, b) = shade((cell as f64 + 0.5) / width as f64 * 100.0);
+        out.push_str(&format!("\x1b[38;2;{};{};{}m{}", r, g, b, glyphs.used));
+    }
+    out.push_str(&format!("\x1b[38;2;92;99;112m{}\x1b[0m", empty));
+    out
+}
+
+/// Colors `text` with `color`, using the richer shade when the terminal
+/// supports one.
+pub fn paint(text: &str, color: Color, depth: Depth) -> String {
+    // colored has no 256-color variant and only emits RGB when COLORTERM
+    // asks for it, so the richer escapes are written here
+    let code = match depth {
+        Depth::Basic => return text.color(color).to_string(),
+        _ if !SHOULD_COLORIZE.should_colorize() => return text.to_string(),
+        Depth::Ansi256 => format!("5;{}", ansi256(color)),
+        Depth::TrueColor => {
+            let (r, g, b) = rgb(color);
+            format!("2;{};{};{}", r, g, b)
+        }
+    };
+    format!("\x1b[38;{}m{}\x1b[0m", code, text)
+}
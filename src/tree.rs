@@ -0,0 +1,39 @@
+use crate::glyph;
+
+/// Whether the entry at `index` is followed by another one at the same
+/// depth before its parent's subtree ends.
+fn has_next_sibling(depths: &[usize], index: usize) -> bool {
+    let depth = depths[index];
+    depths[index + 1..]
+        .iter()
+        .take_while(|&&d| d >= depth)
+        .any(|&d| d == depth)
+}
+
+/// Box-drawing connectors for a pre-order list of nesting depths, e.g.
+/// `["", "├─", "│ └─", "└─"]` for `[0, 1, 2, 1]`. Depth 0 entries hang off
+/// the drive header and get no connector.
+pub fn prefixes(depths: &[usize]) -> Vec<String> {
+    let glyphs = glyph::glyphs();
+    (0..depths.len())
+        .map(|index| {
+            let depth = depths[index];
+            if depth == 0 {
+                return String::new();
+            }
+            let mut prefix = String::new();
+            for level in 1..depth {
+                let ancestor = (0..index).rev().find(|&k| depths[k] == level);
+                prefix.push_str(match ancestor.is_some_and(|k| has_next_sibling(depths, k)) {
+                    true => glyphs.pipe,
+                    false => "  ",
+                });
+            }
+            prefix.push_str(match has_next_sibling(depths, index) {
+                true => glyphs.tee,
+                false => glyphs.last,
+            });
+            prefix
+        })
+        .collect()
+}
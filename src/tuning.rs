@@ -0,0 +1,228 @@
+use std::{
+    fs::{self, read_dir, read_to_string},
+    path::Path,
+    process::Command,
+    time::UNIX_EPOCH,
+};
+
+use colored::*;
+
+use crate::{
+    Drive,
+    clock::{Clock, SystemClock},
+    snapshot::ago,
+    text,
+};
+
+/// Periodic trims older than this are reported as overdue.
+const STALE_TRIM_SECONDS: u64 = 30 * 86_400;
+
+/// Queue settings of one drive from `/sys/block/<drive>/queue`.
+struct Queue {
+    rotational: Option<bool>,
+    discard_granularity: Option<u64>,
+    discard_max: Option<u64>,
+    /// Active scheduler first, then the alternatives
+    schedulers: Vec<String>,
+    nr_requests: Option<u64>,
+    /// Tagged command queue depth of SCSI and SATA devices
+    device_depth: Option<u64>,
+}
+
+fn attribute(drive: &str, name: &str) -> Option<String> {
+    read_to_string(format!("/sys/block/{}/{}", drive, name))
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+fn number(drive: &str, name: &str) -> Option<u64> {
+    attribute(drive, name)?.parse().ok()
+}
+
+impl Queue {
+    fn read(drive: &str) -> Queue {
+        // `none [mq-deadline] kyber` lists the active scheduler in brackets
+        let mut schedulers: Vec<String> = Vec::new();
+        for name in attribute(drive, "queue/scheduler")
+            .unwrap_or_default()
+            .split_whitespace()
+        {
+            match name.strip_prefix('[').and_then(|n| n.strip_suffix(']')) {
+                Some(active) => schedulers.insert(0, active.to_string()),
+                None => schedulers.push(name.to_string()),
+            }
+        }
+        Queue {
+            rotational: number(drive, "queue/rotational").map(|r| r == 1),
+            discard_granularity: number(drive, "queue/discard_granularity"),
+            discard_max: number(drive, "queue/discard_max_bytes"),
+            schedulers,
+            nr_requests: number(drive, "queue/nr_requests"),
+            device_depth: number(drive, "device/queue_depth"),
+        }
+    }
+}
+
+/// Formats a byte count with the largest binary unit it fills, e.g. 4 KiB.
+fn binary(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < units.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match value.fract() {
+        0.0 => format!("{} {}", value, units[unit]),
+        _ => format!("{:.1} {}", value, units[unit]),
+    }
+}
+
+fn fstrim_timer_enabled() -> bool {
+    ["/etc/systemd/system", "/usr/lib/systemd/system"]
+        .iter()
+        .any(|dir| Path::new(dir).join("timers.target.wants/fstrim.timer").exists())
+}
+
+/// The last run of fstrim.service.
+struct Fstrim {
+    /// Unix time it last started
+    last: Option<u64>,
+    failed: bool,
+}
+
+/// Asks systemd about fstrim.service, falling back to the stamp file
+/// systemd keeps for the persistent fstrim.timer when systemctl is missing.
+fn last_fstrim() -> Fstrim {
+    let output = Command::new("systemctl")
+        .args(["show", "fstrim.service", "--timestamp=unix"])
+        .args(["--property=ExecMainStartTimestamp", "--property=Result"])
+        .output();
+    if let Ok(output) = output
+        && output.status.success()
+    {
+        let properties = String::from_utf8_lossy(&output.stdout);
+        let value = |key: &str| {
+            properties
+                .lines()
+                .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+                .map(str::to_string)
+        };
+        return Fstrim {
+            // `@1700000000`, empty when the service never ran since boot
+            last: value("ExecMainStartTimestamp").and_then(|t| t.trim_start_matches('@').parse().ok()),
+            failed: value("Result").is_some_and(|result| result != "success"),
+        };
+    }
+    let stamp = fs::metadata("/var/lib/systemd/timers/stamp-fstrim.timer").and_then(|m| m.modified());
+    Fstrim {
+        last: stamp
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs()),
+        failed: false,
+    }
+}
+
+/// Bytes btrfs has queued for asynchronous discard on the filesystem that
+/// `device` belongs to, when it is mounted with `discard=async`.
+fn btrfs_discardable(device: &str) -> Option<u64> {
+    let filesystem = read_dir("/sys/fs/btrfs")
+        .ok()?
+        .filter_map(Result::ok)
+        .find(|entry| entry.path().join("devices").join(device).exists())?;
+    let bytes = read_to_string(filesystem.path().join("discard/discardable_bytes")).ok()?;
+    bytes.trim().parse().ok()
+}
+
+/// Prints discard support, I/O scheduler and queue depth of every drive,
+/// and how each of its filesystems gets trimmed: online discard, or the
+/// fstrim timer and when that last ran. Solid state drives whose
+/// filesystems are never trimmed are flagged.
+pub fn print(drives: &[Drive]) {
+    let label = |name: &str| format!("  {:10}", name.dimmed());
+    let timer = fstrim_timer_enabled();
+    let fstrim = match timer {
+        true => last_fstrim(),
+        false => Fstrim {
+            last: None,
+            failed: false,
+        },
+    };
+    let now = SystemClock.now();
+    let periodic = match (fstrim.last, fstrim.failed) {
+        (None, _) => "periodic fstrim, not run yet".yellow(),
+        (Some(last), true) => format!("periodic fstrim, failed {} ago", ago(now.saturating_sub(last))).red(),
+        (Some(last), false) if now.saturating_sub(last) > STALE_TRIM_SECONDS => {
+            format!("periodic fstrim, last run {} ago", ago(now.saturating_sub(last))).yellow()
+        }
+        (Some(last), false) => format!("periodic fstrim, last run {} ago", ago(now.saturating_sub(last))).normal(),
+    };
+
+    for drive in drives.iter().filter(|d| d.origin.is_none()) {
+        let queue = Queue::read(&drive.name);
+        let kind = match queue.rotational {
+            Some(true) => "rotational",
+            Some(false) => "solid state",
+            None => "unknown media",
+        };
+        println!(
+            "\n{} {} ({})",
+            "Drive:".bold().blue(),
+            drive.display_name().bold(),
+            kind
+        );
+
+        let discard = match (queue.discard_max, queue.discard_granularity) {
+            (None, _) => "-".to_string(),
+            (Some(0), _) => "not supported".to_string(),
+            (Some(max), Some(granularity)) => {
+                format!("{} granularity, up to {} per request", binary(granularity), binary(max))
+            }
+            (Some(max), None) => format!("up to {} per request", binary(max)),
+        };
+        println!("{} {}", label("discard"), discard);
+
+        let scheduler = match queue.schedulers.split_first() {
+            Some((active, [])) => active.clone(),
+            Some((active, others)) => {
+                format!("{} {}", active, format!("({})", others.join(", ")).dimmed())
+            }
+            None => "-".to_string(),
+        };
+        println!("{} {}", label("scheduler"), scheduler);
+
+        let depth = match (queue.nr_requests, queue.device_depth) {
+            (Some(requests), Some(tags)) => format!("{} requests, device depth {}", requests, tags),
+            (Some(requests), None) => format!("{} requests", requests),
+            (None, Some(tags)) => format!("device depth {}", tags),
+            (None, None) => "-".to_string(),
+        };
+        println!("{} {}", label("queue"), depth);
+
+        for partition in &drive.partitions {
+            let Some(mountpoint) = &partition.mountpoint else {
+                continue;
+            };
+            let online = partition
+                .mount_options
+                .iter()
+                .any(|o| o == "discard" || o.starts_with("discard="));
+            let trim = match (queue.discard_max, online, timer) {
+                (None | Some(0), _, _) => "-".dimmed(),
+                (_, true, _) => match btrfs_discardable(partition.dev_name()) {
+                    Some(pending) => format!("online discard, {} queued", binary(pending)).normal(),
+                    None => "online discard".normal(),
+                },
+                (_, false, true) => periodic.clone(),
+                (_, false, false) if queue.rotational == Some(false) => {
+                    "SSD never trimmed: no discard mount option and fstrim.timer not enabled"
+                        .red()
+                        .bold()
+                }
+                (_, false, false) => "no discard, fstrim.timer not enabled".yellow(),
+            };
+            println!("{} {} {}", label("mount"), text::isolate(mountpoint), trim);
+        }
+    }
+}
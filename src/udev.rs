@@ -0,0 +1,19 @@
+use std::{collections::HashMap, fs::read_to_string};
+
+/// Reads the properties udev recorded for the block device at `sysfs_dir`
+/// (e.g. `/sys/block/sda/sda1`) from its database entry in `/run/udev/data`.
+pub fn properties(sysfs_dir: &str) -> HashMap<String, String> {
+    let Ok(dev) = read_to_string(format!("{}/dev", sysfs_dir)) else {
+        return HashMap::new();
+    };
+    let Ok(content) = read_to_string(format!("/run/udev/data/b{}", dev.trim())) else {
+        return HashMap::new();
+    };
+
+    content
+        .lines()
+        .filter_map(|line| line.strip_prefix("E:"))
+        .filter_map(|prop| prop.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+
+use crate::lang;
+
+/// A unit for displaying byte counts.
+#[derive(Clone, Copy, PartialEq)]
+pub struct SizeUnit {
+    pub divisor: f64,
+    pub label: &'static str,
+}
+
+const SIZE_UNITS: [SizeUnit; 9] = [
+    SizeUnit {
+        divisor: 1.0,
+        label: "B",
+    },
+    SizeUnit {
+        divisor: 1e3,
+        label: "KB",
+    },
+    SizeUnit {
+        divisor: 1e6,
+        label: "MB",
+    },
+    SizeUnit {
+        divisor: 1e9,
+        label: "GB",
+    },
+    SizeUnit {
+        divisor: 1e12,
+        label: "TB",
+    },
+    SizeUnit {
+        divisor: 1024.0,
+        label: "KiB",
+    },
+    SizeUnit {
+        divisor: 1048576.0,
+        label: "MiB",
+    },
+    SizeUnit {
+        divisor: 1073741824.0,
+        label: "GiB",
+    },
+    SizeUnit {
+        divisor: 1099511627776.0,
+        label: "TiB",
+    },
+];
+
+/// What prettyblk has always printed: binary gigabytes labelled "GB".
+pub const DEFAULT_SIZE_UNIT: SizeUnit = SizeUnit {
+    divisor: 1073741824.0,
+    label: "GB",
+};
+
+impl SizeUnit {
+    pub fn parse(name: &str) -> Option<SizeUnit> {
+        SIZE_UNITS
+            .iter()
+            .find(|unit| unit.label.eq_ignore_ascii_case(name))
+            .copied()
+    }
+
+    pub fn value(self, bytes: u64) -> f64 {
+        bytes as f64 / self.divisor
+    }
+
+    /// Formats `bytes` with one decimal, e.g. `12.3 GiB`.
+    pub fn format(self, bytes: u64) -> String {
+        format!("{} {}", lang::number(self.value(bytes), 1), self.label)
+    }
+
+    /// Formats a throughput with one decimal, e.g. `512.0 MB/s`.
+    pub fn format_rate(self, bytes_per_second: f64) -> String {
+        format!("{} {}/s", lang::number(bytes_per_second / self.divisor, 1), self.label)
+    }
+}
+
+impl fmt::Display for SizeUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.label)
+    }
+}
+
+/// Throughput has always been printed in decimal megabytes per second.
+pub const DEFAULT_RATE_UNIT: SizeUnit = SizeUnit {
+    divisor: 1e6,
+    label: "MB",
+};
+
+/// A unit for drive temperatures, which drives report in Celsius.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    /// `C` or `F`, with or without the degree sign.
+    pub fn parse(name: &str) -> Option<TemperatureUnit> {
+        match name.trim_start_matches('°').to_ascii_uppercase().as_str() {
+            "C" | "CELSIUS" => Some(TemperatureUnit::Celsius),
+            "F" | "FAHRENHEIT" => Some(TemperatureUnit::Fahrenheit),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "C",
+            TemperatureUnit::Fahrenheit => "F",
+        }
+    }
+
+    /// Formats a temperature in degrees Celsius, e.g. `41 °C` or `106 °F`.
+    pub fn format(self, celsius: u64) -> String {
+        match self {
+            TemperatureUnit::Celsius => format!("{} °C", celsius),
+            TemperatureUnit::Fahrenheit => format!("{:.0} °F", celsius as f64 * 1.8 + 32.0),
+        }
+    }
+}
+
+/// Per-column units from the `[units]` config table.
+#[derive(Clone, Copy)]
+pub struct Units {
+    /// Drive and partition capacities
+    pub size: SizeUnit,
+    /// Used and free space
+    pub used: SizeUnit,
+    /// Throughput, per second
+    pub rate: SizeUnit,
+    /// Drive temperatures
+    pub temperature: TemperatureUnit,
+}
+
+impl Default for Units {
+    fn default() -> Units {
+        Units {
+            size: DEFAULT_SIZE_UNIT,
+            used: DEFAULT_SIZE_UNIT,
+            rate: DEFAULT_RATE_UNIT,
+            temperature: TemperatureUnit::Celsius,
+        }
+    }
+}
+
+impl Units {
+    /// Formats a used/total pair, sharing the unit label when both columns agree.
+    pub fn used_of(&self, used: u64, total: u64) -> String {
+        if self.used == self.size {
+            let (used, total) = (
+                lang::number(self.used.value(used), 1),
+                lang::number(self.size.value(total), 1),
+            );
+            format!("{} / {} {}", used, total, self.size)
+        } else {
+            format!("{} / {}", self.used.format(used), self.size.format(total))
+        }
+    }
+}
+
+/// Parses a percentage like `50%` or `12.5`, between 0 and 100.
+pub fn parse_percent(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let number: f64 = text.strip_suffix('%').unwrap_or(text).trim().parse().ok()?;
+    (0.0..=100.0).contains(&number).then_some(number)
+}
+
+/// Parses sizes like `50M`, `1.5GiB`, `2T` or `4096`. Single-letter and
+/// `iB` suffixes are powers of 1024, `KB`/`MB`/... are powers of 1000.
+pub fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let (number, suffix) = text.split_at(split);
+    let number: f64 = number.parse().ok()?;
+
+    let multiplier = match suffix.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1.0,
+        "K" | "KIB" => 1024.0,
+        "M" | "MIB" => 1024f64.powi(2),
+        "G" | "GIB" => 1024f64.powi(3),
+        "T" | "TIB" => 1024f64.powi(4),
+        "P" | "PIB" => 1024f64.powi(5),
+        "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        "PB" => 1e15,
+        _ => return None,
+    };
+    Some((number * multiplier).round() as u64)
+}
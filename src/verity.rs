@@ -0,0 +1,106 @@
+//! dm-verity maps, which check every block read from a read-only data
+//! device against a hash tree, as embedded and Android images use for
+//! their system partitions.
+
+use std::fs::read_to_string;
+
+use crate::{
+    Drive,
+    platform::linux::{block_name, dm_device, dm_maps, dmsetup, sysfs_links},
+};
+
+#[derive(Clone)]
+pub struct Verity {
+    /// Kernel name of the device holding the data
+    pub data: String,
+    /// Kernel name of the device holding the hash tree, often the data
+    /// device itself; unknown when dmsetup did not answer
+    pub hash: Option<String>,
+    /// Whether a block failed verification since the map was set up;
+    /// `None` when only the map's UUID gave it away as a verity map
+    pub corrupted: Option<bool>,
+}
+
+/// One map from its `dmsetup table` line,
+/// `name: start length verity version data_dev hash_dev ...`, and the
+/// status line ending in `V` while verified or `C` after corruption.
+fn parse(table: &str, status: &str) -> Option<(String, Verity)> {
+    let fields: Vec<&str> = table.split_whitespace().collect();
+    let device = dm_device(fields.first()?.strip_suffix(':')?)?;
+    let verity = Verity {
+        data: block_name(fields.get(5)?)?,
+        hash: block_name(fields.get(6)?),
+        corrupted: match status.split_whitespace().nth(4) {
+            Some("C") => Some(true),
+            Some("V") => Some(false),
+            _ => None,
+        },
+    };
+    Some((device, verity))
+}
+
+/// Verity maps from dmsetup, which needs root.
+fn from_dmsetup() -> Option<Vec<(String, Verity)>> {
+    let (tables, statuses) = (dmsetup("table", "verity")?, dmsetup("status", "verity")?);
+    let maps = tables.lines().filter_map(|table| {
+        let map = table.split_whitespace().next()?;
+        let status = statuses
+            .lines()
+            .find(|line| line.split_whitespace().next() == Some(map))?;
+        parse(table, status)
+    });
+    Some(maps.collect())
+}
+
+/// Verity maps set up by veritysetup or systemd, whose UUIDs start with
+/// `CRYPT-VERITY-`, for users who cannot run dmsetup. The data device is
+/// the first one the map is built on.
+fn from_sysfs() -> Vec<(String, Verity)> {
+    dm_maps()
+        .into_iter()
+        .filter(|(device, _)| {
+            read_to_string(format!("/sys/block/{}/dm/uuid", device)).is_ok_and(|uuid| uuid.starts_with("CRYPT-VERITY-"))
+        })
+        .filter_map(|(device, _)| {
+            let data = sysfs_links(&format!("/sys/block/{}/slaves", device))
+                .into_iter()
+                .next()?;
+            Some((
+                device,
+                Verity {
+                    data,
+                    hash: None,
+                    corrupted: None,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Attaches each verity map to its row, and marks the rows of the devices
+/// holding its data and hash tree.
+pub fn annotate(drives: &mut [Drive]) {
+    if !drives
+        .iter()
+        .flat_map(|d| &d.partitions)
+        .any(|p| p.dev_name().starts_with("dm-"))
+    {
+        return;
+    }
+    let maps = from_dmsetup().unwrap_or_else(from_sysfs);
+    for partition in drives.iter_mut().flat_map(|d| d.partitions.iter_mut()) {
+        let name = partition.dev_name().to_string();
+        for (device, verity) in &maps {
+            if *device == name {
+                partition.verity = Some(verity.clone());
+            }
+            let (data, hash) = (verity.data == name, verity.hash.as_deref() == Some(&name));
+            partition.verity_backing = match (data, hash) {
+                (true, true) => Some("verity data + hashes"),
+                (true, false) => Some("verity data"),
+                (false, true) => Some("verity hash tree"),
+                (false, false) => continue,
+            };
+        }
+    }
+}
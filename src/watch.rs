@@ -0,0 +1,413 @@
+use std::{
+    fs,
+    io::{self, IsTerminal, Write},
+    mem,
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+#[cfg(unix)]
+use std::{
+    io::Read,
+    os::fd::{AsRawFd, RawFd},
+    sync::atomic::{AtomicI32, Ordering},
+};
+
+#[cfg(unix)]
+use nix::{
+    poll::{PollFd, PollFlags, poll},
+    sys::termios::{LocalFlags, SetArg, SpecialCharacterIndices, Termios, tcgetattr, tcsetattr},
+    unistd::{close, dup, dup2, read},
+};
+#[cfg(unix)]
+use terminal_size::terminal_size_using_fd;
+use terminal_size::{Height, Width};
+
+use crate::power;
+
+#[cfg(unix)]
+const STDOUT: RawFd = 1;
+#[cfg(unix)]
+const STDERR: RawFd = 2;
+
+/// The terminal stdout pointed at before a frame started being captured,
+/// -1 outside a capture.
+#[cfg(unix)]
+static TERMINAL: AtomicI32 = AtomicI32::new(-1);
+
+/// Size of the terminal while stdout is redirected into a frame.
+#[cfg(unix)]
+pub fn terminal_size() -> Option<(Width, Height)> {
+    match TERMINAL.load(Ordering::Relaxed) {
+        -1 => None,
+        fd => terminal_size_using_fd(fd),
+    }
+}
+
+/// Frames are not captured on Windows, so stdout stays the terminal.
+#[cfg(windows)]
+pub fn terminal_size() -> Option<(Width, Height)> {
+    None
+}
+
+/// Disk traffic above this many 512-byte sectors per second counts as busy.
+const BUSY_SECTORS_PER_SECOND: u64 = 2048;
+
+/// Calls `tick` until the process is interrupted or q is pressed, about
+/// every `interval`: faster while the screen or disk traffic keeps
+/// changing, slower while nothing happens, so an idle laptop is not woken
+/// up for nothing. On battery every wait is stretched by `battery_slowdown`.
+/// A key press redraws at once, with the keys pressed passed to `tick`.
+pub fn run(interval: Duration, battery_slowdown: f64, mut tick: impl FnMut(&mut Screen, &[u8])) -> ! {
+    let mut screen = Screen::new();
+    let mut pace = Pace::new(interval);
+    let mut last = Instant::now();
+    let keys = Keys::new();
+    let mut pressed = Vec::new();
+    loop {
+        tick(&mut screen, &pressed);
+        let changed = mem::take(&mut screen.changed);
+        let wait = pace.next(changed, sectors_transferred(), last.elapsed());
+        last = Instant::now();
+        pressed = match power::on_battery() {
+            true => {
+                keys.wait(Duration::try_from_secs_f64(wait.as_secs_f64() * battery_slowdown).unwrap_or(Duration::MAX))
+            }
+            false => keys.wait(wait),
+        };
+        // Ctrl+C arrives as a key while the terminal is in key-by-key mode
+        if pressed.iter().any(|&key| key == b'q' || key == 0x03) {
+            // Restores the terminal; nothing was changed on Windows
+            #[cfg_attr(windows, allow(clippy::drop_non_drop))]
+            drop(keys);
+            screen.leave();
+            std::process::exit(0);
+        }
+    }
+}
+
+/// Whether keys can be read while the charts are up. The Windows console
+/// has no key-by-key mode that works like termios, so never there.
+pub fn interactive() -> bool {
+    cfg!(unix) && io::stdin().is_terminal()
+}
+
+/// The keyboard while watching. The terminal hands over each key as it is
+/// pressed, without echoing it, until this is dropped.
+#[cfg(unix)]
+struct Keys {
+    /// The terminal settings to restore; `None` when stdin is no terminal
+    saved: Option<Termios>,
+}
+
+#[cfg(unix)]
+impl Keys {
+    fn new() -> Keys {
+        let stdin = io::stdin();
+        let Some(saved) = interactive().then(|| tcgetattr(&stdin).ok()).flatten() else {
+            return Keys { saved: None };
+        };
+        let mut keys = saved.clone();
+        keys.local_flags
+            .remove(LocalFlags::ICANON | LocalFlags::ECHO | LocalFlags::ISIG);
+        keys.control_chars[SpecialCharacterIndices::VMIN as usize] = 1;
+        keys.control_chars[SpecialCharacterIndices::VTIME as usize] = 0;
+        match tcsetattr(&stdin, SetArg::TCSANOW, &keys) {
+            Ok(()) => Keys { saved: Some(saved) },
+            Err(_) => Keys { saved: None },
+        }
+    }
+
+    /// Waits `timeout` or until keys are pressed, and returns them.
+    fn wait(&self, timeout: Duration) -> Vec<u8> {
+        if self.saved.is_none() {
+            thread::sleep(timeout);
+            return Vec::new();
+        }
+        let stdin = io::stdin();
+        let mut fds = [PollFd::new(&stdin, PollFlags::POLLIN)];
+        let millis = timeout.as_millis().min(i32::MAX as u128) as i32;
+        if !poll(&mut fds, millis).is_ok_and(|ready| ready > 0) {
+            return Vec::new();
+        }
+        let mut buffer = [0; 64];
+        let count = read(stdin.as_raw_fd(), &mut buffer).unwrap_or(0);
+        buffer[..count].to_vec()
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Keys {
+    fn drop(&mut self) {
+        if let Some(saved) = &self.saved {
+            let _ = tcsetattr(io::stdin(), SetArg::TCSANOW, saved);
+        }
+    }
+}
+
+#[cfg(windows)]
+struct Keys;
+
+#[cfg(windows)]
+impl Keys {
+    fn new() -> Keys {
+        Keys
+    }
+
+    fn wait(&self, timeout: Duration) -> Vec<u8> {
+        thread::sleep(timeout);
+        Vec::new()
+    }
+}
+
+/// The refresh interval, eased between a quarter and four times the
+/// requested one.
+struct Pace {
+    current: Duration,
+    fastest: Duration,
+    slowest: Duration,
+    sectors: Option<u64>,
+}
+
+impl Pace {
+    fn new(interval: Duration) -> Pace {
+        Pace {
+            current: interval,
+            fastest: (interval / 4).max(Duration::from_millis(250)).min(interval),
+            slowest: interval.saturating_mul(4),
+            sectors: None,
+        }
+    }
+
+    /// How long to wait before the next refresh, given whether the last one
+    /// changed anything and the disk traffic since the one before.
+    fn next(&mut self, changed: bool, sectors: Option<u64>, elapsed: Duration) -> Duration {
+        let moved = match (self.sectors, sectors) {
+            (Some(before), Some(now)) => now.saturating_sub(before),
+            _ => 0,
+        };
+        self.sectors = sectors;
+        let rate = moved as f64 / elapsed.as_secs_f64().max(0.001);
+        let busy = rate > BUSY_SECTORS_PER_SECOND as f64;
+
+        self.current = match changed || busy {
+            true => (self.current / 2).max(self.fastest),
+            false => (self.current * 3 / 2).min(self.slowest),
+        };
+        self.current
+    }
+}
+
+/// Sectors read and written by all whole disks since boot, from
+/// `/proc/diskstats`; `None` where that does not exist.
+fn sectors_transferred() -> Option<u64> {
+    let stats = fs::read_to_string("/proc/diskstats").ok()?;
+    let total = stats
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            // Partitions would count their traffic a second time
+            let name = fields.get(2)?;
+            if !Path::new(&format!("/sys/block/{}", name)).exists() {
+                return None;
+            }
+            Some(fields.get(5)?.parse::<u64>().ok()? + fields.get(9)?.parse::<u64>().ok()?)
+        })
+        .sum();
+    Some(total)
+}
+
+/// Redraws the terminal with each frame, rewriting only the rows that differ
+/// from the previous frame so slow terminals and ssh sessions do not flicker.
+/// When stdout is not a terminal, and on Windows, frames are printed one
+/// after another.
+pub struct Screen {
+    tty: bool,
+    rows: Vec<String>,
+    #[cfg_attr(windows, allow(dead_code))]
+    size: Option<(Width, Height)>,
+    /// The last frame differed from the one before it
+    changed: bool,
+}
+
+impl Screen {
+    fn new() -> Screen {
+        let tty = cfg!(unix) && io::stdout().is_terminal();
+        if tty {
+            // colored decides on first use whether stdout is a terminal;
+            // make it decide before stdout turns into a pipe
+            colored::control::SHOULD_COLORIZE.should_colorize();
+        }
+        Screen {
+            tty,
+            rows: Vec::new(),
+            size: None,
+            changed: false,
+        }
+    }
+
+    /// Runs `render`, which prints a whole frame, and puts what it printed
+    /// on screen. Warnings printed while rendering go below the frame.
+    pub fn frame<T>(&mut self, render: impl FnOnce() -> T) -> T {
+        if !self.tty {
+            let value = render();
+            let _ = io::stdout().flush();
+            return value;
+        }
+        self.capture(render)
+    }
+
+    /// Never reached, since `tty` is never set on Windows.
+    #[cfg(windows)]
+    fn capture<T>(&mut self, render: impl FnOnce() -> T) -> T {
+        render()
+    }
+
+    #[cfg(unix)]
+    fn capture<T>(&mut self, render: impl FnOnce() -> T) -> T {
+        let terminal = dup(STDOUT).ok();
+        let size = terminal.and_then(terminal_size_using_fd);
+        let (value, output, warnings) = match terminal {
+            Some(terminal) => {
+                TERMINAL.store(terminal, Ordering::Relaxed);
+                let (value, output, warnings) = capture(render);
+                TERMINAL.store(-1, Ordering::Relaxed);
+                let _ = close(terminal);
+                (value, output, warnings)
+            }
+            None => (render(), String::new(), String::new()),
+        };
+
+        let mut rows: Vec<String> = output.lines().chain(warnings.lines()).map(String::from).collect();
+        if let Some((_, Height(height))) = size {
+            rows.truncate(height as usize);
+        }
+        self.paint(rows, size);
+        value
+    }
+
+    /// Moves the cursor below the last frame, for the shell prompt.
+    fn leave(&self) {
+        if self.tty {
+            print!("\x1b[{};1H", self.rows.len() + 1);
+            let _ = io::stdout().flush();
+        }
+    }
+
+    #[cfg_attr(windows, allow(dead_code))]
+    fn paint(&mut self, rows: Vec<String>, size: Option<(Width, Height)>) {
+        let mut out = String::new();
+        // A resize reflows everything on screen, so start over
+        let redraw = self.size != size;
+        if redraw {
+            out.push_str("\x1b[H\x1b[2J");
+        }
+        for (i, row) in rows.iter().enumerate() {
+            if redraw || self.rows.get(i) != Some(row) {
+                out.push_str(&format!("\x1b[{};1H{}\x1b[K", i + 1, row));
+            }
+        }
+        if rows.len() < self.rows.len() {
+            out.push_str(&format!("\x1b[{};1H\x1b[J", rows.len() + 1));
+        }
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(out.as_bytes());
+        let _ = stdout.flush();
+        self.changed = redraw || rows != self.rows;
+        self.rows = rows;
+        self.size = size;
+    }
+}
+
+/// Runs `render` with stdout and stderr redirected into pipes and returns
+/// what it wrote to each.
+#[cfg(unix)]
+fn capture<T>(render: impl FnOnce() -> T) -> (T, String, String) {
+    let _ = io::stdout().flush();
+    let Ok(out) = Redirect::start(STDOUT) else {
+        return (render(), String::new(), String::new());
+    };
+    let err = Redirect::start(STDERR).ok();
+    let value = render();
+    let _ = io::stdout().flush();
+    (value, out.finish(), err.map(Redirect::finish).unwrap_or_default())
+}
+
+/// Runs `render` with only stdout redirected into a pipe, while
+/// `terminal_size` still reports the terminal's, and returns what it
+/// printed. The pager uses this to find out whether the charts fit.
+#[cfg(unix)]
+pub fn capture_stdout<T>(render: impl FnOnce() -> T) -> (T, String) {
+    let _ = io::stdout().flush();
+    let Ok(terminal) = dup(STDOUT) else {
+        return (render(), String::new());
+    };
+    let Ok(out) = Redirect::start(STDOUT) else {
+        let _ = close(terminal);
+        return (render(), String::new());
+    };
+    TERMINAL.store(terminal, Ordering::Relaxed);
+    let value = render();
+    let _ = io::stdout().flush();
+    TERMINAL.store(-1, Ordering::Relaxed);
+    let _ = close(terminal);
+    (value, out.finish())
+}
+
+/// File descriptor `fd` pointed into a pipe that a thread drains, so output
+/// larger than the pipe buffer cannot block the renderer.
+#[cfg(unix)]
+struct Redirect {
+    fd: RawFd,
+    saved: RawFd,
+    reader: thread::JoinHandle<String>,
+}
+
+#[cfg(unix)]
+impl Redirect {
+    fn start(fd: RawFd) -> io::Result<Redirect> {
+        let (mut reader, writer) = io::pipe()?;
+        let saved = dup(fd)?;
+        if let Err(e) = dup2(writer.as_raw_fd(), fd) {
+            let _ = close(saved);
+            return Err(e.into());
+        }
+        drop(writer);
+        let reader = thread::spawn(move || {
+            let mut bytes = Vec::new();
+            let _ = reader.read_to_end(&mut bytes);
+            String::from_utf8_lossy(&bytes).into_owned()
+        });
+        Ok(Redirect { fd, saved, reader })
+    }
+
+    /// Points `fd` back where it was and returns everything written to it.
+    fn finish(self) -> String {
+        let _ = dup2(self.saved, self.fd);
+        let _ = close(self.saved);
+        self.reader.join().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pace_eases_between_bounds() {
+        let second = Duration::from_secs(1);
+        let mut pace = Pace::new(Duration::from_secs(4));
+        let idle: Vec<u64> = (0..6)
+            .map(|_| pace.next(false, Some(0), second).as_millis() as u64)
+            .collect();
+        assert_eq!(idle, [6000, 9000, 13500, 16000, 16000, 16000]);
+
+        // Heavy disk traffic brings it down to a quarter of the interval
+        let busy: Vec<u64> = (1..=4)
+            .map(|i| pace.next(false, Some(i * 100_000), second).as_millis() as u64)
+            .collect();
+        assert_eq!(busy, [8000, 4000, 2000, 1000]);
+
+        assert_eq!(pace.next(true, Some(400_000), second), Duration::from_secs(1));
+    }
+}
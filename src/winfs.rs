@@ -0,0 +1,57 @@
+//! Partitions formatted by Windows, labelled for dual-boot machines and USB
+//! drives passed between systems: NTFS, exFAT and BitLocker, with whether a
+//! BitLocker volume has been unlocked here.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Windows {
+    Ntfs,
+    Exfat,
+    /// Unlocked once `cryptsetup open --type bitlk` maps it to a device
+    BitLocker {
+        unlocked: bool,
+    },
+}
+
+impl Windows {
+    /// The Windows filesystem `fstype` names, as blkid and [`crate::image`]
+    /// call them, on a partition `holders` are built on.
+    pub fn of(fstype: &str, holders: &[String]) -> Option<Windows> {
+        match fstype {
+            "ntfs" => Some(Windows::Ntfs),
+            "exfat" => Some(Windows::Exfat),
+            "BitLocker" => Some(Windows::BitLocker {
+                unlocked: holders.iter().any(|h| h.ends_with(" (crypt)")),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Shown in place of `unmounted`.
+    pub fn label(self) -> &'static str {
+        match self {
+            Windows::Ntfs => "Windows NTFS",
+            Windows::Exfat => "exFAT",
+            Windows::BitLocker { unlocked: false } => "BitLocker, locked",
+            Windows::BitLocker { unlocked: true } => "BitLocker, unlocked",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bitlocker_counts_as_unlocked_once_mapped() {
+        assert_eq!(
+            Windows::of("BitLocker", &[]).map(Windows::label),
+            Some("BitLocker, locked")
+        );
+        let holders = ["bitlk-data (crypt)".to_string()];
+        assert_eq!(
+            Windows::of("BitLocker", &holders).map(Windows::label),
+            Some("BitLocker, unlocked")
+        );
+        assert_eq!(Windows::of("ext4", &holders), None);
+    }
+}
@@ -0,0 +1,124 @@
+use std::process::Command;
+
+use colored::*;
+
+use crate::{ChartOptions, Drive, compression, describe_savings, glyph, udev};
+
+pub struct Pool {
+    pub name: String,
+    pub size: u64,
+    pub allocated: u64,
+    pub free: u64,
+    pub health: String,
+    /// Kernel names of the leaf vdevs, e.g. `sda1`
+    pub members: Vec<String>,
+    /// What compression saves across the pool's datasets, for --verbose
+    pub compression: Option<compression::Savings>,
+}
+
+fn zpool(args: &[&str]) -> Option<String> {
+    let output = Command::new("zpool").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+/// Leaf vdevs from the `config:` section of `zpool status -PL`.
+fn pool_members(name: &str) -> Vec<String> {
+    let Some(status) = zpool(&["status", "-PL", name]) else {
+        return Vec::new();
+    };
+    status
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("config:"))
+        .filter_map(|line| line.split_whitespace().next())
+        .filter_map(|vdev| vdev.strip_prefix("/dev/"))
+        .map(|vdev| vdev.rsplit('/').next().unwrap_or(vdev).to_string())
+        .collect()
+}
+
+pub fn read_pools() -> Vec<Pool> {
+    let Some(list) = zpool(&["list", "-Hp", "-o", "name,size,alloc,free,health"]) else {
+        return Vec::new();
+    };
+    list.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() < 5 {
+                return None;
+            }
+            Some(Pool {
+                name: fields[0].to_string(),
+                size: fields[1].parse().ok()?,
+                allocated: fields[2].parse().ok()?,
+                free: fields[3].parse().ok()?,
+                health: fields[4].to_string(),
+                members: pool_members(fields[0]),
+                compression: None,
+            })
+        })
+        .collect()
+}
+
+/// Marks partitions carrying a ZFS label, using the pool list when `zpool`
+/// is available and udev's filesystem probe otherwise.
+pub fn annotate(drives: &mut [Drive], pools: &[Pool]) {
+    for drive in drives.iter_mut() {
+        for partition in &mut drive.partitions {
+            let from_pool = pools
+                .iter()
+                .find(|pool| pool.members.iter().any(|m| m == partition.dev_name()))
+                .map(|pool| pool.name.clone());
+            let from_udev = || {
+                let props = udev::properties(&partition.sysfs_dir());
+                match props.get("ID_FS_TYPE").map(String::as_str) {
+                    Some("zfs_member") => Some(props.get("ID_FS_LABEL").cloned().unwrap_or_default()),
+                    _ => None,
+                }
+            };
+
+            if let Some(pool) = from_pool.or_else(from_udev) {
+                partition.fstype = Some("zfs_member".to_string());
+                partition.zfs_pool = Some(pool);
+            }
+        }
+    }
+}
+
+pub fn print_pools(pools: &[Pool], options: &ChartOptions) {
+    let (width, units) = (options.width, &options.units);
+    for pool in pools {
+        let health = match pool.health.as_str() {
+            "ONLINE" => pool.health.green(),
+            "DEGRADED" => pool.health.yellow(),
+            _ => pool.health.red(),
+        };
+        println!(
+            "\n{} {} ({:.2} {}, {})",
+            "Pool:".bold().blue(),
+            pool.name.bold(),
+            units.size.value(pool.size),
+            units.size,
+            health
+        );
+
+        let ratio = (pool.allocated as f64 / pool.size.max(1) as f64).clamp(0.0, 1.0);
+        let filled = ((ratio * width as f64).round() as usize).min(width);
+        println!(
+            "[{}{}]",
+            glyph::glyphs().used.repeat(filled).color(Color::Cyan),
+            glyph::glyphs().free.repeat(width - filled)
+        );
+        println!(
+            "  {} used, {} free across {} vdevs: {}",
+            units.used.format(pool.allocated),
+            units.used.format(pool.free),
+            pool.members.len(),
+            pool.members.join(", ")
+        );
+        if let Some(savings) = &pool.compression {
+            println!("  {}", options.theme.faint(&describe_savings(savings, units)));
+        }
+    }
+}